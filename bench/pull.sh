@@ -0,0 +1,60 @@
+#!/usr/bin/env bash
+# Benchmark 'super pull' on a generated super repo with local submodules.
+#
+# Usage: bench/pull.sh [number of submodules] <super binary>...
+#
+# Pass several binaries to compare them, e.g. a build from before and after a change:
+#
+#   bench/pull.sh 100 ./super-before ./target/release/super
+#
+# If 'hyperfine' is installed it is used for the measurements, otherwise we fall back to 'time'.
+# Every binary is also run under 'strace' (if available) to count the spawned subprocesses.
+
+set -euo pipefail
+
+count=20
+if [[ $# -gt 0 && $1 =~ ^[0-9]+$ ]]; then
+  count=$1
+  shift
+fi
+
+if [[ $# -eq 0 ]]; then
+  echo "Usage: $0 [number of submodules] <super binary>..."
+  exit 1
+fi
+
+workdir=$(mktemp -d)
+trap 'rm -rf "$workdir"' EXIT
+
+export GIT_AUTHOR_NAME=bench GIT_AUTHOR_EMAIL=bench@example.com
+export GIT_COMMITTER_NAME=bench GIT_COMMITTER_EMAIL=bench@example.com
+
+echo "Creating a super repo with $count submodules in $workdir"
+
+git init --quiet "$workdir/super"
+for i in $(seq 1 "$count"); do
+  remote="$workdir/remotes/repo$i"
+  git init --quiet --initial-branch=master "$remote"
+  git -C "$remote" commit --quiet --allow-empty -m "Initial commit"
+  git -C "$workdir/super" -c protocol.file.allow=always submodule --quiet add "$remote" "repo$i"
+done
+git -C "$workdir/super" commit --quiet -m "Add submodules"
+
+cd "$workdir/super"
+
+for binary in "$@"; do
+  binary=$(realpath "$binary")
+  echo
+  echo "== $binary"
+
+  if command -v strace >/dev/null; then
+    processes=$(strace -f -e trace=execve -o /dev/stdout "$binary" pull 2>/dev/null | grep -c 'execve(')
+    echo "Spawned processes: $processes"
+  fi
+
+  if command -v hyperfine >/dev/null; then
+    hyperfine --warmup 1 "$binary pull"
+  else
+    time "$binary" pull >/dev/null
+  fi
+done
@@ -0,0 +1,17 @@
+use std::process::Command;
+
+// Embed the hash of the commit that 'super' is built from, so that 'super version' can show it. Nix
+// builds from a source tarball without a .git directory, so the hash is optional.
+fn main() {
+    let hash = Command::new("git")
+        .args(["rev-parse", "--short", "HEAD"])
+        .output()
+        .ok()
+        .filter(|output| output.status.success())
+        .map(|output| String::from_utf8_lossy(&output.stdout).trim().to_string())
+        .unwrap_or_else(|| "unknown".to_string());
+
+    println!("cargo:rustc-env=SUPER_GIT_HASH={}", hash);
+    println!("cargo:rerun-if-changed=.git/HEAD");
+    println!("cargo:rerun-if-changed=.git/refs");
+}
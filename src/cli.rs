@@ -0,0 +1,1065 @@
+//! The command line of super: the options of each command, and how they are parsed from the
+//! arguments
+
+use crate::get_group;
+use crate::relative_to_root;
+use crate::suggest_repo_names;
+use crate::HeadState;
+use std::env;
+use std::fs;
+use std::io::IsTerminal;
+use std::path::Path;
+use std::path::PathBuf;
+use std::time::Duration;
+
+/// A command of super with its arguments, as parsed from the command line
+pub(crate) enum Subcommand {
+    Status(StatusOptions),
+    Init(Vec<String>),
+    Add {
+        repo_path: String,
+        branch_from_head: bool,
+    },
+    /// 'super add --no-clone', which adds a repo that is already cloned
+    AddExisting {
+        repo_path: String,
+        dir: String,
+    },
+    /// 'super add --all', which adds all repos in a directory
+    AddAll(String),
+    Pull(PullOptions),
+    Fetch(PullOptions),
+    Sync,
+    Branch(BranchOptions),
+    Undo(UndoOptions),
+    Reset(ResetOptions),
+    Clean(CleanOptions),
+    Version,
+    Upgrade {
+        yes: bool,
+    },
+    Open(String),
+    /// 'super open --shell-init', which prints the shell function that changes to a repo
+    ShellInit,
+    Diff,
+    Commit(CommitOptions),
+    Stash(StashOptions),
+    Tag(TagOptions),
+    Log(LogOptions),
+    PruneGone {
+        force: bool,
+    },
+    Mv {
+        from: String,
+        to: String,
+    },
+    Config {
+        name: String,
+        key: String,
+        value: Option<String>,
+    },
+    Which(String),
+    List {
+        paths: bool,
+    },
+    Watch,
+    Tui,
+    Freeze,
+    Thaw,
+    /// The options of 'super foreach', and the command to run in each repo
+    Foreach(ForeachOptions, Vec<String>),
+    /// 'super --help', which lists all commands
+    Overview,
+    /// 'super help', which prints the whole documentation
+    Documentation,
+    /// 'super help <command>' or 'super <command> --help'
+    CommandHelp(String),
+}
+
+/// The options that 'super pull' accepts
+#[derive(Clone, Default)]
+pub(crate) struct PullOptions {
+    /// How often a failed fetch is retried before the repo is reported as failed
+    pub(crate) retries: u32,
+    /// Stash uncommitted changes before fast-forwarding, and pop them again afterwards
+    pub(crate) autostash: bool,
+    /// Remove remote-tracking branches whose branch was deleted on the remote
+    pub(crate) prune: bool,
+    /// Clone submodules that are not initialized yet, instead of skipping them
+    pub(crate) init: bool,
+    /// Fetch all tags, including the ones that are not on the fetched branch
+    pub(crate) tags: bool,
+    /// Limit the history that is fetched to the given number of commits
+    pub(crate) depth: Option<u32>,
+    /// Fetch the complete history of shallow repos
+    pub(crate) unshallow: bool,
+    /// Set the upstream of branches that have none to the branch of the same name on origin
+    pub(crate) set_upstream: bool,
+    /// Pull one repo after the other instead of all at once, e.g. on a slow connection
+    pub(crate) serial: bool,
+    /// Only fetch the repos whose branch moved on the remote, which 'git ls-remote' tells cheaply
+    pub(crate) only_changed_remote: bool,
+    /// Only fetch, and never fast-forward, which is what 'super fetch' does
+    pub(crate) fetch_only: bool,
+    /// Remove lock files that git left behind, if they are older than STALE_LOCK_AGE
+    pub(crate) force_unlock: bool,
+    /// Measure how long each repo takes, and list the slowest ones at the end, which -v turns on
+    pub(crate) timings: bool,
+    /// Write a report of what the pull changed to this file
+    pub(crate) report: Option<String>,
+    pub(crate) report_format: ReportFormat,
+    pub(crate) repos: RepoSelection,
+}
+
+/// The format of the report that 'super pull --report' writes
+#[derive(Clone, Copy, Default, PartialEq)]
+pub(crate) enum ReportFormat {
+    #[default]
+    Markdown,
+    Html,
+}
+
+impl ReportFormat {
+    pub(crate) fn parse(format: &str) -> Option<ReportFormat> {
+        match format {
+            "markdown" => Some(ReportFormat::Markdown),
+            "html" => Some(ReportFormat::Html),
+            _ => None,
+        }
+    }
+}
+
+/// The options that 'super foreach' accepts
+#[derive(Clone, Default)]
+pub(crate) struct ForeachOptions {
+    /// Run the command as a git subcommand, i.e. prepend 'git' to it
+    pub(crate) git: bool,
+    /// Also run the command in the super repo itself, not just in its submodules
+    pub(crate) include_super: bool,
+    /// Stream the output line by line, with the name of the repo in front of each line
+    pub(crate) prefix_output: bool,
+    /// Run the command through a shell, so that pipes, redirects and globs work
+    pub(crate) shell: bool,
+    /// Run the command in these directories instead of in the submodules, e.g. the projects of a
+    /// monorepo
+    pub(crate) dirs: Vec<String>,
+    pub(crate) repos: RepoSelection,
+    pub(crate) filter: Option<DirtyFilter>,
+    /// Stop starting the command in more repos once it failed in this many. None means that the
+    /// command runs in all repos, no matter how many fail.
+    pub(crate) max_failures: Option<usize>,
+    /// Print the command before it runs in each repo
+    pub(crate) print_cmd: bool,
+    /// Only print the command for each repo, without running it
+    pub(crate) dry_run: bool,
+    /// Only print this many lines of the stdout of each repo. None means that all of it is printed.
+    pub(crate) max_output_lines: Option<usize>,
+    /// The exit codes that count as success, as given to --expect-codes
+    pub(crate) expect_codes: Option<Vec<i32>>,
+    /// Kill the command in a repo if it runs longer than this
+    pub(crate) timeout: Option<Duration>,
+}
+
+impl ForeachOptions {
+    /// The exit codes that count as success. Without --expect-codes, that is only 0.
+    pub(crate) fn expected_codes(&self) -> &[i32] {
+        self.expect_codes.as_deref().unwrap_or(&[0])
+    }
+}
+
+/// The options that 'super status' accepts
+#[derive(Default)]
+pub(crate) struct StatusOptions {
+    pub(crate) repos: RepoSelection,
+    pub(crate) filter: Option<DirtyFilter>,
+    /// Always show how far each repo is ahead and behind of its upstream, e.g. '↑2 ↓1'
+    pub(crate) ahead_behind: bool,
+    /// Fetch each repo first, so that the upstreams are up to date
+    pub(crate) fetch: bool,
+    /// Print a tab-separated line per repo, whose format stays stable across versions
+    pub(crate) porcelain: bool,
+    /// Show separately whether the working tree of each repo is dirty, and whether the super repo
+    /// has a new commit of it that is staged or not staged yet
+    pub(crate) index: bool,
+}
+
+/// The options that 'super branch' accepts
+#[derive(Default)]
+pub(crate) struct BranchOptions {
+    pub(crate) repos: RepoSelection,
+    pub(crate) format: OutputFormat,
+}
+
+/// Which repos a command operates on
+#[derive(Clone, Default)]
+pub(crate) struct RepoSelection {
+    /// The names of the selected repos. If this is empty, all repos are selected, unless the names
+    /// were read from a file.
+    pub(crate) names: Vec<String>,
+    /// The file that the names were read from with --repos-from, and the line number of each name
+    pub(crate) source: Option<(String, Vec<usize>)>,
+    /// Only select the repos in this group, as given to --group
+    pub(crate) group: Option<String>,
+    /// Leave out these repos, no matter which other options select them, as given to --exclude
+    pub(crate) exclude: Vec<String>,
+}
+
+impl RepoSelection {
+    /// Parse a comma-separated list of repo names, as given to --repos
+    pub(crate) fn parse(names: &str) -> RepoSelection {
+        RepoSelection {
+            names: names
+                .split(',')
+                .map(|name| name.trim().to_string())
+                .filter(|name| !name.is_empty())
+                .collect(),
+            source: None,
+            group: None,
+            exclude: vec![],
+        }
+    }
+
+    /// Read the repo names from a file with one name per line, as given to --repos-from. Blank
+    /// lines and lines that start with '#' are ignored. Prints the error and returns None if the
+    /// file can't be read.
+    pub(crate) fn read(path: &str) -> Option<RepoSelection> {
+        let content = match fs::read_to_string(path) {
+            Ok(content) => content,
+            Err(error) => {
+                println!("Failed to read {}: {}", path, error);
+                return None;
+            }
+        };
+
+        let mut names = vec![];
+        let mut lines = vec![];
+        for (index, line) in content.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            names.push(line.to_string());
+            lines.push(index + 1);
+        }
+
+        Some(RepoSelection {
+            names,
+            source: Some((path.to_string(), lines)),
+            group: None,
+            exclude: vec![],
+        })
+    }
+
+    /// Check that all selected repos actually exist, and that the selected group isn't empty.
+    /// Excluding a repo that doesn't exist is harmless, so that only prints a warning.
+    pub(crate) fn validate(&self, all_names: &[&str]) -> Result<(), String> {
+        for name in &self.exclude {
+            if !all_names.contains(&name.as_str()) {
+                // stderr, so that machine-readable output on stdout stays intact
+                eprintln!(
+                    "Warning: excluding an unknown repo: {}{}",
+                    name,
+                    suggest_repo_names(name, all_names)
+                );
+            }
+        }
+
+        if let Some(group) = &self.group {
+            if !all_names.iter().any(|name| get_group(name) == *group) {
+                return Err(format!("No repos are in the group {}", group));
+            }
+        }
+
+        for (index, name) in self.names.iter().enumerate() {
+            if all_names.contains(&name.as_str()) {
+                continue;
+            }
+            return Err(match &self.source {
+                Some((path, lines)) => {
+                    format!(
+                        "Unknown repo in {}, line {}: {}{}",
+                        path,
+                        lines[index],
+                        name,
+                        suggest_repo_names(name, all_names)
+                    )
+                }
+                None => format!(
+                    "Unknown repo: {}{}",
+                    name,
+                    suggest_repo_names(name, all_names)
+                ),
+            });
+        }
+
+        Ok(())
+    }
+
+    pub(crate) fn includes(&self, name: &str) -> bool {
+        if let Some(group) = &self.group {
+            if get_group(name) != *group {
+                return false;
+            }
+        }
+
+        if self.exclude.iter().any(|excluded| excluded == name) {
+            return false;
+        }
+
+        // A file without any names selects no repos at all
+        (self.names.is_empty() && self.source.is_none())
+            || self.names.iter().any(|selected| selected == name)
+    }
+}
+
+/// The output format of commands that support machine-readable output
+#[derive(Clone, Copy, Default, PartialEq)]
+pub(crate) enum OutputFormat {
+    #[default]
+    Human,
+    Json,
+}
+
+impl OutputFormat {
+    pub(crate) fn parse(format: &str) -> Option<OutputFormat> {
+        match format {
+            "human" => Some(OutputFormat::Human),
+            "json" => Some(OutputFormat::Json),
+            _ => None,
+        }
+    }
+}
+
+/// The options that 'super commit' accepts
+#[derive(Default)]
+pub(crate) struct CommitOptions {
+    /// The commit message
+    pub(crate) message: String,
+    /// Append a list of the submodules that moved, and by how many commits, to the message
+    pub(crate) describe: bool,
+}
+
+/// The options that 'super stash' accepts
+#[derive(Default)]
+pub(crate) struct StashOptions {
+    /// Pop the stash entries that 'super stash' made, instead of stashing
+    pub(crate) pop: bool,
+    /// Stash untracked files as well, like 'git stash --include-untracked'
+    pub(crate) include_untracked: bool,
+}
+
+/// The options that 'super tag' accepts
+#[derive(Default)]
+pub(crate) struct TagOptions {
+    /// The name of the tag
+    pub(crate) name: String,
+    /// The message of the tag. Without one, the tag is a lightweight tag.
+    pub(crate) message: Option<String>,
+    /// Push the tag to origin after creating it
+    pub(crate) push: bool,
+    /// Replace tags with the same name, locally and on origin
+    pub(crate) force: bool,
+}
+
+/// The options that 'super log' accepts
+pub(crate) struct LogOptions {
+    /// How many commits are shown, across all repos
+    pub(crate) count: usize,
+    /// Only show commits after this date, in any format that 'git log --since' understands
+    pub(crate) since: Option<String>,
+}
+
+/// Restricts a command to the repos with or without uncommitted changes, as given to --filter
+#[derive(Clone, Copy)]
+pub(crate) enum DirtyFilter {
+    Dirty,
+    Clean,
+}
+
+impl DirtyFilter {
+    pub(crate) fn parse(filter: &str) -> Option<DirtyFilter> {
+        match filter {
+            "dirty" => Some(DirtyFilter::Dirty),
+            "clean" => Some(DirtyFilter::Clean),
+            _ => None,
+        }
+    }
+
+    pub(crate) fn to_str(self) -> &'static str {
+        match self {
+            DirtyFilter::Dirty => "dirty",
+            DirtyFilter::Clean => "clean",
+        }
+    }
+
+    pub(crate) fn matches(self, head: &HeadState) -> bool {
+        match self {
+            DirtyFilter::Dirty => head.changed > 0,
+            DirtyFilter::Clean => head.changed == 0,
+        }
+    }
+}
+
+/// When to color the output, as given to --color
+#[derive(Clone, Copy, Default)]
+pub(crate) enum ColorChoice {
+    Always,
+    /// Color the output if stdout is a terminal, and NO_COLOR is not set
+    #[default]
+    Auto,
+    Never,
+}
+
+impl ColorChoice {
+    pub(crate) fn parse(choice: &str) -> Option<ColorChoice> {
+        match choice {
+            "always" => Some(ColorChoice::Always),
+            "auto" => Some(ColorChoice::Auto),
+            "never" => Some(ColorChoice::Never),
+            _ => None,
+        }
+    }
+
+    pub(crate) fn enabled(self) -> bool {
+        match self {
+            ColorChoice::Always => true,
+            // See https://no-color.org: NO_COLOR only counts if it isn't empty
+            ColorChoice::Auto => {
+                env::var_os("NO_COLOR").map_or(true, |value| value.is_empty())
+                    && std::io::stdout().is_terminal()
+            }
+            ColorChoice::Never => false,
+        }
+    }
+}
+
+/// The options that 'super undo' accepts
+#[derive(Default)]
+pub(crate) struct UndoOptions {
+    /// The repo to reset. This is None if all repos should be reset (--all).
+    pub(crate) repo: Option<String>,
+    /// Discard uncommitted changes
+    pub(crate) force: bool,
+    /// Don't ask for confirmation before discarding changes
+    pub(crate) yes: bool,
+}
+
+/// The options that 'super reset' accepts
+#[derive(Default)]
+pub(crate) struct ResetOptions {
+    /// Also remove untracked files and directories
+    pub(crate) clean: bool,
+    /// Reset repos that are on another branch, or have a detached HEAD
+    pub(crate) force: bool,
+    /// Don't ask for confirmation before resetting
+    pub(crate) yes: bool,
+}
+
+/// The options that 'super clean' accepts
+#[derive(Default)]
+pub(crate) struct CleanOptions {
+    /// Remove the untracked files, instead of only listing them
+    pub(crate) force: bool,
+    /// Remove ignored files as well, like 'git clean -x'
+    pub(crate) ignored: bool,
+    /// Don't ask for confirmation before removing
+    pub(crate) yes: bool,
+}
+
+/// The options that come before the command, and apply to all commands
+#[derive(Default)]
+pub(crate) struct GlobalOptions {
+    /// Log all git commands that are run
+    pub(crate) verbose: bool,
+    /// Answer all confirmation prompts with yes
+    pub(crate) yes: bool,
+    /// The directory to run in instead of the current one, as given to --root
+    pub(crate) root: Option<PathBuf>,
+    pub(crate) color: ColorChoice,
+    /// The file to append a record of each repo result to, as given to --log-file
+    pub(crate) log_file: Option<PathBuf>,
+}
+
+/// Parse the command and its arguments, i.e. everything after the global options. Without a
+/// command, super shows the status. Returns None if the command doesn't exist, or if its arguments
+/// are wrong.
+pub(crate) fn parse_command(
+    args: &[String],
+    global_options: &GlobalOptions,
+    invoked_from: &Path,
+) -> Option<Subcommand> {
+    let Some((command, args)) = args.split_first() else {
+        return Some(Subcommand::Status(StatusOptions::default()));
+    };
+
+    // Every command has a --help, which is checked before its arguments are parsed. Only the
+    // first argument counts, so that e.g. 'super foreach git -h' still runs 'git -h'.
+    if args
+        .first()
+        .is_some_and(|arg| arg == "--help" || arg == "-h")
+    {
+        return Some(Subcommand::CommandHelp(command.clone()));
+    }
+
+    match command.as_str() {
+        "status" => parse_status_options(args).map(Subcommand::Status),
+        "init" => match args {
+            [] => Some(Subcommand::Init(vec![])),
+            [flag, repo_paths @ ..] if flag == "--add" && !repo_paths.is_empty() => {
+                Some(Subcommand::Init(repo_paths.to_vec()))
+            }
+            _ => None,
+        },
+        "add" => match args {
+            [flag, dir] if flag == "--all" || flag == "--recursive" => {
+                Some(Subcommand::AddAll(relative_to_root(invoked_from, dir)))
+            }
+            [flag, repo_path, dir] if flag == "--no-clone" => Some(Subcommand::AddExisting {
+                repo_path: repo_path.clone(),
+                dir: relative_to_root(invoked_from, dir),
+            }),
+            [flag, repo_path] if flag == "--branch-from-head" => Some(Subcommand::Add {
+                repo_path: repo_path.clone(),
+                branch_from_head: true,
+            }),
+            [repo_path] => Some(Subcommand::Add {
+                repo_path: repo_path.clone(),
+                branch_from_head: false,
+            }),
+            _ => None,
+        },
+        // With -v, the time that each repo takes is measured as well
+        "pull" => parse_pull_options(args).map(|options| {
+            Subcommand::Pull(PullOptions {
+                timings: global_options.verbose,
+                ..options
+            })
+        }),
+        "fetch" => parse_fetch_options(args).map(|options| {
+            Subcommand::Fetch(PullOptions {
+                timings: global_options.verbose,
+                ..options
+            })
+        }),
+        "sync" => args.is_empty().then_some(Subcommand::Sync),
+        "branch" => parse_branch_options(args).map(Subcommand::Branch),
+        "undo" => parse_undo_options(args).map(|options| {
+            Subcommand::Undo(UndoOptions {
+                yes: options.yes || global_options.yes,
+                ..options
+            })
+        }),
+        "reset" => parse_reset_options(args).map(|options| {
+            Subcommand::Reset(ResetOptions {
+                yes: options.yes || global_options.yes,
+                ..options
+            })
+        }),
+        "clean" => parse_clean_options(args).map(|options| {
+            Subcommand::Clean(CleanOptions {
+                yes: options.yes || global_options.yes,
+                ..options
+            })
+        }),
+        "version" | "--version" => Some(Subcommand::Version),
+        "upgrade" => match args {
+            [] => Some(Subcommand::Upgrade {
+                yes: global_options.yes,
+            }),
+            [flag] if flag == "-y" || flag == "--yes" => Some(Subcommand::Upgrade { yes: true }),
+            _ => None,
+        },
+        "open" => match args {
+            [flag] if flag == "--shell-init" => Some(Subcommand::ShellInit),
+            [query] => Some(Subcommand::Open(query.clone())),
+            _ => None,
+        },
+        "diff" => args.is_empty().then_some(Subcommand::Diff),
+        "commit" => parse_commit_options(args).map(Subcommand::Commit),
+        "stash" => parse_stash_options(args).map(Subcommand::Stash),
+        "tag" => parse_tag_options(args).map(Subcommand::Tag),
+        "log" => parse_log_options(args).map(Subcommand::Log),
+        "prune-gone" => match args {
+            [] => Some(Subcommand::PruneGone { force: false }),
+            [flag] if flag == "--force" => Some(Subcommand::PruneGone { force: true }),
+            _ => None,
+        },
+        "mv" => match args {
+            [from, to] => Some(Subcommand::Mv {
+                from: from.clone(),
+                to: to.clone(),
+            }),
+            _ => None,
+        },
+        "config" => match args {
+            [name, key, value @ ..] if value.len() <= 1 => Some(Subcommand::Config {
+                name: name.clone(),
+                key: key.clone(),
+                value: value.first().cloned(),
+            }),
+            _ => None,
+        },
+        "which" => match args {
+            [name] => Some(Subcommand::Which(name.clone())),
+            _ => None,
+        },
+        "list" => match args {
+            [] => Some(Subcommand::List { paths: false }),
+            [flag] if flag == "--paths" => Some(Subcommand::List { paths: true }),
+            _ => None,
+        },
+        "watch" => args.is_empty().then_some(Subcommand::Watch),
+        "tui" => args.is_empty().then_some(Subcommand::Tui),
+        "freeze" => args.is_empty().then_some(Subcommand::Freeze),
+        "thaw" => args.is_empty().then_some(Subcommand::Thaw),
+        // Note: all arguments after the options of "super foreach" are interpreted as the
+        // command to run in each submodule.
+        "foreach" => parse_foreach_options(args).map(|(mut options, command)| {
+            // The directories are given relative to where super was started
+            for dir in options.dirs.iter_mut() {
+                *dir = relative_to_root(invoked_from, dir);
+            }
+            Subcommand::Foreach(options, command)
+        }),
+        "help" => match args {
+            [command] => Some(Subcommand::CommandHelp(command.clone())),
+            // Print the docs with usage instructions
+            _ => Some(Subcommand::Documentation),
+        },
+        "--help" | "-h" => Some(Subcommand::Overview),
+        _ => None,
+    }
+}
+
+/// The usage of a command, which is printed if its arguments are wrong. Returns None if there is
+/// no such command.
+pub(crate) fn usage(command: &str) -> Option<&'static str> {
+    let usage = match command {
+        "status" => concat!(
+            "Usage: super status [--repos <names> | --repos-from <file>] [--group <name>]\n",
+            "                    [--exclude <names> | --exclude-from <file>]\n",
+            "                    [--filter (dirty | clean)] [--ahead-behind] [--fetch] [--porcelain]\n",
+            "                    [--index]",
+        ),
+        "init" => "Usage: super init [--add <repo>...]",
+        "add" => concat!(
+            "Usage: super add [--branch-from-head] <repo_path>\n",
+            "       super add --no-clone <repo_path> <dir>\n",
+            "       super add (--all | --recursive) <dir>",
+        ),
+        "pull" => concat!(
+            "Usage: super pull [--retries <n>] [--autostash] [--prune] [--init] [--tags]\n",
+            "                  [--depth <n> | --unshallow] [--set-upstream] [--serial]\n",
+            "                  [--only-changed-remote] [--force-unlock]\n",
+            "                  [--report <path> [--report-format (markdown | html)]]\n",
+            "                  [--repos <names> | --repos-from <file>] [--group <name>]\n",
+            "                  [--exclude <names> | --exclude-from <file>]",
+        ),
+        "fetch" => concat!(
+            "Usage: super fetch [--retries <n>] [--prune] [--tags] [--force-unlock]\n",
+            "                   [--repos <names> | --repos-from <file>] [--group <name>]\n",
+            "                   [--exclude <names> | --exclude-from <file>]",
+        ),
+        "sync" => "Usage: super sync",
+        "branch" => concat!(
+            "Usage: super branch [--repos <names> | --repos-from <file>]\n",
+            "                    [--exclude <names> | --exclude-from <file>] [--format json]",
+        ),
+        "undo" => "Usage: super undo (<repo> | --all) [--force] [--yes]",
+        "reset" => "Usage: super reset --hard [--clean] [--force] [--yes]",
+        "clean" => "Usage: super clean [--force] [-x] [--yes]",
+        "upgrade" => "Usage: super upgrade [--yes]",
+        "open" => "Usage: super open (<repo> | --shell-init)",
+        "diff" => "Usage: super diff",
+        "commit" => "Usage: super commit -m <message> [--describe]",
+        "stash" => "Usage: super stash [pop | --include-untracked]",
+        "tag" => "Usage: super tag <name> [-m <message>] [--push] [--force]",
+        "log" => "Usage: super log [-n <count>] [--since <date>]",
+        "prune-gone" => "Usage: super prune-gone [--force]",
+        "mv" => "Usage: super mv <repo> <new_path>",
+        "config" => "Usage: super config <repo> <key> [<value>]",
+        "which" => "Usage: super which <command>",
+        "list" => "Usage: super list [--paths]",
+        "watch" => "Usage: super watch",
+        "tui" => "Usage: super tui",
+        "freeze" => "Usage: super freeze",
+        "thaw" => "Usage: super thaw",
+        "foreach" => concat!(
+            "Usage: super foreach [--git] [--shell] [--include-super] [--parallel-output-prefix]\n",
+            "                     [--repos <names> | --repos-from <file>] [--group <name>]\n",
+            "                     [--exclude <names> | --exclude-from <file>]\n",
+            "                     [--on-dir <path>]...\n",
+            "                     [--filter (dirty | clean)]\n",
+            "                     [--keep-going | --max-failures <n>]\n",
+            "                     [--print-cmd] [--dry-run] [--max-output-lines <n>]\n",
+            "                     [--expect-codes <codes>] [--timeout <seconds>] <command>",
+        ),
+        _ => return None,
+    };
+    Some(usage)
+}
+
+/// Parse the global options, which come before the command (just like with git), and remove them
+/// from the arguments.
+pub(crate) fn parse_global_options(args: &mut Vec<String>) -> GlobalOptions {
+    let mut options = GlobalOptions::default();
+
+    while args.len() > 1 {
+        match args[1].as_str() {
+            "-v" | "--verbose" => options.verbose = true,
+            "-y" | "--yes" => options.yes = true,
+            "--root" if args.len() > 2 => options.root = Some(PathBuf::from(args.remove(2))),
+            "--log-file" if args.len() > 2 => {
+                options.log_file = Some(PathBuf::from(args.remove(2)))
+            }
+            "--color" if args.len() > 2 => match ColorChoice::parse(&args[2]) {
+                Some(color) => {
+                    options.color = color;
+                    args.remove(2);
+                }
+                None => break,
+            },
+            _ => break,
+        }
+        args.remove(1);
+    }
+
+    options
+}
+
+/// Parse the arguments of 'super pull'. Returns None if an argument is not recognized.
+fn parse_pull_options(args: &[String]) -> Option<PullOptions> {
+    let mut options = PullOptions::default();
+    let mut group = None;
+    let mut exclude = vec![];
+    let mut args = args.iter();
+
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--retries" => options.retries = args.next()?.parse().ok()?,
+            "--autostash" => options.autostash = true,
+            "--prune" => options.prune = true,
+            "--init" => options.init = true,
+            "--tags" => options.tags = true,
+            "--depth" => options.depth = Some(args.next()?.parse().ok()?),
+            "--unshallow" => options.unshallow = true,
+            "--set-upstream" => options.set_upstream = true,
+            "--serial" => options.serial = true,
+            "--only-changed-remote" => options.only_changed_remote = true,
+            "--force-unlock" => options.force_unlock = true,
+            "--report" => options.report = Some(args.next()?.clone()),
+            "--report-format" => options.report_format = ReportFormat::parse(args.next()?)?,
+            "--repos" => options.repos = RepoSelection::parse(args.next()?),
+            "--repos-from" => options.repos = RepoSelection::read(args.next()?)?,
+            "--group" => group = Some(args.next()?.clone()),
+            "--exclude" => exclude = RepoSelection::parse(args.next()?).names,
+            "--exclude-from" => exclude = RepoSelection::read(args.next()?)?.names,
+            _ => return None,
+        }
+    }
+    options.repos.group = group;
+    options.repos.exclude = exclude;
+
+    // git doesn't allow both at the same time either
+    if options.depth.is_some() && options.unshallow {
+        return None;
+    }
+
+    Some(options)
+}
+
+/// Parse the arguments of 'super fetch', which are the options of 'super pull' that affect the
+/// fetch. Returns None if an argument is not recognized.
+fn parse_fetch_options(args: &[String]) -> Option<PullOptions> {
+    let mut options = PullOptions {
+        fetch_only: true,
+        ..PullOptions::default()
+    };
+    let mut group = None;
+    let mut exclude = vec![];
+    let mut args = args.iter();
+
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--retries" => options.retries = args.next()?.parse().ok()?,
+            "--prune" => options.prune = true,
+            "--tags" => options.tags = true,
+            "--force-unlock" => options.force_unlock = true,
+            "--repos" => options.repos = RepoSelection::parse(args.next()?),
+            "--repos-from" => options.repos = RepoSelection::read(args.next()?)?,
+            "--group" => group = Some(args.next()?.clone()),
+            "--exclude" => exclude = RepoSelection::parse(args.next()?).names,
+            "--exclude-from" => exclude = RepoSelection::read(args.next()?)?.names,
+            _ => return None,
+        }
+    }
+    options.repos.group = group;
+    options.repos.exclude = exclude;
+
+    Some(options)
+}
+
+/// Parse the arguments of 'super branch'. Returns None if an argument is not recognized.
+fn parse_branch_options(args: &[String]) -> Option<BranchOptions> {
+    let mut options = BranchOptions::default();
+    let mut exclude = vec![];
+    let mut args = args.iter();
+
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--repos" => options.repos = RepoSelection::parse(args.next()?),
+            "--repos-from" => options.repos = RepoSelection::read(args.next()?)?,
+            "--exclude" => exclude = RepoSelection::parse(args.next()?).names,
+            "--exclude-from" => exclude = RepoSelection::read(args.next()?)?.names,
+            "--format" => options.format = OutputFormat::parse(args.next()?)?,
+            _ => return None,
+        }
+    }
+    options.repos.exclude = exclude;
+
+    Some(options)
+}
+
+/// Parse the arguments of 'super status'
+fn parse_status_options(args: &[String]) -> Option<StatusOptions> {
+    let mut options = StatusOptions::default();
+    let mut group = None;
+    let mut exclude = vec![];
+    let mut args = args.iter();
+
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--repos" => options.repos = RepoSelection::parse(args.next()?),
+            "--repos-from" => options.repos = RepoSelection::read(args.next()?)?,
+            "--group" => group = Some(args.next()?.clone()),
+            "--exclude" => exclude = RepoSelection::parse(args.next()?).names,
+            "--exclude-from" => exclude = RepoSelection::read(args.next()?)?.names,
+            "--filter" => options.filter = Some(DirtyFilter::parse(args.next()?)?),
+            "--ahead-behind" => options.ahead_behind = true,
+            "--porcelain" => options.porcelain = true,
+            "--index" => options.index = true,
+            // Fetching is only useful to compare against the upstream
+            "--fetch" => {
+                options.ahead_behind = true;
+                options.fetch = true;
+            }
+            _ => return None,
+        }
+    }
+    options.repos.group = group;
+    options.repos.exclude = exclude;
+
+    Some(options)
+}
+
+/// Parse the arguments of 'super commit'. Returns None if the arguments are invalid, or if no
+/// message is given.
+fn parse_commit_options(args: &[String]) -> Option<CommitOptions> {
+    let mut options = CommitOptions::default();
+    let mut message = None;
+    let mut args = args.iter();
+
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "-m" | "--message" => message = Some(args.next()?.clone()),
+            "--describe" => options.describe = true,
+            _ => return None,
+        }
+    }
+
+    options.message = message?;
+    Some(options)
+}
+
+/// Parse the arguments of 'super stash'. Returns None if the arguments are invalid.
+fn parse_stash_options(args: &[String]) -> Option<StashOptions> {
+    let mut options = StashOptions::default();
+    let mut args = args.iter().peekable();
+
+    if args.peek().is_some_and(|arg| *arg == "pop") {
+        options.pop = true;
+        args.next();
+    }
+
+    for arg in args {
+        match arg.as_str() {
+            "-u" | "--include-untracked" => options.include_untracked = true,
+            _ => return None,
+        }
+    }
+
+    // Popping restores untracked files anyway, if they were stashed
+    if options.pop && options.include_untracked {
+        return None;
+    }
+
+    Some(options)
+}
+
+/// Parse the arguments of 'super tag'. Returns None if the arguments are invalid.
+fn parse_tag_options(args: &[String]) -> Option<TagOptions> {
+    let mut options = TagOptions::default();
+    let mut name = None;
+    let mut args = args.iter();
+
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "-m" | "--message" => options.message = Some(args.next()?.clone()),
+            "--push" => options.push = true,
+            "--force" => options.force = true,
+            _ if name.is_none() && !arg.starts_with('-') => name = Some(arg.clone()),
+            _ => return None,
+        }
+    }
+
+    options.name = name?;
+    Some(options)
+}
+
+/// Parse the arguments of 'super log'. Returns None if the arguments are invalid.
+fn parse_log_options(args: &[String]) -> Option<LogOptions> {
+    let mut options = LogOptions {
+        count: 10,
+        since: None,
+    };
+    let mut args = args.iter();
+
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "-n" => options.count = args.next()?.parse().ok().filter(|count| *count > 0)?,
+            "--since" => options.since = Some(args.next()?.clone()),
+            _ => return None,
+        }
+    }
+
+    Some(options)
+}
+
+/// Parse the arguments of 'super undo'. Returns None if the arguments are invalid, or if not
+/// exactly one of <repo> and --all is given.
+fn parse_undo_options(args: &[String]) -> Option<UndoOptions> {
+    let mut options = UndoOptions::default();
+    let mut all = false;
+
+    for arg in args {
+        match arg.as_str() {
+            "--all" => all = true,
+            "--force" => options.force = true,
+            "-y" | "--yes" => options.yes = true,
+            _ if arg.starts_with('-') || options.repo.is_some() => return None,
+            _ => options.repo = Some(arg.clone()),
+        }
+    }
+
+    if all == options.repo.is_some() {
+        return None;
+    }
+
+    Some(options)
+}
+
+/// Parse the arguments of 'super reset'. Returns None if the arguments are invalid, or if --hard is
+/// missing, since that is the only kind of reset there is (and it should be spelled out).
+fn parse_reset_options(args: &[String]) -> Option<ResetOptions> {
+    let mut options = ResetOptions::default();
+    let mut hard = false;
+
+    for arg in args {
+        match arg.as_str() {
+            "--hard" => hard = true,
+            "--clean" => options.clean = true,
+            "--force" => options.force = true,
+            "-y" | "--yes" => options.yes = true,
+            _ => return None,
+        }
+    }
+
+    if !hard {
+        return None;
+    }
+
+    Some(options)
+}
+
+/// Parse the arguments of 'super clean'. Returns None if the arguments are invalid.
+fn parse_clean_options(args: &[String]) -> Option<CleanOptions> {
+    let mut options = CleanOptions::default();
+
+    for arg in args {
+        match arg.as_str() {
+            "-f" | "--force" => options.force = true,
+            "-x" => options.ignored = true,
+            "-y" | "--yes" => options.yes = true,
+            _ => return None,
+        }
+    }
+
+    Some(options)
+}
+
+/// Parse the arguments of 'super foreach' into its options and the command to run.
+///
+/// Options have to come before the command: everything from the first argument that is not an
+/// option (or from after a '--') is the command. Returns None if there is no command.
+fn parse_foreach_options(args: &[String]) -> Option<(ForeachOptions, Vec<String>)> {
+    let mut options = ForeachOptions::default();
+    let mut group = None;
+    let mut exclude = vec![];
+    let mut command: Vec<String> = vec![];
+    let mut args = args.iter();
+
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--git" => options.git = true,
+            "--include-super" => options.include_super = true,
+            "--parallel-output-prefix" => options.prefix_output = true,
+            "--shell" => options.shell = true,
+            "--on-dir" => options.dirs.push(args.next()?.clone()),
+            "--repos" => options.repos = RepoSelection::parse(args.next()?),
+            "--repos-from" => options.repos = RepoSelection::read(args.next()?)?,
+            "--group" => group = Some(args.next()?.clone()),
+            "--exclude" => exclude = RepoSelection::parse(args.next()?).names,
+            "--exclude-from" => exclude = RepoSelection::read(args.next()?)?.names,
+            "--filter" => options.filter = Some(DirtyFilter::parse(args.next()?)?),
+            "--max-failures" => {
+                options.max_failures = Some(args.next()?.parse().ok().filter(|max| *max > 0)?)
+            }
+            "--keep-going" => options.max_failures = None,
+            "--print-cmd" => options.print_cmd = true,
+            "--dry-run" => options.dry_run = true,
+            "--timeout" => {
+                let seconds = args.next()?.parse().ok().filter(|seconds| *seconds > 0)?;
+                options.timeout = Some(Duration::from_secs(seconds));
+            }
+            "--max-output-lines" => options.max_output_lines = Some(args.next()?.parse().ok()?),
+            "--expect-codes" => {
+                let codes = args.next()?.split(',').map(|code| code.trim().parse().ok());
+                options.expect_codes = Some(codes.collect::<Option<Vec<i32>>>()?);
+            }
+            "--" => break,
+            _ => {
+                command.push(arg.clone());
+                break;
+            }
+        }
+    }
+    command.extend(args.cloned());
+    if command.is_empty() {
+        return None;
+    }
+
+    // --repos, --group and --exclude select submodules, which --on-dir replaces
+    let selects_repos = !options.repos.names.is_empty() || options.repos.source.is_some();
+    if !options.dirs.is_empty() && (selects_repos || group.is_some() || !exclude.is_empty()) {
+        return None;
+    }
+
+    // Streamed output is never captured, so there is nothing to truncate
+    if options.prefix_output && options.max_output_lines.is_some() {
+        return None;
+    }
+    options.repos.group = group;
+    options.repos.exclude = exclude;
+
+    Some((options, command))
+}
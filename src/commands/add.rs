@@ -0,0 +1,348 @@
+//! 'super add', which adds a repo to the super repo
+
+use crate::confirm;
+use crate::get_default_branch;
+use crate::get_git_repos;
+use crate::git_stdout;
+use crate::print_repo_line;
+use crate::run_git;
+use crate::submodule_name;
+use crate::SuperError;
+use crate::CONFIG_FILE;
+use git2::Repository;
+use std::path::Path;
+use std::path::PathBuf;
+
+/// The shorthands that 'super add' understands without any configuration
+const DEFAULT_SHORTHANDS: [(&str, &str); 2] =
+    [("gh", "git@github.com:"), ("gl", "git@gitlab.com:")];
+
+/// Add a new repo to the super repo
+///
+/// This will add the repo as a submodule and will also initialize it. With branch_from_head, the
+/// default branch of its remote is recorded as the branch that it tracks. Returns whether the repo
+/// was added.
+pub(crate) fn command_add(repo_path: &str, branch_from_head: bool) -> bool {
+    let repo_path = &expand_shorthand(repo_path);
+    let dir = Path::new(default_submodule_path(repo_path));
+
+    // Adding a repo that is already a submodule changes nothing, except that its directory may
+    // still have to be initialized, e.g. after a partial checkout
+    if let Some((name, path)) = find_existing_submodule(repo_path, dir) {
+        return initialize_existing_submodule(&name, &path);
+    }
+
+    // A repo that was added with 'git add' instead of 'git submodule add' is in the index without
+    // being in .gitmodules, and git would fail with 'already exists in the index'
+    let staged = git_stdout(
+        Path::new("."),
+        &["ls-files", "--stage", "--", &dir.to_string_lossy()],
+    );
+    if staged.is_ok_and(|staged| staged.starts_with("160000")) {
+        println!(
+            "{} is already in the index of the super repo, but not in .gitmodules. Remove it from \
+             the index with 'git rm --cached {}', and add it again.",
+            dir.display(),
+            dir.display()
+        );
+        return false;
+    }
+
+    // git adopts a repo that is already checked out at the path, but fails on anything else there
+    let checked = check_new_submodule(repo_path, dir).and_then(|()| {
+        if dir.exists() && !dir.join(".git").exists() {
+            Err(format!(
+                "{} already exists and is not a git repo",
+                dir.display()
+            ))
+        } else {
+            Ok(())
+        }
+    });
+    if let Err(message) = checked {
+        println!("{}", message);
+        return false;
+    }
+
+    // TODO: We might want to pass along all optional arguments here
+    match run_git(Path::new("."), &["submodule", "add", repo_path]) {
+        Ok(_) => {
+            println!("The submodule {} was added successfully.", repo_path);
+            if branch_from_head {
+                // The repo is added even if this fails, it just tracks no branch yet
+                match track_default_branch(dir) {
+                    Ok(branch) => println!("It tracks the branch {} in .gitmodules.", branch),
+                    Err(error) => println!("Failed to record its default branch. Error: {}", error),
+                }
+            }
+            println!("You probably will want to commit this (along with .gitmodules, if this is the first submodule.");
+            true
+        }
+        Err(error) => {
+            println!("Failed to add the submodule. Error: {}", error);
+            false
+        }
+    }
+}
+
+/// Return the name and path of the submodule that has the given URL or path, if there is one
+fn find_existing_submodule(repo_path: &str, path: &Path) -> Option<(String, PathBuf)> {
+    let repo = Repository::open(".").ok()?;
+    let submodules = repo.submodules().ok()?;
+    let submodule = submodules
+        .iter()
+        .find(|submodule| submodule.url() == Some(repo_path) || submodule.path() == path)?;
+
+    Some((submodule_name(submodule), submodule.path().to_path_buf()))
+}
+
+/// Initialize a submodule that 'super add' was asked to add again, after asking for confirmation.
+/// Submodules that are already initialized are left alone. Returns false if initializing it failed.
+fn initialize_existing_submodule(name: &str, path: &Path) -> bool {
+    if path.join(".git").exists() {
+        println!(
+            "{} is already a submodule at {}, so there is nothing to add",
+            name,
+            path.display()
+        );
+        return true;
+    }
+
+    let prompt = format!(
+        "{} is already a submodule, but {} is not initialized. Initialize it with \
+         'git submodule update --init'?",
+        name,
+        path.display()
+    );
+    // Without a terminal to ask, nothing is changed
+    if !confirm(&prompt).unwrap_or(false) {
+        println!(
+            "Skipped {}, run 'super pull --init' to initialize it later",
+            name
+        );
+        return true;
+    }
+
+    let path = path.to_string_lossy();
+    match run_git(
+        Path::new("."),
+        &["submodule", "update", "--init", "--", &path],
+    ) {
+        Ok(_) => {
+            println!("The submodule {} was initialized successfully.", name);
+            true
+        }
+        Err(error) => {
+            println!("Failed to initialize the submodule. Error: {}", error);
+            false
+        }
+    }
+}
+
+/// Add a repo that is already checked out in the given directory as a submodule, without cloning it
+/// again
+///
+/// The origin of the repo should match the given URL. If it doesn't, we only warn, since it may
+/// e.g. be the same repo with a different protocol.
+pub(crate) fn command_add_existing(repo_path: &str, dir: &str) -> Result<(), SuperError> {
+    let repo_path = &expand_shorthand(repo_path);
+
+    if !Path::new(dir).join(".git").exists() {
+        println!("{} is not a git repo", dir);
+        return Ok(());
+    }
+
+    if let Err(message) = check_new_submodule(repo_path, Path::new(dir)) {
+        println!("{}", message);
+        return Ok(());
+    }
+
+    match git_stdout(Path::new(dir), &["remote", "get-url", "origin"]) {
+        Ok(origin) if origin == *repo_path => (),
+        Ok(origin) => println!(
+            "Warning: the origin of {} is {}, not {}",
+            dir, origin, repo_path
+        ),
+        Err(_) => println!("Warning: {} has no origin remote", dir),
+    }
+
+    // git adopts an existing repo at the path as it is, so nothing is cloned
+    run_git(Path::new("."), &["submodule", "add", repo_path, dir])?;
+
+    println!("The existing repo in {} was added as a submodule.", dir);
+    println!("You probably will want to commit this (along with .gitmodules).");
+
+    Ok(())
+}
+
+/// Record the default branch of the remote of the newly added submodule at the given path as the
+/// branch that it tracks in .gitmodules, and return it. Its origin/HEAD was set by the clone.
+fn track_default_branch(path: &Path) -> Result<String, SuperError> {
+    let branch = get_default_branch(path);
+
+    // 'git submodule add' names the submodule after its path
+    let name = path.to_string_lossy();
+    let mut config = git2::Config::open(Path::new(".gitmodules"))?;
+    config.set_str(&format!("submodule.{}.branch", name), &branch)?;
+    run_git(
+        Path::new("."),
+        &["submodule", "sync", "--quiet", "--", &name],
+    )?;
+
+    Ok(branch)
+}
+
+/// Check that the repo can be added as a submodule at the given path, before git is asked to do
+/// it, since git's errors don't tell new users what to do. Returns the message to print if it
+/// can't be added.
+fn check_new_submodule(repo_path: &str, path: &Path) -> Result<(), String> {
+    let Ok(repo) = Repository::open(".") else {
+        return Err(
+            "This is not a git repo, run 'super init' to create a super repo first".to_string(),
+        );
+    };
+
+    if !is_repo_url(repo_path) {
+        return Err(format!(
+            "{} is neither a URL nor the path of a repo, e.g. 'git@github.com:me/repo.git'",
+            repo_path
+        ));
+    }
+
+    let submodules = repo.submodules().map_err(|error| error.to_string())?;
+    for submodule in submodules.iter() {
+        let name = submodule_name(submodule);
+        if submodule.url() == Some(repo_path) {
+            return Err(format!("{} is already a submodule: {}", repo_path, name));
+        }
+        if submodule.path() == path {
+            return Err(format!(
+                "{} is already the path of the submodule {}",
+                path.display(),
+                name
+            ));
+        }
+    }
+
+    Ok(())
+}
+
+/// Whether git could clone the repo: a URL with a scheme ('https://...'), an scp-like address
+/// ('git@host:path'), a path relative to the remote of the super repo ('../repo'), or the path of
+/// an existing local repo
+fn is_repo_url(repo_path: &str) -> bool {
+    if repo_path.is_empty() || repo_path.contains(char::is_whitespace) {
+        return false;
+    }
+
+    if let Some((scheme, rest)) = repo_path.split_once("://") {
+        let is_scheme = scheme
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || "+-.".contains(c));
+        return !scheme.is_empty() && is_scheme && !rest.is_empty();
+    }
+
+    // Like git, we treat a colon before the first slash as an scp-like address
+    if let Some((host, path)) = repo_path.split_once(':') {
+        if !host.is_empty() && !host.contains('/') {
+            return !path.is_empty();
+        }
+    }
+
+    repo_path.starts_with("./") || repo_path.starts_with("../") || Path::new(repo_path).exists()
+}
+
+/// Return the directory that 'git submodule add' clones the repo into, e.g. 'repo' for
+/// 'git@github.com:me/repo.git'
+fn default_submodule_path(repo_path: &str) -> &str {
+    let trimmed = repo_path.trim_end_matches('/');
+    let trimmed = trimmed.strip_suffix(".git").unwrap_or(trimmed);
+    let trimmed = trimmed.trim_end_matches('/');
+    trimmed.rsplit(['/', ':']).next().unwrap_or(trimmed)
+}
+
+/// Expand shorthand URLs like 'gh:me/repo' to the full URL, i.e. 'git@github.com:me/repo.git'
+///
+/// Shorthands are defined in .superconfig with 'shorthand.<name>.url', which also overrides the
+/// default 'gh' and 'gl' shorthands. Anything that is not a shorthand is returned as it is.
+fn expand_shorthand(repo: &str) -> String {
+    let (name, path) = match repo.split_once(':') {
+        Some(parts) => parts,
+        None => return repo.to_string(),
+    };
+
+    // Shorthands are plain names, which rules out URLs with a scheme ('https://...') and scp-like
+    // URLs ('git@host:path')
+    let is_name = !name.is_empty() && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '-');
+    if !is_name || path.starts_with("//") {
+        return repo.to_string();
+    }
+
+    let configured = git2::Config::open(Path::new(CONFIG_FILE))
+        .and_then(|config| config.get_string(&format!("shorthand.{}.url", name)));
+
+    let url = match configured {
+        Ok(url) => url,
+        Err(_error) => match DEFAULT_SHORTHANDS.iter().find(|(short, _)| *short == name) {
+            Some((_, url)) => url.to_string(),
+            None => return repo.to_string(),
+        },
+    };
+
+    let suffix = if path.ends_with(".git") { "" } else { ".git" };
+    format!("{}{}{}", url, path, suffix)
+}
+
+/// Add all git repos in the immediate subdirectories of the given directory as submodules
+///
+/// This is a bulk version of command_add, to migrate an existing multi-repo checkout into a super
+/// repo. git adopts the existing repos as they are, so nothing is cloned again.
+pub(crate) fn command_add_all(dir: &str) -> Result<(), SuperError> {
+    let repo: Repository = Repository::open(".")?;
+    let submodule_paths: Vec<PathBuf> = repo
+        .submodules()?
+        .iter()
+        .map(|submodule| submodule.path().to_path_buf())
+        .collect();
+
+    let dir = Path::new(dir);
+
+    for repo_path in get_git_repos(dir) {
+        // The directory itself may be a git repo (e.g. the super repo), but we only want to add
+        // the repos in its subdirectories
+        if repo_path == dir {
+            continue;
+        }
+
+        // find reports paths with a leading './' if it searched in '.'
+        let repo_path = repo_path.strip_prefix(".").unwrap_or(&repo_path);
+        let name = repo_path.display().to_string();
+
+        if submodule_paths.iter().any(|path| path == repo_path) {
+            print_repo_line(&name, "skipped", "already a submodule");
+            continue;
+        }
+
+        let Some(path) = repo_path.to_str() else {
+            print_repo_line(&name, "skipped", "the path is not valid UTF-8");
+            continue;
+        };
+
+        let url = match git_stdout(repo_path, &["remote", "get-url", "origin"]) {
+            Ok(url) => url,
+            Err(_) => {
+                print_repo_line(&name, "skipped", "no origin remote");
+                continue;
+            }
+        };
+
+        match run_git(Path::new("."), &["submodule", "add", &url, path]) {
+            Ok(_) => print_repo_line(&name, "added", &url),
+            Err(error) => print_repo_line(&name, "failed", &error.to_string()),
+        }
+    }
+
+    println!("You probably will want to commit this (along with .gitmodules).");
+
+    Ok(())
+}
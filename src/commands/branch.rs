@@ -0,0 +1,136 @@
+//! 'super branch', which shows which branch each repo is on
+
+use crate::cli::BranchOptions;
+use crate::cli::OutputFormat;
+use crate::for_each_repo;
+use crate::get_default_branch;
+use crate::get_head_state;
+use crate::init_layout;
+use crate::json_string;
+use crate::layout;
+use crate::paint;
+use crate::print_repo_line;
+use crate::short_hash;
+use crate::submodule_name;
+use crate::HeadState;
+use crate::SuperError;
+use git2::Repository;
+use std::path::PathBuf;
+
+/// Show the current branch of each submodule, next to the branch it tracks in .gitmodules
+pub(crate) fn command_branch(options: &BranchOptions) -> Result<(), SuperError> {
+    let repo: Repository = Repository::open(".")?;
+    let submodules = repo.submodules()?;
+
+    let names: Vec<String> = submodules.iter().map(submodule_name).collect();
+    let name_refs: Vec<&str> = names.iter().map(String::as_str).collect();
+    if let Err(error) = options.repos.validate(&name_refs) {
+        println!("{}", error);
+        return Ok(());
+    }
+    init_layout(
+        name_refs
+            .iter()
+            .copied()
+            .filter(|name| options.repos.includes(name)),
+    );
+
+    // Vector of (repo_name, repo_path, tracked) tuples, where tracked is the branch from
+    // .gitmodules, if there is one
+    let repos: Vec<(String, PathBuf, Option<String>)> = submodules
+        .iter()
+        .zip(&names)
+        .map(|(submodule, name)| {
+            let tracked = submodule.branch().map(str::to_string);
+            (name.clone(), submodule.path().to_path_buf(), tracked)
+        })
+        .filter(|(name, ..)| options.repos.includes(name))
+        .collect();
+
+    // Query all repos in parallel, but keep the order of .gitmodules for the output
+    let branches: Vec<(&str, Result<HeadState, SuperError>, String)> =
+        for_each_repo(&repos, |(_, repo_dir, tracked)| {
+            let head = get_head_state(repo_dir);
+            let tracked = tracked
+                .clone()
+                .unwrap_or_else(|| get_default_branch(repo_dir));
+            (head, tracked)
+        })
+        .into_iter()
+        .map(|((name, ..), (head, tracked))| (name.as_str(), head, tracked))
+        .collect();
+
+    match options.format {
+        OutputFormat::Human => {
+            for (name, head, tracked) in &branches {
+                match head {
+                    Ok(head) => print_branch_line(name, head, tracked),
+                    Err(error) => print_repo_line(name, "failed", &error.to_string()),
+                }
+            }
+        }
+        OutputFormat::Json => {
+            let entries: Vec<String> = branches
+                .iter()
+                .map(|(name, head, tracked)| {
+                    let head = match head {
+                        Ok(head) => head,
+                        Err(error) => {
+                            return format!(
+                                "{{\"name\":{},\"error\":{}}}",
+                                json_string(name),
+                                json_string(&error.to_string())
+                            )
+                        }
+                    };
+                    let branch = if head.branch.is_empty() {
+                        "null".to_string()
+                    } else {
+                        json_string(&head.branch)
+                    };
+                    format!(
+                        "{{\"name\":{},\"branch\":{},\"detached\":{},\"head\":{},\"tracked\":{},\"on_tracked_branch\":{}}}",
+                        json_string(name),
+                        branch,
+                        head.branch.is_empty(),
+                        json_string(&head.sha),
+                        json_string(tracked),
+                        head.branch == *tracked
+                    )
+                })
+                .collect();
+            println!("[{}]", entries.join(","));
+        }
+    }
+
+    Ok(())
+}
+
+/// Print the current and the tracked branch of the given repo. Repos that are not on their
+/// tracked branch are highlighted.
+fn print_branch_line(repo: &str, head: &HeadState, tracked: &str) {
+    let current = if head.branch.is_empty() {
+        format!("(detached @ {})", short_hash(&head.sha))
+    } else {
+        head.branch.clone()
+    };
+
+    let width = layout().repo;
+
+    if head.branch == tracked {
+        // neon pink, bright cyan
+        println!(
+            "{} {}",
+            paint("38;5;198;1", &format!("{repo:width$}")),
+            paint("1;36", &current)
+        )
+    } else {
+        // neon pink, bright yellow, white
+        println!(
+            "{} {} {}",
+            paint("38;5;198;1", &format!("{repo:width$}")),
+            paint("1;33", &current),
+            paint("1;37", &format!("(tracks {tracked})"))
+        )
+    }
+}
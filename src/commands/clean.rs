@@ -0,0 +1,128 @@
+//! 'super clean', which removes the untracked files of all repos
+
+use crate::cli::CleanOptions;
+use crate::confirm;
+use crate::for_each_repo;
+use crate::get_status_repos;
+use crate::git_stdout;
+use crate::init_layout;
+use crate::initialized_repos;
+use crate::print_repo_line;
+use crate::SuperError;
+use std::path::Path;
+
+/// List the untracked files and directories of all repos, and with --force, remove them after
+/// asking for confirmation
+pub(crate) fn command_clean(options: &CleanOptions) -> Result<(), SuperError> {
+    let repos = get_status_repos()?;
+    init_layout(repos.iter().map(|(name, _)| name.as_str()));
+
+    // Repos that are not initialized can't have any untracked files
+    let repos = initialized_repos(repos);
+    let results = for_each_repo(&repos, |(_, repo_dir)| {
+        clean_repo(repo_dir, options.ignored, None)
+    });
+
+    // Everything is listed before anything is removed
+    let mut cleanable = vec![];
+    for ((name, repo_dir), paths) in results {
+        match paths {
+            Ok(paths) if paths.is_empty() => print_repo_line(name, "clean", "nothing to remove"),
+            Ok(paths) => {
+                let status = if options.force {
+                    "will remove"
+                } else {
+                    "would remove"
+                };
+                print_repo_line(name, status, &describe_paths(&paths));
+                for path in &paths {
+                    println!("    {}", path);
+                }
+                cleanable.push((name, repo_dir, paths));
+            }
+            Err(error) => print_repo_line(name, "failed", &error.to_string()),
+        }
+    }
+
+    if cleanable.is_empty() {
+        println!("Nothing to clean");
+        return Ok(());
+    }
+
+    if !options.force {
+        println!("Run 'super clean --force' to remove these files");
+        return Ok(());
+    }
+
+    if !options.yes {
+        let prompt = format!(
+            "This will permanently delete the files of {} repos listed above. Continue?",
+            cleanable.len()
+        );
+        if !confirm(&prompt)? {
+            println!("Aborted.");
+            return Ok(());
+        }
+    }
+
+    for (name, repo_dir, paths) in cleanable {
+        match clean_repo(repo_dir, options.ignored, Some(&paths)) {
+            Ok(removed) => print_repo_line(name, "removed", &describe_paths(&removed)),
+            Err(error) => print_repo_line(name, "failed", &error.to_string()),
+        }
+    }
+
+    Ok(())
+}
+
+/// Run 'git clean' in the repo, and return the untracked files and directories that it removed.
+/// Without paths, it only lists what it would remove. With paths, it removes only those, so that
+/// nothing is removed that wasn't listed before. Directories end with a '/'.
+fn clean_repo(
+    repo_dir: &Path,
+    ignored: bool,
+    paths: Option<&[String]>,
+) -> Result<Vec<String>, SuperError> {
+    // Without quoting, the paths can be passed back to git as they are. They are literal paths, so
+    // that e.g. a file named '*.tmp' doesn't remove other files that were never listed.
+    let mut args = vec![
+        "--literal-pathspecs",
+        "-c",
+        "core.quotePath=false",
+        "clean",
+        "-d",
+    ];
+    args.push(if paths.is_some() {
+        "--force"
+    } else {
+        "--dry-run"
+    });
+    if ignored {
+        args.push("-x");
+    }
+    if let Some(paths) = paths {
+        args.push("--");
+        args.extend(paths.iter().map(String::as_str));
+    }
+
+    let output = git_stdout(repo_dir, &args)?;
+    let paths = output
+        .lines()
+        .filter_map(|line| {
+            line.strip_prefix("Would remove ")
+                .or_else(|| line.strip_prefix("Removing "))
+        })
+        .map(str::to_string)
+        .collect();
+    Ok(paths)
+}
+
+/// Count the files and directories in the output of clean_repo, e.g. '3 files, 1 directories'
+fn describe_paths(paths: &[String]) -> String {
+    let directories = paths.iter().filter(|path| path.ends_with('/')).count();
+    format!(
+        "{} files, {} directories",
+        paths.len() - directories,
+        directories
+    )
+}
@@ -0,0 +1,81 @@
+//! 'super commit', which commits the submodules that moved
+
+use crate::cli::CommitOptions;
+use crate::git_stdout;
+use crate::init_layout;
+use crate::print_repo_line;
+use crate::run_git;
+use crate::short_hash;
+use crate::submodule_name;
+use crate::SuperError;
+use git2::Repository;
+use std::path::Path;
+
+/// Commit all submodules that have moved to a different commit than the one the super repo records
+/// for them in its last commit
+pub(crate) fn command_commit(options: &CommitOptions) -> Result<(), SuperError> {
+    let repo: Repository = Repository::open(".")?;
+    let submodules = repo.submodules()?;
+
+    // Vector of (name, path, before, after) tuples. 'before' is None for submodules that were
+    // added since the last commit.
+    let mut moved: Vec<(String, String, Option<String>, String)> = vec![];
+
+    for submodule in submodules.iter() {
+        let Some(after) = submodule.workdir_id() else {
+            continue;
+        };
+        let before = submodule.head_id();
+        if before == Some(after) {
+            continue;
+        }
+
+        let name = submodule_name(submodule);
+        let path = submodule.path().to_string_lossy().to_string();
+        moved.push((
+            name,
+            path,
+            before.map(|id| id.to_string()),
+            after.to_string(),
+        ));
+    }
+
+    if moved.is_empty() {
+        println!("No submodules have moved, so there is nothing to commit");
+        return Ok(());
+    }
+
+    init_layout(moved.iter().map(|(name, ..)| name.as_str()));
+
+    let mut add_args = vec!["add", "--"];
+    add_args.extend(moved.iter().map(|(_, path, ..)| path.as_str()));
+    run_git(Path::new("."), &add_args)?;
+
+    // One line per submodule, e.g. 'foo: 1a2b3c4..5d6e7f8 (3 commits)'
+    let mut description = vec![];
+    for (name, path, before, after) in &moved {
+        let line = match before {
+            Some(before) => {
+                let range = format!("{}..{}", before, after);
+                // The count is only a nice-to-have, e.g. the old commit may not be available anymore
+                let count = match git_stdout(Path::new(path), &["rev-list", "--count", &range]) {
+                    Ok(count) => format!(" ({} commits)", count.trim()),
+                    Err(_) => String::new(),
+                };
+                format!("{}..{}{}", short_hash(before), short_hash(after), count)
+            }
+            None => format!("added at {}", short_hash(after)),
+        };
+        print_repo_line(name, "committed", &line);
+        description.push(format!("{}: {}", name, line));
+    }
+
+    let mut message = options.message.clone();
+    if options.describe {
+        message.push_str("\n\n");
+        message.push_str(&description.join("\n"));
+    }
+    run_git(Path::new("."), &["commit", "--quiet", "-m", &message])?;
+
+    Ok(())
+}
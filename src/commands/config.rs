@@ -0,0 +1,111 @@
+//! 'super config', which prints or sets a setting of a submodule
+
+use crate::run_git;
+use crate::submodule_name;
+use crate::suggest_repo_names;
+use crate::SuperError;
+use git2::Repository;
+use std::path::Path;
+
+/// The keys of a submodule in .gitmodules that 'super config' can read and write
+const SUBMODULE_CONFIG_KEYS: [&str; 5] = ["branch", "url", "update", "ignore", "group"];
+
+/// Print the value of the given key of a submodule in .gitmodules, or set it to the given value
+pub(crate) fn command_config(name: &str, key: &str, value: Option<&str>) -> Result<(), SuperError> {
+    if !SUBMODULE_CONFIG_KEYS.contains(&key) {
+        println!(
+            "Unknown key: {}. Valid keys are: {}",
+            key,
+            SUBMODULE_CONFIG_KEYS.join(", ")
+        );
+        return Ok(());
+    }
+
+    let repo: Repository = Repository::open(".")?;
+    let submodule = match repo.find_submodule(name) {
+        Ok(submodule) => submodule,
+        Err(_error) => {
+            let submodules = repo.submodules()?;
+            let names: Vec<String> = submodules.iter().map(submodule_name).collect();
+            let name_refs: Vec<&str> = names.iter().map(String::as_str).collect();
+            println!(
+                "Unknown repo: {}{}",
+                name,
+                suggest_repo_names(name, &name_refs)
+            );
+            return Ok(());
+        }
+    };
+
+    let mut config = git2::Config::open(Path::new(".gitmodules"))?;
+    let config_key = format!("submodule.{}.{}", name, key);
+
+    let Some(value) = value else {
+        // Just like 'git config', we print the bare value, so that scripts can use it
+        match config.get_string(&config_key) {
+            Ok(value) => println!("{}", value),
+            Err(_error) => eprintln!("{} is not set", config_key),
+        }
+        return Ok(());
+    };
+
+    if let Err(message) = validate_submodule_config(key, value) {
+        println!("Invalid {}: {}", key, message);
+        return Ok(());
+    }
+
+    config.set_str(&config_key, value)?;
+    println!("Set {} to {}", config_key, value);
+
+    // The url in particular is copied to .git/config when a submodule is initialized, so it
+    // doesn't take effect until it is synced
+    let path = submodule.path().to_string_lossy().to_string();
+    run_git(
+        Path::new("."),
+        &["submodule", "sync", "--quiet", "--", &path],
+    )?;
+
+    println!("You probably will want to commit this (along with .gitmodules).");
+
+    Ok(())
+}
+
+/// Check that a value for the given key of a submodule is plausible, before it ends up in
+/// .gitmodules. Returns a message that explains the problem otherwise.
+fn validate_submodule_config(key: &str, value: &str) -> Result<(), String> {
+    match key {
+        // '.' means the same branch as the super repo
+        "branch" if value == "." => Ok(()),
+        "branch" => match run_git(Path::new("."), &["check-ref-format", "--branch", value]) {
+            Ok(_) => Ok(()),
+            Err(_) => Err(format!("'{}' is not a valid branch name", value)),
+        },
+        "url" => {
+            // Both "https://host/path" and scp-like "host:path" urls contain a colon
+            let is_remote = value.contains(':');
+            let is_local =
+                value.starts_with('/') || value.starts_with("./") || value.starts_with("../");
+            if value.chars().any(char::is_whitespace) || !(is_remote || is_local) {
+                Err(format!("'{}' doesn't look like a url or a path", value))
+            } else {
+                Ok(())
+            }
+        }
+        // Custom '!command' strategies are deliberately not supported, since they run arbitrary
+        // commands on 'git submodule update'
+        "update" => match value {
+            "checkout" | "rebase" | "merge" | "none" => Ok(()),
+            _ => Err("must be one of checkout, rebase, merge or none".to_string()),
+        },
+        "ignore" => match value {
+            "none" | "untracked" | "dirty" | "all" => Ok(()),
+            _ => Err("must be one of none, untracked, dirty or all".to_string()),
+        },
+        // Group names are given to --group, so they have to survive the shell unquoted
+        "group" if value.is_empty() || value.chars().any(char::is_whitespace) => {
+            Err(format!("'{}' is not a valid group name", value))
+        }
+        "group" => Ok(()),
+        _ => Err(format!("unknown key: {}", key)),
+    }
+}
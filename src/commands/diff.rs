@@ -0,0 +1,68 @@
+//! 'super diff', which shows the submodules that moved since the last commit
+
+use crate::git_stdout;
+use crate::init_layout;
+use crate::print_repo_line;
+use crate::short_hash;
+use crate::submodule_name;
+use crate::SuperError;
+use git2::Repository;
+use std::path::Path;
+
+/// Show the submodules whose checked out commit differs from the one recorded in the index of the
+/// super repo, i.e. the changes that committing the super repo would record
+pub(crate) fn command_diff() -> Result<(), SuperError> {
+    let repo: Repository = Repository::open(".")?;
+    let submodules = repo.submodules()?;
+    let names: Vec<String> = submodules.iter().map(submodule_name).collect();
+    init_layout(names.iter().map(String::as_str));
+
+    let mut changed = 0;
+    for (submodule, name) in submodules.iter().zip(&names) {
+        // Uninitialized submodules have no checked out commit to compare
+        let Some(after) = submodule.workdir_id() else {
+            continue;
+        };
+        let before = submodule.index_id();
+        if before == Some(after) {
+            continue;
+        }
+        changed += 1;
+
+        let path = submodule.path();
+        let after = after.to_string();
+        let remark = match before {
+            Some(before) => {
+                let before = before.to_string();
+                format!(
+                    "{} {} -> {} {}",
+                    short_hash(&before),
+                    get_subject(path, &before),
+                    short_hash(&after),
+                    get_subject(path, &after)
+                )
+            }
+            None => format!(
+                "(new) -> {} {}",
+                short_hash(&after),
+                get_subject(path, &after)
+            ),
+        };
+        print_repo_line(name, "changed", &remark);
+    }
+
+    if changed == 0 {
+        println!("No pending changes");
+    }
+
+    Ok(())
+}
+
+/// Return the subject of the given commit, i.e. the first line of its message. Commits that are
+/// not available locally (e.g. after a force push) are marked as such.
+fn get_subject(repo_dir: &Path, sha: &str) -> String {
+    match git_stdout(repo_dir, &["log", "-1", "--format=%s", sha]) {
+        Ok(subject) => subject,
+        Err(_) => "(not available)".to_string(),
+    }
+}
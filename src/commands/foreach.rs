@@ -0,0 +1,624 @@
+//! 'super foreach', which runs a command in each repo
+
+use crate::cli::DirtyFilter;
+use crate::cli::ForeachOptions;
+use crate::get_head_state;
+use crate::json_string;
+use crate::log_repo_result;
+use crate::no_submodules_message;
+use crate::paint;
+use crate::print_repo_line;
+use crate::run_hook;
+use crate::stop_watchdog;
+use crate::submodule_name;
+use crate::timestamp;
+use crate::track_child;
+use crate::untrack_child;
+use crate::watch_timeout;
+use crate::write_log_record;
+use crate::LoggedOutput;
+use crate::SuperError;
+use crate::LOG_FILE;
+use git2::Repository;
+use std::env;
+use std::io::BufRead;
+use std::io::BufReader;
+use std::os::unix::process::CommandExt;
+use std::path::Path;
+use std::path::PathBuf;
+use std::process::Command;
+use std::process::ExitStatus;
+use std::process::Stdio;
+use std::sync::mpsc;
+use std::thread;
+use std::time::Duration;
+
+/// The colors (ANSI 256-color codes) of the repo prefixes of 'super foreach
+/// --parallel-output-prefix'. They are assigned to the repos in turn.
+const PREFIX_COLORS: [u8; 6] = [198, 45, 214, 118, 141, 208];
+
+/// The result of running the command of 'super foreach' in a single repo
+struct CommandResult {
+    name: String,
+    /// The exit status of the command, or None if it couldn't be started
+    status: Option<ExitStatus>,
+    /// The output of the command. Empty if it was already streamed with --parallel-output-prefix.
+    stdout: String,
+    stderr: String,
+    /// Whether the command was killed because it ran longer than the --timeout
+    timed_out: bool,
+}
+
+impl CommandResult {
+    /// Whether the command exited with one of the expected exit codes. A command that was killed by
+    /// a signal has no exit code, so it never succeeded.
+    fn succeeded(&self, expected_codes: &[i32]) -> bool {
+        self.status
+            .and_then(|status| status.code())
+            .is_some_and(|code| expected_codes.contains(&code))
+    }
+}
+
+// Run the given command for each submodule in parallel. Returns the number of repos where the
+// command failed.
+pub(crate) fn command_foreach(
+    command: &[String],
+    options: &ForeachOptions,
+) -> Result<usize, SuperError> {
+    let current_dir: std::path::PathBuf =
+        env::current_dir().expect("Failed to get current directory");
+
+    // Vector of (name, directory) tuples, for all directories that the command runs in
+    let mut targets: Vec<(String, PathBuf)> = vec![];
+    // The names of the submodules that are skipped, because they are not initialized
+    let mut uninitialized: Vec<String> = vec![];
+
+    if options.include_super {
+        targets.push(("<super>".to_string(), current_dir.clone()));
+    }
+
+    if options.dirs.is_empty() {
+        let repo: Repository = Repository::open(".")?;
+        let submodules = repo.submodules()?;
+        let names: Vec<String> = submodules.iter().map(submodule_name).collect();
+        let name_refs: Vec<&str> = names.iter().map(String::as_str).collect();
+        if let Err(error) = options.repos.validate(&name_refs) {
+            println!("{}", error);
+            return Ok(0);
+        }
+
+        // The directories of submodules that are not initialized are empty, and the command would
+        // run in the super repo instead
+        let initialized = submodules
+            .iter()
+            .filter(|submodule| current_dir.join(submodule.path()).join(".git").exists())
+            .count();
+        if initialized == 0 {
+            println!("{}", no_submodules_message(submodules.len()));
+            if !options.include_super {
+                return Ok(0);
+            }
+        } else {
+            for (submodule, name) in submodules.iter().zip(names) {
+                if !options.repos.includes(&name) {
+                    continue;
+                }
+                // Note: The path of a submodule can differ from its name, so we have to use the
+                // path here. Unlike the name, it is never converted to UTF-8.
+                let repo_dir = current_dir.join(submodule.path());
+                // A directory that is missing altogether is reported as failed below
+                if repo_dir.is_dir() && !repo_dir.join(".git").exists() {
+                    let remark = "not initialized, run 'super pull --init'";
+                    print_repo_line(&name, "skipped", remark);
+                    uninitialized.push(name);
+                    continue;
+                }
+                targets.push((name, repo_dir));
+            }
+        }
+    } else {
+        // The directories don't have to be submodules, or even git repos, so that this works in
+        // monorepos and without a super repo as well
+        for dir in &options.dirs {
+            let dir_path = current_dir.join(dir);
+            if !dir_path.is_dir() {
+                println!("Not a directory: {}", dir);
+                return Ok(0);
+            }
+            targets.push((dir.clone(), dir_path));
+        }
+    }
+
+    if let Some(filter) = options.filter {
+        let before = targets.len();
+        targets = filter_repos(targets, filter);
+        println!(
+            "Filtered out {} repos that are not {}",
+            before - targets.len(),
+            filter.to_str()
+        );
+    }
+
+    // With --git, the command is a git subcommand. Since the command runs inside the repo
+    // directory, this is equivalent to 'git -C <repo_dir> <command>'.
+    let mut cmd: Vec<String> = Vec::new();
+    if options.git {
+        cmd.push("git".to_string());
+    }
+    cmd.extend_from_slice(command);
+
+    // The command as it would be typed into a shell, for --print-cmd and --dry-run
+    let cmd_line = if options.shell {
+        cmd.join(" ")
+    } else {
+        let args: Vec<String> = cmd.iter().map(|arg| shell_quote(arg)).collect();
+        args.join(" ")
+    };
+
+    // With --shell, the arguments are joined with spaces, and the shell splits them up again. That
+    // is what makes pipes and redirects work, but it also means that the arguments need quoting.
+    if options.shell {
+        let script = cmd.join(" ");
+        cmd = if cfg!(windows) {
+            vec!["cmd".to_string(), "/C".to_string(), script]
+        } else {
+            vec!["sh".to_string(), "-c".to_string(), script]
+        };
+    }
+
+    // With --dry-run, nothing runs at all, not even the hooks
+    if options.dry_run {
+        for (name, _) in &targets {
+            print_foreach_cmd(name, &cmd_line);
+        }
+        return Ok(0);
+    }
+
+    // A failing pre-foreach hook aborts, before the command runs anywhere
+    run_hook("pre-foreach", &[], &[])?;
+
+    let total = targets.len() + uninitialized.len();
+    let mut results: Vec<CommandResult> = vec![];
+    // Vector of (index, name, directory) tuples, for the repos that the command still has to run in.
+    // The index picks the color of the repo with --parallel-output-prefix.
+    let mut jobs: Vec<(usize, String, PathBuf)> = vec![];
+
+    for (index, (name, repo_dir)) in targets.into_iter().enumerate() {
+        // The command would fail with a confusing error in a directory that doesn't exist
+        if !repo_dir.is_dir() {
+            let result = CommandResult {
+                name,
+                status: None,
+                stdout: String::new(),
+                stderr: format!(
+                    "The directory {} is missing, run 'super pull --init'",
+                    repo_dir.display()
+                ),
+                timed_out: false,
+            };
+            report_command_result(&result, options);
+            results.push(result);
+            continue;
+        }
+        jobs.push((index, name, repo_dir));
+    }
+
+    // Without --max-failures, the command runs in all repos at once. With it, the jobs are handed
+    // out to a bounded number of workers, so that the ones that haven't started yet can still be
+    // cancelled once too many repos have failed.
+    let workers = match options.max_failures {
+        Some(_) => thread::available_parallelism().map_or(4, |n| n.get()),
+        None => jobs.len(),
+    };
+    let mut failures = results.len();
+    let can_start = |failures: usize| options.max_failures.map_or(true, |max| failures < max);
+
+    // The results are sent back as soon as they are done, so that the output of fast repos doesn't
+    // wait for slow ones
+    let (tx, rx) = mpsc::channel();
+    let mut jobs = jobs.into_iter();
+    let mut running = 0;
+
+    while running < workers && can_start(failures) {
+        let Some(job) = jobs.next() else {
+            break;
+        };
+        if options.print_cmd {
+            print_foreach_cmd(&job.1, &cmd_line);
+        }
+        spawn_command(job, cmd.clone(), options, tx.clone());
+        running += 1;
+    }
+
+    while running > 0 {
+        let result = rx.recv().unwrap();
+        running -= 1;
+        report_command_result(&result, options);
+        if !result.succeeded(options.expected_codes()) {
+            failures += 1;
+        }
+        results.push(result);
+
+        if can_start(failures) {
+            if let Some(job) = jobs.next() {
+                if options.print_cmd {
+                    print_foreach_cmd(&job.1, &cmd_line);
+                }
+                spawn_command(job, cmd.clone(), options, tx.clone());
+                running += 1;
+            }
+        }
+    }
+
+    let failed: Vec<&str> = results
+        .iter()
+        .filter(|result| !result.succeeded(options.expected_codes()))
+        .map(|result| result.name.as_str())
+        .collect();
+    if !failed.is_empty() {
+        println!("Failed in {}: {}", failed.len(), failed.join(", "));
+    }
+
+    // The jobs that are left were cancelled by --max-failures
+    let skipped: Vec<String> = jobs.map(|(_, name, _)| name).collect();
+    if !skipped.is_empty() {
+        println!(
+            "Skipped after {} failures: {}",
+            failures,
+            skipped.join(", ")
+        );
+    }
+
+    // A machine-readable summary with stable keys, like the one of 'super pull'. The repos that
+    // are not initialized count as skipped too.
+    eprintln!(
+        "super: total={} succeeded={} failed={} skipped={}",
+        total,
+        results.len() - failed.len(),
+        failed.len(),
+        skipped.len() + uninitialized.len()
+    );
+
+    let affected: Vec<&str> = results.iter().map(|result| result.name.as_str()).collect();
+    let statuses: Vec<(&str, &str)> = results
+        .iter()
+        .map(|result| {
+            let status = if result.succeeded(options.expected_codes()) {
+                "succeeded"
+            } else {
+                "failed"
+            };
+            (result.name.as_str(), status)
+        })
+        .collect();
+    if let Err(error) = run_hook("post-foreach", &affected, &statuses) {
+        eprintln!("Warning: {}", error);
+    }
+
+    Ok(failed.len())
+}
+
+/// Run the command in the repo of the given job in a new thread, and send the result back
+fn spawn_command(
+    job: (usize, String, PathBuf),
+    cmd: Vec<String>,
+    options: &ForeachOptions,
+    tx: mpsc::Sender<CommandResult>,
+) {
+    let (index, name, repo_dir) = job;
+    // Each repo keeps its color for the whole run, so that its lines are easy to follow
+    let color = PREFIX_COLORS[index % PREFIX_COLORS.len()];
+    let prefix_output = options.prefix_output;
+    let timeout = options.timeout;
+
+    thread::spawn(move || {
+        let result = if prefix_output {
+            run_command_prefixed(&name, &repo_dir, cmd, color, timeout)
+        } else {
+            run_command(&name, &repo_dir, cmd, timeout)
+        };
+        tx.send(result).unwrap();
+    });
+}
+
+/// Print the command that 'super foreach' runs in the given repo, dimmed, e.g. '[foo] $ git status'
+fn print_foreach_cmd(repo: &str, cmd_line: &str) {
+    println!("{}", paint("2", &format!("[{}] $ {}", repo, cmd_line)));
+}
+
+/// Quote the argument for the shell, unless it only consists of characters that the shell doesn't
+/// interpret
+fn shell_quote(arg: &str) -> String {
+    let plain = !arg.is_empty()
+        && arg
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || "-_./=:,+@%".contains(c));
+    if plain {
+        arg.to_string()
+    } else {
+        format!("'{}'", arg.replace('\'', "'\\''"))
+    }
+}
+
+/// Keep only the repos whose uncommitted changes match the filter. Repos whose directory is missing
+/// are kept, so that they are still reported. The order of the repos doesn't change.
+fn filter_repos(repos: Vec<(String, PathBuf)>, filter: DirtyFilter) -> Vec<(String, PathBuf)> {
+    // Check all repos in parallel, since 'git status' can be slow in large repos
+    let threads: Vec<_> = repos
+        .into_iter()
+        .map(|(name, repo_dir)| {
+            thread::spawn(move || {
+                let keep = !repo_dir.is_dir()
+                    || match get_head_state(&repo_dir) {
+                        Ok(head) => filter.matches(&head),
+                        Err(error) => {
+                            print_repo_line(&name, "skipped", &error.to_string());
+                            false
+                        }
+                    };
+                (name, repo_dir, keep)
+            })
+        })
+        .collect();
+
+    threads
+        .into_iter()
+        .map(|handle| handle.join().unwrap())
+        .filter(|(_, _, keep)| *keep)
+        .map(|(name, repo_dir, _)| (name, repo_dir))
+        .collect()
+}
+
+// Run the given command as a subprocess (but not in a sub-shell), and capture its output. With a
+// timeout, the command is killed if it runs longer than that.
+fn run_command(
+    name: &str,
+    repo_path: &Path,
+    cmd: Vec<String>,
+    timeout: Option<Duration>,
+) -> CommandResult {
+    let mut command = Command::new(cmd[0].clone());
+
+    // Add all arguments to the command
+    if cmd.len() > 1 {
+        command.args(&cmd[1..]);
+    }
+
+    // The output is only printed, so invalid UTF-8 in it is replaced instead of failing the command
+    match command
+        .current_dir(repo_path)
+        .logged_output_with_timeout(timeout)
+    {
+        Ok((output, timed_out)) => CommandResult {
+            name: name.to_string(),
+            status: Some(output.status),
+            stdout: String::from_utf8_lossy(&output.stdout).to_string(),
+            stderr: String::from_utf8_lossy(&output.stderr).to_string(),
+            timed_out,
+        },
+        Err(error) => CommandResult {
+            name: name.to_string(),
+            status: None,
+            stdout: String::new(),
+            stderr: format!("Failed to run the command. Error: {}", error),
+            timed_out: false,
+        },
+    }
+}
+
+/// Print the result of the command in a single repo, and record it in the --log-file. With
+/// --max-output-lines, only the first lines of stdout are printed, and the full output goes to the
+/// --log-file instead.
+fn report_command_result(result: &CommandResult, options: &ForeachOptions) {
+    let truncated = options
+        .max_output_lines
+        .and_then(|max_lines| truncate_output(&result.stdout, max_lines));
+
+    match truncated {
+        Some(stdout) => {
+            let shortened = CommandResult {
+                name: result.name.clone(),
+                status: result.status,
+                stdout,
+                stderr: result.stderr.clone(),
+                timed_out: result.timed_out,
+            };
+            print_command_result(&shortened, options.prefix_output, options.expected_codes());
+            log_command_result(result, options.expected_codes());
+            log_repo_output("foreach", &result.name, &result.stdout);
+        }
+        None => {
+            print_command_result(result, options.prefix_output, options.expected_codes());
+            log_command_result(result, options.expected_codes());
+        }
+    }
+}
+
+/// Keep the first max_lines lines of the output, followed by a note of how many lines were cut.
+/// Returns None if the output has no more lines than that.
+fn truncate_output(output: &str, max_lines: usize) -> Option<String> {
+    let total = output.lines().count();
+    if total <= max_lines {
+        return None;
+    }
+
+    let mut kept = String::new();
+    for line in output.lines().take(max_lines) {
+        kept.push_str(line);
+        kept.push('\n');
+    }
+    kept.push_str(&format!(
+        "… (truncated, {} more lines)\n",
+        total - max_lines
+    ));
+    Some(kept)
+}
+
+/// Record the result of the command of 'super foreach' in a single repo in the --log-file
+fn log_command_result(result: &CommandResult, expected_codes: &[i32]) {
+    let status = if result.timed_out {
+        "timeout"
+    } else if result.succeeded(expected_codes) {
+        "succeeded"
+    } else {
+        "failed"
+    };
+    let exit_code = result.status.and_then(|status| status.code());
+    log_repo_result("foreach", &result.name, status, exit_code, &result.stderr);
+}
+
+/// Print the result of the command in a single repo, below a header with the name of the repo: a
+/// green one if the command exited with one of the expected codes, and a red one with the exit
+/// code if it failed. stderr is indented, so that it stands out from stdout. If the output was
+/// already streamed, only failures are printed.
+fn print_command_result(result: &CommandResult, streamed: bool, expected_codes: &[i32]) {
+    if streamed && result.succeeded(expected_codes) {
+        return;
+    }
+
+    // bright green, bright red
+    let header = match result.status {
+        Some(_) if result.timed_out => paint("1;31", &format!("{} (timeout)", result.name)),
+        Some(_) if result.succeeded(expected_codes) => paint("1;32", &result.name),
+        Some(status) => match status.code() {
+            Some(code) => paint("1;31", &format!("{} (exit code {})", result.name, code)),
+            // The command was killed by a signal
+            None => paint("1;31", &format!("{} ({})", result.name, status)),
+        },
+        None => paint("1;31", &format!("{} (not run)", result.name)),
+    };
+
+    let mut block = format!("{}\n{}", header, result.stdout);
+    if !block.ends_with('\n') {
+        block.push('\n');
+    }
+    for line in result.stderr.lines() {
+        block.push_str(&format!("    {}\n", line));
+    }
+
+    // Note: We print the whole block with a single call, so that the output of different repos
+    // doesn't get interleaved.
+    println!("{}", block);
+}
+
+/// Run the given command as a subprocess, and stream its output line by line as it comes in. Each
+/// line is prefixed with the name of the repo, in the given color (an ANSI 256-color code).
+/// The output is not captured, so the stdout and stderr of the result are only set for errors.
+fn run_command_prefixed(
+    name: &str,
+    repo_path: &Path,
+    cmd: Vec<String>,
+    color: u8,
+    timeout: Option<Duration>,
+) -> CommandResult {
+    let prefix = paint(&format!("38;5;{color};1"), &format!("[{name}]"));
+
+    log::debug!("Running '{}' in {}", cmd.join(" "), repo_path.display());
+
+    // Without a terminal to type into, a command that asks for input would wait forever. Like in
+    // logged_output, the command only gets a process group of its own with a timeout.
+    let mut command = Command::new(&cmd[0]);
+    if timeout.is_some() {
+        command.process_group(0);
+    }
+    let child = command
+        .args(&cmd[1..])
+        .current_dir(repo_path)
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn();
+
+    let mut child = match child {
+        Ok(child) => {
+            track_child(child.id(), timeout.is_some());
+            child
+        }
+        Err(error) => {
+            return CommandResult {
+                name: name.to_string(),
+                status: None,
+                stdout: String::new(),
+                stderr: format!("Failed to run the command. Error: {error}"),
+                timed_out: false,
+            }
+        }
+    };
+
+    let watchdog = timeout.map(|timeout| watch_timeout(child.id(), timeout));
+
+    // stderr is read by a second thread, so that neither of the pipes can fill up and block the
+    // child. println! and eprintln! lock the stream, so lines of different repos don't get mixed.
+    let stderr = child.stderr.take().map(|stderr| {
+        let prefix = prefix.clone();
+        thread::spawn(move || {
+            for line in BufReader::new(stderr).split(b'\n').map_while(Result::ok) {
+                eprintln!("{prefix} {}", display_line(&line));
+            }
+        })
+    });
+
+    if let Some(stdout) = child.stdout.take() {
+        for line in BufReader::new(stdout).split(b'\n').map_while(Result::ok) {
+            println!("{prefix} {}", display_line(&line));
+        }
+    }
+
+    if let Some(handle) = stderr {
+        let _ = handle.join();
+    }
+
+    let status = child.wait();
+    untrack_child(child.id());
+    let timed_out = stop_watchdog(watchdog);
+
+    match status {
+        Ok(status) => {
+            log::debug!(
+                "'{}' in {} exited with {}",
+                cmd[0],
+                repo_path.display(),
+                status
+            );
+            CommandResult {
+                name: name.to_string(),
+                status: Some(status),
+                stdout: String::new(),
+                stderr: String::new(),
+                timed_out,
+            }
+        }
+        Err(error) => CommandResult {
+            name: name.to_string(),
+            status: None,
+            stdout: String::new(),
+            stderr: format!("Failed to wait for the command. Error: {error}"),
+            timed_out: false,
+        },
+    }
+}
+
+/// Convert a line of output for display, without its line ending. The lines are split as bytes,
+/// because 'BufRead::lines' stops at the first line that is not valid UTF-8, which would leave the
+/// rest of the output unread. Invalid UTF-8 is replaced, since the line is only printed.
+fn display_line(line: &[u8]) -> std::borrow::Cow<str> {
+    String::from_utf8_lossy(line.strip_suffix(b"\r").unwrap_or(line))
+}
+
+/// Append the full output of a command in a repo to the --log-file, if one was given, e.g. because
+/// only part of it was printed. The output is a JSON string, so that the record stays on one line.
+fn log_repo_output(command: &str, repo: &str, output: &str) {
+    let Some(file) = LOG_FILE.get() else {
+        return;
+    };
+
+    let record = format!(
+        "{} command={} repo={} output={}\n",
+        timestamp(),
+        command,
+        json_string(repo),
+        json_string(output)
+    );
+
+    write_log_record(file, &record);
+}
@@ -0,0 +1,163 @@
+//! 'super freeze' and 'super thaw', which write the commit of each submodule to super.lock and
+//! check those commits out again
+
+use crate::checkout;
+use crate::init_layout;
+use crate::json_string;
+use crate::print_repo_line;
+use crate::run_git;
+use crate::short_hash;
+use crate::submodule_name;
+use crate::SuperError;
+use crate::LOCK_FILE;
+use git2::Repository;
+use std::fs;
+use std::path::Path;
+
+/// A submodule and the commit that it is pinned to in super.lock
+#[derive(Default)]
+struct LockEntry {
+    name: String,
+    path: String,
+    sha: String,
+}
+
+/// Write the commit that each submodule is at to super.lock, so that 'super thaw' can restore it
+///
+/// Unlike committing the super repo, this also works for commits that are not on any branch yet.
+pub(crate) fn command_freeze() -> Result<(), SuperError> {
+    let repo: Repository = Repository::open(".")?;
+    let submodules = repo.submodules()?;
+    let names: Vec<String> = submodules.iter().map(submodule_name).collect();
+    init_layout(names.iter().map(String::as_str));
+
+    let mut lock = String::from("# Written by 'super freeze', restore it with 'super thaw'\n");
+
+    for (submodule, name) in submodules.iter().zip(&names) {
+        let Some(sha) = submodule.workdir_id() else {
+            print_repo_line(name, "skipped", "not initialized");
+            continue;
+        };
+        let Some(path) = submodule.path().to_str() else {
+            print_repo_line(name, "skipped", "the path is not valid UTF-8");
+            continue;
+        };
+        let sha = sha.to_string();
+
+        // The strings are quoted like in JSON, which makes them valid TOML as well
+        lock.push_str(&format!(
+            "\n[[submodule]]\nname = {}\npath = {}\nsha = {}\n",
+            json_string(name),
+            json_string(path),
+            json_string(&sha)
+        ));
+        print_repo_line(name, "frozen", short_hash(&sha));
+    }
+
+    fs::write(LOCK_FILE, lock).map_err(SuperError::Io)?;
+
+    Ok(())
+}
+
+/// Check out the commits that super.lock records for the submodules
+pub(crate) fn command_thaw() -> Result<(), SuperError> {
+    let lock = fs::read_to_string(LOCK_FILE).map_err(SuperError::Io)?;
+    let entries = parse_lock_file(&lock)?;
+    init_layout(entries.iter().map(|entry| entry.name.as_str()));
+
+    for entry in &entries {
+        let repo_dir = Path::new(&entry.path);
+        let short_sha = short_hash(&entry.sha);
+
+        // Commits that were frozen on another machine may not have been fetched here yet
+        let commit = format!("{}^{{commit}}", entry.sha);
+        if run_git(repo_dir, &["cat-file", "-e", &commit]).is_err() {
+            let remark = format!("{} is not available locally, fetch it first", short_sha);
+            print_repo_line(&entry.name, "missing", &remark);
+            continue;
+        }
+
+        match checkout(repo_dir, &entry.sha, false) {
+            Ok(_) => print_repo_line(&entry.name, "thawed", short_sha),
+            Err(error) => print_repo_line(&entry.name, "failed", &error.to_string()),
+        }
+    }
+
+    Ok(())
+}
+
+/// Parse the contents of super.lock, which has a '[[submodule]]' table with a name, path and sha
+/// for each submodule
+fn parse_lock_file(content: &str) -> Result<Vec<LockEntry>, SuperError> {
+    let mut entries: Vec<LockEntry> = vec![];
+
+    for (index, line) in content.lines().enumerate() {
+        let line = line.trim();
+        let invalid =
+            |message: &str| SuperError::InvalidLockFile(format!("line {}: {}", index + 1, message));
+
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        if line == "[[submodule]]" {
+            entries.push(LockEntry::default());
+            continue;
+        }
+
+        let Some((key, value)) = line.split_once('=') else {
+            return Err(invalid("expected 'key = \"value\"'"));
+        };
+        let Some(value) = unquote_string(value.trim()) else {
+            return Err(invalid("expected a quoted string"));
+        };
+        let Some(entry) = entries.last_mut() else {
+            return Err(invalid("expected '[[submodule]]' first"));
+        };
+
+        match key.trim() {
+            "name" => entry.name = value,
+            "path" => entry.path = value,
+            "sha" => entry.sha = value,
+            key => return Err(invalid(&format!("unknown key '{}'", key))),
+        }
+    }
+
+    if let Some(entry) = entries
+        .iter()
+        .find(|entry| entry.path.is_empty() || entry.sha.is_empty())
+    {
+        let message = format!("submodule '{}' needs a path and a sha", entry.name);
+        return Err(SuperError::InvalidLockFile(message));
+    }
+
+    Ok(entries)
+}
+
+/// Parse a string that was quoted with json_string. Returns None if it isn't a valid quoted string.
+fn unquote_string(quoted: &str) -> Option<String> {
+    let inner = quoted.strip_prefix('"')?.strip_suffix('"')?;
+    let mut unquoted = String::with_capacity(inner.len());
+    let mut chars = inner.chars();
+
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            unquoted.push(c);
+            continue;
+        }
+
+        match chars.next()? {
+            '"' => unquoted.push('"'),
+            '\\' => unquoted.push('\\'),
+            'n' => unquoted.push('\n'),
+            'r' => unquoted.push('\r'),
+            't' => unquoted.push('\t'),
+            'u' => {
+                let code: String = chars.by_ref().take(4).collect();
+                unquoted.push(char::from_u32(u32::from_str_radix(&code, 16).ok()?)?);
+            }
+            _ => return None,
+        }
+    }
+
+    Some(unquoted)
+}
@@ -0,0 +1,48 @@
+//! 'super help' and 'super --help', which print the documentation of super
+
+use crate::COMMANDS;
+use crate::DOCUMENTATION;
+
+/// Print the commands of super with a short description, for 'super --help'
+pub(crate) fn print_overview() {
+    println!("super - manage all of your git repos in one super repository");
+    println!();
+    println!("Usage: super [<global options>] <command> [<args>]");
+    println!();
+    println!("Commands:");
+    let width = COMMANDS
+        .iter()
+        .map(|(name, _)| name.len())
+        .max()
+        .unwrap_or(0);
+    for (name, description) in COMMANDS {
+        println!("    {name:width$}   {description}");
+    }
+    println!();
+    println!("Run 'super <command> --help' for the options of a command, and 'super help' for the");
+    println!("full documentation, including the global options.");
+}
+
+/// Print the documentation of a single command, i.e. its paragraphs from DOCUMENTATION
+pub(crate) fn print_command_help(command: &str) {
+    // The paragraph of a command starts with its synopsis, e.g. 'super pull [--retries <n>] ...'.
+    // The brackets mark the command of 'super [status]' as optional.
+    let paragraphs: Vec<&str> = DOCUMENTATION
+        .split("\n\n")
+        .filter(|paragraph| {
+            let synopsis = paragraph.trim_start().strip_prefix("super ");
+            synopsis
+                .and_then(|synopsis| synopsis.split_whitespace().next())
+                .is_some_and(|name| name.trim_start_matches('[').trim_end_matches(']') == command)
+        })
+        .collect();
+
+    if paragraphs.is_empty() {
+        println!(
+            "Unknown command: {}. Run 'super --help' for a list of commands.",
+            command
+        );
+    } else {
+        println!("{}", paragraphs.join("\n\n"));
+    }
+}
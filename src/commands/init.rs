@@ -0,0 +1,34 @@
+//! 'super init', which initializes a new super repo
+
+use super::add::command_add;
+use crate::run_git;
+use std::path::Path;
+
+/// Initialize the super repo for the first time
+///
+/// You have to call this in the directory that you want to initialize
+pub(crate) fn command_init(repo_paths: &[String]) {
+    match run_git(Path::new("."), &["init"]) {
+        Ok(_) => println!("The super repo was initialized successfully."),
+        Err(error) => {
+            println!("Failed to initialize the super repo. Error: {}", error);
+            return;
+        }
+    }
+
+    if repo_paths.is_empty() {
+        println!("You can now add your repos with 'super add <pathspec>");
+        return;
+    }
+
+    // A repo that can't be added shouldn't keep the others from being added
+    let failed: Vec<&str> = repo_paths
+        .iter()
+        .filter(|repo_path| !command_add(repo_path, false))
+        .map(String::as_str)
+        .collect();
+
+    if !failed.is_empty() {
+        println!("Failed to add {}: {}", failed.len(), failed.join(", "));
+    }
+}
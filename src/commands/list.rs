@@ -0,0 +1,37 @@
+//! 'super list', which lists all repos with their group and path
+
+use crate::get_group;
+use crate::get_status_repos;
+use crate::init_layout;
+use crate::print_repo_line;
+use crate::SuperError;
+use std::io::Write;
+use std::os::unix::ffi::OsStrExt;
+
+/// List all repos, with the group that each of them is in and its path, or only their paths
+pub(crate) fn command_list(paths: bool) -> Result<(), SuperError> {
+    let repos = get_status_repos()?;
+
+    // The paths are meant for other programs, so they are written as they are, even if they are
+    // not valid UTF-8
+    if paths {
+        let mut stdout = std::io::stdout().lock();
+        for (_, repo_dir) in &repos {
+            // Discovered repos start with './'
+            let path = repo_dir.strip_prefix(".").unwrap_or(repo_dir);
+            stdout
+                .write_all(path.as_os_str().as_bytes())
+                .and_then(|_| stdout.write_all(b"\n"))
+                .map_err(SuperError::Io)?;
+        }
+        return Ok(());
+    }
+
+    init_layout(repos.iter().map(|(name, _)| name.as_str()));
+
+    for (name, repo_dir) in &repos {
+        print_repo_line(name, &get_group(name), &repo_dir.display().to_string());
+    }
+
+    Ok(())
+}
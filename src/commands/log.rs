@@ -0,0 +1,87 @@
+//! 'super log', which shows the latest commits of all repos in one list
+
+use crate::cli::LogOptions;
+use crate::for_each_repo;
+use crate::get_status_repos;
+use crate::git_stdout;
+use crate::init_layout;
+use crate::initialized_repos;
+use crate::print_repo_line;
+use crate::short_hash;
+use crate::SuperError;
+
+/// A commit that 'super log' shows
+struct LogEntry {
+    repo: String,
+    /// The commit time as a Unix timestamp, by which the commits of all repos are sorted
+    time: u64,
+    sha: String,
+    /// The commit date, e.g. '2023-11-05'
+    date: String,
+    author: String,
+    subject: String,
+}
+
+/// Show the latest commits of all repos in a single list, newest first. The logs of the repos are
+/// read in parallel.
+pub(crate) fn command_log(options: &LogOptions) -> Result<(), SuperError> {
+    let repos = get_status_repos()?;
+    init_layout(repos.iter().map(|(name, _)| name.as_str()));
+
+    // No repo can contribute more than the total number of commits. The fields are separated by
+    // tabs, and the subject comes last, because it is the only one that can contain a tab.
+    let mut args = vec![
+        "log".to_string(),
+        format!("--max-count={}", options.count),
+        "--format=%ct%x09%H%x09%cs%x09%an%x09%s".to_string(),
+    ];
+    if let Some(since) = &options.since {
+        args.push(format!("--since={}", since));
+    }
+
+    // Repos that are not initialized don't have any commits
+    let repos = initialized_repos(repos);
+    let args: Vec<&str> = args.iter().map(String::as_str).collect();
+    let logs = for_each_repo(&repos, |(_, repo_dir)| git_stdout(repo_dir, &args));
+
+    let mut entries: Vec<LogEntry> = vec![];
+    for ((name, _), log) in logs {
+        let log = match log {
+            Ok(log) => log,
+            Err(error) => {
+                print_repo_line(name, "failed", &error.to_string());
+                continue;
+            }
+        };
+
+        for line in log.lines() {
+            let fields: Vec<&str> = line.splitn(5, '\t').collect();
+            let [time, sha, date, author, subject] = fields[..] else {
+                continue;
+            };
+            entries.push(LogEntry {
+                repo: name.clone(),
+                time: time.parse().unwrap_or(0),
+                sha: sha.to_string(),
+                date: date.to_string(),
+                author: author.to_string(),
+                subject: subject.to_string(),
+            });
+        }
+    }
+
+    // The repos finish in any order, so commits with the same time are sorted by their repo, to
+    // get the same list every time. The sort is stable, so the commits of a single repo keep the
+    // order of 'git log'.
+    entries.sort_by(|a, b| b.time.cmp(&a.time).then_with(|| a.repo.cmp(&b.repo)));
+
+    if entries.is_empty() {
+        println!("No commits found");
+    }
+    for entry in entries.iter().take(options.count) {
+        let remark = format!("{} {} ({})", entry.date, entry.subject, entry.author);
+        print_repo_line(&entry.repo, short_hash(&entry.sha), &remark);
+    }
+
+    Ok(())
+}
@@ -0,0 +1,196 @@
+//! The handlers of the commands of super. Each command has a module of its own, together with the
+//! helpers that only it uses. The helpers that several commands share are in the crate root.
+
+mod add;
+mod branch;
+mod clean;
+mod commit;
+mod config;
+mod diff;
+mod foreach;
+mod freeze;
+mod help;
+mod init;
+mod list;
+mod log;
+mod mv;
+mod open;
+mod prune_gone;
+mod pull;
+mod reset;
+mod stash;
+mod status;
+mod sync;
+mod tag;
+mod tui;
+mod undo;
+mod upgrade;
+mod version;
+mod watch;
+mod which;
+
+use crate::cli::Subcommand;
+use crate::print_health_footer;
+use crate::print_summary_line;
+use crate::DOCUMENTATION;
+
+/// Run the handler of a command, and print its error if it fails
+pub(crate) fn run(subcommand: Subcommand) {
+    match subcommand {
+        Subcommand::Status(options) => match status::command_status(&options) {
+            Ok(_) => (),
+            Err(error) => println!("Error getting the status of your repos: {}", error),
+        },
+        Subcommand::Init(repo_paths) => init::command_init(&repo_paths),
+        Subcommand::Add {
+            repo_path,
+            branch_from_head,
+        } => {
+            add::command_add(&repo_path, branch_from_head);
+        }
+        Subcommand::AddExisting { repo_path, dir } => {
+            match add::command_add_existing(&repo_path, &dir) {
+                Ok(_) => (),
+                Err(error) => println!("Error adding your repo: {}", error),
+            }
+        }
+        Subcommand::AddAll(dir) => match add::command_add_all(&dir) {
+            Ok(_) => (),
+            Err(error) => println!("Error adding your repos: {}", error),
+        },
+        Subcommand::Pull(options) => match pull::command_pull(&options) {
+            Ok(summary) => {
+                print_health_footer(&summary.by_status);
+                print_summary_line(&summary);
+                // Scripts and cron jobs rely on the exit code to notice failed repos
+                if summary.failed > 0 {
+                    println!(
+                        "Failed to pull {}: {}",
+                        summary.failed,
+                        summary.failed_repos.join(", ")
+                    );
+                    std::process::exit(1);
+                }
+            }
+            Err(error) => {
+                println!("Error pulling your repos: {}", error);
+                std::process::exit(1);
+            }
+        },
+        Subcommand::Fetch(options) => match pull::command_fetch(&options) {
+            Ok(summary) => {
+                print_health_footer(&summary.by_status);
+                print_summary_line(&summary);
+                if summary.failed > 0 {
+                    println!(
+                        "Failed to fetch {}: {}",
+                        summary.failed,
+                        summary.failed_repos.join(", ")
+                    );
+                    std::process::exit(1);
+                }
+            }
+            Err(error) => {
+                println!("Error fetching your repos: {}", error);
+                std::process::exit(1);
+            }
+        },
+        Subcommand::Sync => match sync::command_sync() {
+            Ok(_) => (),
+            Err(error) => println!("Error syncing your repos: {}", error),
+        },
+        Subcommand::Branch(options) => match branch::command_branch(&options) {
+            Ok(_) => (),
+            Err(error) => println!("Error getting the branches of your repos: {}", error),
+        },
+        Subcommand::Undo(options) => match undo::command_undo(&options) {
+            Ok(_) => (),
+            Err(error) => println!("Error resetting your repos: {}", error),
+        },
+        Subcommand::Reset(options) => match reset::command_reset(&options) {
+            Ok(_) => (),
+            Err(error) => println!("Error resetting your repos: {}", error),
+        },
+        Subcommand::Clean(options) => match clean::command_clean(&options) {
+            Ok(_) => (),
+            Err(error) => println!("Error cleaning your repos: {}", error),
+        },
+        Subcommand::Version => version::command_version(),
+        Subcommand::Upgrade { yes } => match upgrade::command_upgrade(yes) {
+            Ok(_) => (),
+            Err(error) => println!("Error upgrading super: {}", error),
+        },
+        // stdout is reserved for the path, so that 'cd $(super open <repo>)' works
+        Subcommand::Open(query) => match open::command_open(&query) {
+            Ok(_) => (),
+            Err(error) => eprintln!("Error finding your repo: {}", error),
+        },
+        Subcommand::ShellInit => print!("{}", open::SHELL_INIT),
+        Subcommand::Diff => match diff::command_diff() {
+            Ok(_) => (),
+            Err(error) => println!("Error comparing your repos: {}", error),
+        },
+        Subcommand::Commit(options) => match commit::command_commit(&options) {
+            Ok(_) => (),
+            Err(error) => println!("Error committing your repos: {}", error),
+        },
+        Subcommand::Stash(options) => match stash::command_stash(&options) {
+            Ok(_) => (),
+            Err(error) => println!("Error stashing your changes: {}", error),
+        },
+        Subcommand::Tag(options) => match tag::command_tag(&options) {
+            Ok(_) => (),
+            Err(error) => println!("Error tagging your repos: {}", error),
+        },
+        Subcommand::Log(options) => match log::command_log(&options) {
+            Ok(_) => (),
+            Err(error) => println!("Error showing the log of your repos: {}", error),
+        },
+        Subcommand::PruneGone { force } => match prune_gone::command_prune_gone(force) {
+            Ok(_) => (),
+            Err(error) => println!("Error pruning your branches: {}", error),
+        },
+        Subcommand::Mv { from, to } => match mv::command_mv(&from, &to) {
+            Ok(_) => (),
+            Err(error) => println!("Error moving your repo: {}", error),
+        },
+        Subcommand::Config { name, key, value } => {
+            match config::command_config(&name, &key, value.as_deref()) {
+                Ok(_) => (),
+                Err(error) => println!("Error configuring your repo: {}", error),
+            }
+        }
+        Subcommand::Which(name) => which::command_which(&name),
+        Subcommand::List { paths } => match list::command_list(paths) {
+            Ok(_) => (),
+            Err(error) => println!("Error listing your repos: {}", error),
+        },
+        Subcommand::Watch => match watch::command_watch() {
+            Ok(_) => (),
+            Err(error) => println!("Error watching your repos: {}", error),
+        },
+        Subcommand::Tui => match tui::command_tui() {
+            Ok(_) => (),
+            Err(error) => println!("Error running the dashboard: {}", error),
+        },
+        Subcommand::Freeze => match freeze::command_freeze() {
+            Ok(_) => (),
+            Err(error) => println!("Error freezing your repos: {}", error),
+        },
+        Subcommand::Thaw => match freeze::command_thaw() {
+            Ok(_) => (),
+            Err(error) => println!("Error thawing your repos: {}", error),
+        },
+        Subcommand::Foreach(options, command) => {
+            match foreach::command_foreach(&command, &options) {
+                // Like with 'super pull', scripts rely on the exit code to notice failed repos
+                Ok(failed) if failed > 0 => std::process::exit(1),
+                Ok(_) => (),
+                Err(error) => println!("Error running command: {}", error),
+            }
+        }
+        Subcommand::Overview => help::print_overview(),
+        Subcommand::Documentation => println!("{}", DOCUMENTATION),
+        Subcommand::CommandHelp(command) => help::print_command_help(&command),
+    }
+}
@@ -0,0 +1,54 @@
+//! 'super mv', which moves a submodule to a new path
+
+use crate::run_git;
+use crate::submodule_name;
+use crate::SuperError;
+use git2::Repository;
+use std::fs;
+use std::path::Path;
+
+/// Move a submodule to a new path, and keep .gitmodules and the submodule's own config in sync
+pub(crate) fn command_mv(from: &str, to: &str) -> Result<(), SuperError> {
+    let repo: Repository = Repository::open(".")?;
+    let submodules = repo.submodules()?;
+
+    // The submodule can be given by its path or by its name, which are often the same
+    let from_path = Path::new(from.trim_end_matches('/'));
+    let Some(submodule) = submodules
+        .iter()
+        .find(|s| s.path() == from_path || submodule_name(s) == from)
+    else {
+        println!("{} is not a submodule", from);
+        return Ok(());
+    };
+
+    let to_path = Path::new(to.trim_end_matches('/'));
+    if to_path.exists() {
+        println!("{} already exists", to);
+        return Ok(());
+    }
+
+    let name = submodule_name(submodule);
+    let from = submodule.path().to_string_lossy().to_string();
+    let to = to_path.to_string_lossy().to_string();
+
+    // 'git mv' doesn't create missing parent directories
+    if let Some(parent) = to_path
+        .parent()
+        .filter(|parent| !parent.as_os_str().is_empty())
+    {
+        fs::create_dir_all(parent).map_err(SuperError::Io)?;
+    }
+    run_git(Path::new("."), &["mv", &from, &to])?;
+
+    // Recent versions of git update .gitmodules themselves, but older ones don't
+    let mut config = git2::Config::open(Path::new(".gitmodules"))?;
+    config.set_str(&format!("submodule.{}.path", name), &to)?;
+    run_git(Path::new("."), &["add", ".gitmodules"])?;
+    run_git(Path::new("."), &["submodule", "sync", "--quiet", "--", &to])?;
+
+    println!("Moved {} from {} to {}", name, from, to);
+    println!("You probably will want to commit this (along with .gitmodules).");
+
+    Ok(())
+}
@@ -0,0 +1,81 @@
+//! 'super open', which prints the path of a repo
+
+use crate::submodule_name;
+use crate::suggest_repo_names;
+use crate::SuperError;
+use git2::Repository;
+use std::env;
+
+/// A shell function that makes 'super open <repo>' change into the directory of the repo, which the
+/// super process itself can't do for its parent shell. It is printed by 'super open --shell-init'.
+pub(crate) const SHELL_INIT: &str = r#"super() {
+    if [ "$1" = "open" ] && [ "$#" -eq 2 ] && [ "$2" != "--shell-init" ]; then
+        local dir
+        dir="$(command super open "$2")" && [ -n "$dir" ] && cd "$dir"
+    else
+        command super "$@"
+    fi
+}
+"#;
+
+/// Print the absolute path of the submodule with the given name
+///
+/// The name doesn't have to be exact: a prefix of a name, or its characters in the right order
+/// (e.g. 'fe' for 'frontend') are enough, as long as only one repo matches.
+pub(crate) fn command_open(query: &str) -> Result<(), SuperError> {
+    let repo: Repository = Repository::open(".")?;
+    let submodules = repo.submodules()?;
+    let owned_names: Vec<String> = submodules.iter().map(submodule_name).collect();
+    let names: Vec<&str> = owned_names.iter().map(String::as_str).collect();
+
+    match match_repo_names(&names, query).as_slice() {
+        [] => eprintln!(
+            "No repo matches '{}'{}",
+            query,
+            suggest_repo_names(query, &names)
+        ),
+        [name] => {
+            let submodule = &submodules[names.iter().position(|n| n == name).unwrap()];
+            let current_dir = env::current_dir().map_err(SuperError::Io)?;
+            println!("{}", current_dir.join(submodule.path()).display());
+        }
+        candidates => {
+            eprintln!("'{}' matches more than one repo:", query);
+            for name in candidates {
+                eprintln!("    {}", name);
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// Return the repo names that match the query. Only the strictest kind of match counts: an exact
+/// match, otherwise all names that start with the query, otherwise all names that contain the
+/// characters of the query in the right order.
+fn match_repo_names<'a>(names: &[&'a str], query: &str) -> Vec<&'a str> {
+    if let Some(name) = names.iter().find(|name| **name == query) {
+        return vec![name];
+    }
+
+    let prefixed: Vec<&str> = names
+        .iter()
+        .copied()
+        .filter(|name| name.starts_with(query))
+        .collect();
+    if !prefixed.is_empty() {
+        return prefixed;
+    }
+
+    names
+        .iter()
+        .copied()
+        .filter(|name| is_subsequence(query, name))
+        .collect()
+}
+
+/// Return true if all characters of the needle appear in the haystack, in the same order
+fn is_subsequence(needle: &str, haystack: &str) -> bool {
+    let mut haystack = haystack.chars();
+    needle.chars().all(|c| haystack.any(|h| h == c))
+}
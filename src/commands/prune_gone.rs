@@ -0,0 +1,85 @@
+//! 'super prune-gone', which deletes the branches whose upstream was deleted
+
+use crate::for_each_repo;
+use crate::get_status_repos;
+use crate::git_stdout;
+use crate::init_layout;
+use crate::initialized_repos;
+use crate::print_repo_line;
+use crate::run_git;
+use crate::SuperError;
+use std::path::Path;
+
+/// Delete the local branches of all repos whose upstream is gone, e.g. because the branch was
+/// deleted on the remote after it was merged
+pub(crate) fn command_prune_gone(force: bool) -> Result<(), SuperError> {
+    let repos = get_status_repos()?;
+    init_layout(repos.iter().map(|(name, _)| name.as_str()));
+
+    // Repos that are not initialized don't have any branches
+    let repos = initialized_repos(repos);
+    let results = for_each_repo(&repos, |(_, repo_dir)| prune_gone_branches(repo_dir, force));
+
+    for ((name, _), (status, remark)) in results {
+        print_repo_line(name, status, &remark);
+    }
+
+    Ok(())
+}
+
+/// Delete the branches of a single repo whose upstream is gone, and return its status and remark
+/// for 'super prune-gone'. Without force, only merged branches are deleted. The current branch is
+/// never deleted.
+fn prune_gone_branches(repo_dir: &Path, force: bool) -> (&'static str, String) {
+    // %(upstream:track) is '[gone]' for branches whose upstream doesn't exist anymore, and
+    // %(HEAD) is '*' for the current branch
+    let format = "--format=%(refname:short)%09%(upstream:track)%09%(HEAD)";
+    let branches = match git_stdout(repo_dir, &["for-each-ref", format, "refs/heads"]) {
+        Ok(branches) => branches,
+        Err(error) => return ("failed", error.to_string()),
+    };
+
+    let mut deleted = vec![];
+    let mut unmerged = vec![];
+    let mut current = None;
+    for line in branches.lines() {
+        let fields: Vec<&str> = line.split('\t').collect();
+        let [branch, "[gone]", head] = fields[..] else {
+            continue;
+        };
+        if head == "*" {
+            current = Some(branch);
+            continue;
+        }
+
+        // 'git branch -d' refuses to delete branches that are not merged into HEAD
+        let delete = if force { "-D" } else { "-d" };
+        match run_git(repo_dir, &["branch", "--quiet", delete, branch]) {
+            Ok(_) => deleted.push(branch),
+            Err(_) => unmerged.push(branch),
+        }
+    }
+
+    let mut remarks = vec![];
+    if !deleted.is_empty() {
+        remarks.push(format!("deleted {}: {}", deleted.len(), deleted.join(", ")));
+    }
+    if !unmerged.is_empty() {
+        remarks.push(format!(
+            "kept {} unmerged: {} (use --force)",
+            unmerged.len(),
+            unmerged.join(", ")
+        ));
+    }
+    if let Some(current) = current {
+        remarks.push(format!("kept the current branch {}", current));
+    }
+
+    if remarks.is_empty() {
+        ("unchanged", "no branches with a gone upstream".to_string())
+    } else if deleted.is_empty() {
+        ("unchanged", remarks.join(", "))
+    } else {
+        ("pruned", remarks.join(", "))
+    }
+}
@@ -0,0 +1,421 @@
+//! 'super pull' and 'super fetch', which fetch all repos, and fast-forward them
+
+use crate::cli::PullOptions;
+use crate::cli::ReportFormat;
+use crate::count_status;
+use crate::for_each_repo;
+use crate::format_duration;
+use crate::get_head_sha;
+use crate::get_pull_targets;
+use crate::get_uncommitted_submodules;
+use crate::init_layout;
+use crate::log_repo_result;
+use crate::no_submodules_message;
+use crate::print_repo_line;
+use crate::pull_single_repo;
+use crate::run_git;
+use crate::run_hook;
+use crate::short_hash;
+use crate::PullStatus;
+use crate::PullTarget;
+use crate::RepoResult;
+use crate::Summary;
+use crate::SuperError;
+use git2::Repository;
+use std::env;
+use std::fs;
+use std::path::Path;
+use std::time::Duration;
+use std::time::Instant;
+
+/// How many of the slowest repos 'super pull -v' lists at the end
+const SLOWEST_REPOS: usize = 5;
+
+// Pull all submodules in the given repo in parallel
+fn pull_in_parallel(
+    current_dir: &Path,
+    options: &PullOptions,
+) -> Result<Vec<RepoResult>, SuperError> {
+    let mut results: Vec<RepoResult> = vec![];
+
+    let mut repos: Vec<PullTarget> = vec![];
+    // Vector of (repo_name, repo_path) tuples of the submodules that are not cloned yet
+    let mut uninitialized: Vec<(String, String)> = vec![];
+
+    let targets = get_pull_targets()?;
+    let names: Vec<&str> = targets.iter().map(|target| target.name.as_str()).collect();
+    if let Err(error) = options.repos.validate(&names) {
+        println!("{}", error);
+        return Ok(results);
+    }
+    // Only a super repo is expected to have submodules, a directory with repos in it isn't
+    if names.is_empty() && Repository::open(".").is_ok() {
+        println!("{}", no_submodules_message(0));
+        return Ok(results);
+    }
+    init_layout(
+        names
+            .into_iter()
+            .filter(|name| options.repos.includes(name)),
+    );
+
+    for target in targets {
+        if !options.repos.includes(&target.name) {
+            continue;
+        }
+
+        // Submodules can be marked as manual with 'update = none' in .gitmodules, and we respect
+        // that just like 'git submodule update' does
+        if target.manual {
+            let result = RepoResult::new(&target.name, PullStatus::Skipped, "update=none");
+            print_status_line(&result.name, &result.status, &result.remark);
+            results.push(result);
+            continue;
+        }
+
+        // A submodule directory that was deleted (or is a dangling symlink) is restored by --init,
+        // just like an uninitialized one
+        if !target.dir.is_dir() && !options.init {
+            let remark = "the directory doesn't exist, run 'super pull --init'";
+            let result = RepoResult::new(&target.name, PullStatus::Missing, remark);
+            print_status_line(&result.name, &result.status, &result.remark);
+            results.push(result);
+            continue;
+        }
+
+        // After cloning a super repo without --recurse-submodules, the submodule directories are
+        // empty
+        if !target.dir.join(".git").exists() {
+            let path = target.dir.display().to_string();
+            uninitialized.push((target.name, path));
+            continue;
+        }
+
+        repos.push(target);
+    }
+
+    if !uninitialized.is_empty() {
+        for result in init_submodules(current_dir, &uninitialized, options.init) {
+            print_status_line(&result.name, &result.status, &result.remark);
+            results.push(result);
+        }
+        if repos.is_empty() && !options.init {
+            println!("{}", no_submodules_message(uninitialized.len()));
+        }
+    }
+
+    // With --serial, the repos are pulled one after the other, in the order of .gitmodules. This
+    // keeps a slow connection from being saturated, and makes it easy to follow what happens. The
+    // progress goes to stderr, so that the status lines still line up.
+    if options.serial {
+        let total = repos.len();
+        for (index, target) in repos.iter().enumerate() {
+            eprintln!("[{}/{}] {}", index + 1, total, target.name);
+            let result = timed_pull(target, options);
+            print_status_line(&result.name, &result.status, &result.timed_remark());
+            results.push(result);
+        }
+        return Ok(results);
+    }
+
+    for (_, result) in for_each_repo(&repos, |target| timed_pull(target, options)) {
+        print_status_line(&result.name, &result.status, &result.timed_remark());
+        results.push(result);
+    }
+
+    Ok(results)
+}
+
+/// Pull a single repo like pull_single_repo, and with options.timings, measure how long it took
+fn timed_pull(target: &PullTarget, options: &PullOptions) -> RepoResult {
+    let started = Instant::now();
+    let mut result = pull_single_repo(target, options);
+    if options.timings {
+        result.duration = Some(started.elapsed());
+    }
+    result
+}
+
+/// Print the repos that took the longest, slowest first, to find the bottleneck of a pull. Only
+/// repos that were measured are listed.
+fn print_slowest_repos(results: &[RepoResult]) {
+    let mut timed: Vec<(&str, Duration)> = results
+        .iter()
+        .filter_map(|result| Some((result.name.as_str(), result.duration?)))
+        .collect();
+    if timed.is_empty() {
+        return;
+    }
+
+    timed.sort_by(|(_, a), (_, b)| b.cmp(a));
+    let slowest: Vec<String> = timed
+        .iter()
+        .take(SLOWEST_REPOS)
+        .map(|(name, duration)| format!("{} ({})", name, format_duration(*duration)))
+        .collect();
+    println!("Slowest repos: {}", slowest.join(", "));
+}
+
+/// Clone the given submodules, which are (repo_name, repo_path) tuples. Without init, they are only
+/// reported as not initialized.
+///
+/// All submodules are cloned by a single 'git submodule update', because git has to write to the
+/// config of the super repo for each of them, which can't be done from several processes at once.
+fn init_submodules(
+    current_dir: &Path,
+    submodules: &[(String, String)],
+    init: bool,
+) -> Vec<RepoResult> {
+    if !init {
+        return submodules
+            .iter()
+            .map(|(name, _)| {
+                let remark = "not initialized, run 'super pull --init'";
+                RepoResult::new(name, PullStatus::Skipped, remark)
+            })
+            .collect();
+    }
+
+    let mut args = vec!["submodule", "update", "--init", "--"];
+    args.extend(submodules.iter().map(|(_, path)| path.as_str()));
+
+    if let Err(error) = run_git(current_dir, &args) {
+        return submodules
+            .iter()
+            .map(|(name, _)| RepoResult::new(name, PullStatus::Failed, &error.to_string()))
+            .collect();
+    }
+
+    submodules
+        .iter()
+        .map(|(name, path)| match get_head_sha(&current_dir.join(path)) {
+            Ok(sha) => {
+                let remark = format!("cloned at {}", short_hash(&sha));
+                RepoResult::new(name, PullStatus::Initialized, &remark)
+            }
+            Err(error) => RepoResult::new(name, PullStatus::Failed, &error.to_string()),
+        })
+        .collect()
+}
+
+/// Pull the latest code for all submodules in the super repo, and return how many repos ended up in
+/// each status
+pub(crate) fn command_pull(options: &PullOptions) -> Result<Summary, SuperError> {
+    let current_dir: std::path::PathBuf =
+        env::current_dir().expect("Failed to get current directory");
+
+    // Pulling moves the submodules, which mixes their new commits up with the submodule changes
+    // that are already uncommitted in the super repo. This only informs, and never blocks the pull.
+    if let Ok(repo) = Repository::open(".") {
+        match get_uncommitted_submodules(&repo) {
+            Ok(submodules) if !submodules.is_empty() => {
+                let names: Vec<&str> = submodules.iter().map(|(name, _)| name.as_str()).collect();
+                eprintln!(
+                    "Warning: the super repo has uncommitted changes to the commits of {}: {}",
+                    names.len(),
+                    names.join(", ")
+                );
+                eprintln!("Run 'super commit' after the pull to record all of them at once");
+            }
+            Ok(_) => (),
+            Err(error) => log::warn!("Failed to check the super repo for changes: {}", error),
+        }
+    }
+
+    // A failing pre-pull hook aborts the pull, before anything has changed
+    run_hook("pre-pull", &[], &[])?;
+
+    let results = pull_in_parallel(&current_dir, options)?;
+
+    // The pull itself is done at this point, so a failing post-pull hook is only worth a warning
+    let affected: Vec<&str> = results
+        .iter()
+        .filter(|result| result.status == PullStatus::Updated)
+        .map(|result| result.name.as_str())
+        .collect();
+    let statuses: Vec<(&str, &str)> = results
+        .iter()
+        .map(|result| (result.name.as_str(), result.status.to_str()))
+        .collect();
+    if let Err(error) = run_hook("post-pull", &affected, &statuses) {
+        eprintln!("Warning: {}", error);
+    }
+
+    // Like the hook, the report can't undo the pull anymore, so failing to write it isn't an error
+    if let Some(path) = &options.report {
+        let report = match options.report_format {
+            ReportFormat::Markdown => format_markdown_report(&results),
+            ReportFormat::Html => format_html_report(&results),
+        };
+        match fs::write(path, report) {
+            Ok(()) => println!("Report written to {}", path),
+            Err(error) => eprintln!("Warning: failed to write the report to {}: {}", path, error),
+        }
+    }
+
+    if options.timings {
+        print_slowest_repos(&results);
+    }
+
+    Ok(summarize(&results))
+}
+
+/// Sort the results of a pull by the name of their repo, since the repos finish in any order
+fn sorted_results(results: &[RepoResult]) -> Vec<&RepoResult> {
+    let mut sorted: Vec<&RepoResult> = results.iter().collect();
+    sorted.sort_by(|a, b| a.name.cmp(&b.name));
+    sorted
+}
+
+/// Format the report of 'super pull --report' as Markdown: a table with the status of each repo,
+/// followed by the new commits of each repo that moved
+fn format_markdown_report(results: &[RepoResult]) -> String {
+    // A '|' would end the table cell early
+    let cell = |text: &str| text.replace('|', "\\|");
+    let results = sorted_results(results);
+
+    let mut report = String::from("# super pull\n\n");
+    report.push_str("| Repo | Status | Before | After | Remark |\n");
+    report.push_str("| --- | --- | --- | --- | --- |\n");
+    for result in &results {
+        let (before, after) = match &result.change {
+            Some(change) => (change.before.as_str(), change.after.as_str()),
+            None => ("", ""),
+        };
+        report.push_str(&format!(
+            "| {} | {} | {} | {} | {} |\n",
+            cell(&result.name),
+            result.status.to_str(),
+            before,
+            after,
+            cell(&result.remark)
+        ));
+    }
+
+    for result in &results {
+        let Some(change) = &result.change else {
+            continue;
+        };
+        report.push_str(&format!(
+            "\n## {} ({}..{})\n\n",
+            result.name, change.before, change.after
+        ));
+        for (sha, subject) in &change.commits {
+            report.push_str(&format!("- `{}` {}\n", sha, subject));
+        }
+    }
+
+    report
+}
+
+/// Format the report of 'super pull --report' as a standalone HTML page, with the same content as
+/// the Markdown report
+fn format_html_report(results: &[RepoResult]) -> String {
+    let results = sorted_results(results);
+
+    let mut report = String::from("<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n");
+    report.push_str("<title>super pull</title>\n</head>\n<body>\n<h1>super pull</h1>\n<table>\n");
+    report.push_str(
+        "<tr><th>Repo</th><th>Status</th><th>Before</th><th>After</th><th>Remark</th></tr>\n",
+    );
+    for result in &results {
+        let (before, after) = match &result.change {
+            Some(change) => (change.before.as_str(), change.after.as_str()),
+            None => ("", ""),
+        };
+        report.push_str(&format!(
+            "<tr><td>{}</td><td>{}</td><td>{}</td><td>{}</td><td>{}</td></tr>\n",
+            escape_html(&result.name),
+            result.status.to_str(),
+            before,
+            after,
+            escape_html(&result.remark)
+        ));
+    }
+    report.push_str("</table>\n");
+
+    for result in &results {
+        let Some(change) = &result.change else {
+            continue;
+        };
+        report.push_str(&format!(
+            "<h2>{} ({}..{})</h2>\n<ul>\n",
+            escape_html(&result.name),
+            change.before,
+            change.after
+        ));
+        for (sha, subject) in &change.commits {
+            report.push_str(&format!(
+                "<li><code>{}</code> {}</li>\n",
+                sha,
+                escape_html(subject)
+            ));
+        }
+        report.push_str("</ul>\n");
+    }
+    report.push_str("</body>\n</html>\n");
+
+    report
+}
+
+/// Escape the characters that have a meaning in HTML
+fn escape_html(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+/// Fetch all repos like 'super pull' does, but without fast-forwarding any of them, and return how
+/// many repos ended up in each status
+pub(crate) fn command_fetch(options: &PullOptions) -> Result<Summary, SuperError> {
+    let current_dir: std::path::PathBuf =
+        env::current_dir().expect("Failed to get current directory");
+
+    let results = pull_in_parallel(&current_dir, options)?;
+    if options.timings {
+        print_slowest_repos(&results);
+    }
+
+    Ok(summarize(&results))
+}
+
+/// Count how many repos ended up in each status
+fn summarize(results: &[RepoResult]) -> Summary {
+    let mut summary = Summary {
+        total: results.len(),
+        ..Summary::default()
+    };
+
+    for result in results {
+        count_status(&mut summary.by_status, result.status.to_str());
+        match result.status {
+            PullStatus::Updated | PullStatus::Initialized | PullStatus::Fetched => {
+                summary.updated += 1
+            }
+            PullStatus::UpToDate => summary.up_to_date += 1,
+            PullStatus::Failed
+            | PullStatus::StashConflict
+            | PullStatus::Diverged
+            | PullStatus::Missing
+            | PullStatus::Locked => {
+                summary.failed += 1;
+                summary.failed_repos.push(result.name.clone());
+            }
+            PullStatus::Unchanged
+            | PullStatus::Skipped
+            | PullStatus::Pinned
+            | PullStatus::Detached => summary.skipped += 1,
+        }
+    }
+
+    summary
+}
+
+/// Print the status of the given repo, and record it in the --log-file
+fn print_status_line(repo: &str, status: &PullStatus, remark: &str) {
+    log_repo_result("pull", repo, status.to_str(), None, remark);
+    // Note: We have to convert the pull status to a string first, because we want to align the string,
+    // and alignment is not implemented for the Debug trait.
+    print_repo_line(repo, status.to_str(), remark)
+}
@@ -0,0 +1,197 @@
+//! 'super reset', which resets all repos to the branch that they track
+
+use crate::cli::ResetOptions;
+use crate::confirm;
+use crate::get_default_branch;
+use crate::get_head_state;
+use crate::get_pull_targets;
+use crate::git_stdout;
+use crate::init_layout;
+use crate::print_repo_line;
+use crate::resolve_ref;
+use crate::run_git;
+use crate::PullTarget;
+use crate::SuperError;
+use std::path::PathBuf;
+
+/// What 'super reset' is going to discard in a repo
+struct ResetPlan {
+    name: String,
+    dir: PathBuf,
+    /// The branch whose remote-tracking branch the repo is reset to
+    branch: String,
+    /// Whether the branch has to be checked out first, because the repo is on another branch or
+    /// has a detached HEAD
+    switch: bool,
+    /// The number of local commits on the branch that are not on the remote
+    commits: usize,
+    /// The number of tracked files with uncommitted changes
+    changed: usize,
+    /// The number of untracked files and directories that --clean removes
+    untracked: usize,
+}
+
+/// Reset all repos to the remote-tracking branch of the branch that they track, discarding local
+/// commits and uncommitted changes
+pub(crate) fn command_reset(options: &ResetOptions) -> Result<(), SuperError> {
+    let targets = get_pull_targets()?;
+    init_layout(targets.iter().map(|target| target.name.as_str()));
+
+    // First find out what would be discarded in each repo, so that all of it is listed before we ask
+    // for confirmation and change anything
+    let mut plans = vec![];
+    for target in &targets {
+        match plan_reset(target, options) {
+            Ok(plan) => {
+                print_repo_line(&plan.name, "will reset", &describe_reset(&plan));
+                plans.push(plan);
+            }
+            Err((status, remark)) => print_repo_line(&target.name, status, &remark),
+        }
+    }
+
+    if plans.is_empty() {
+        println!("Nothing to reset");
+        return Ok(());
+    }
+
+    if !options.yes {
+        let prompt = format!(
+            "This will reset {} repos and discard everything listed above. Continue?",
+            plans.len()
+        );
+        if !confirm(&prompt)? {
+            println!("Aborted.");
+            return Ok(());
+        }
+    }
+
+    for plan in plans {
+        match reset_repo(&plan, options.clean) {
+            Ok(()) => print_repo_line(&plan.name, "reset", &describe_reset(&plan)),
+            Err(error) => print_repo_line(&plan.name, "failed", &error.to_string()),
+        }
+    }
+
+    Ok(())
+}
+
+/// Find out what resetting the repo would discard. Returns the status and remark to print instead,
+/// if the repo is skipped or there is nothing to reset.
+fn plan_reset(
+    target: &PullTarget,
+    options: &ResetOptions,
+) -> Result<ResetPlan, (&'static str, String)> {
+    let failed = |error: SuperError| ("failed", error.to_string());
+    let dir = &target.dir;
+
+    if !dir.join(".git").exists() {
+        return Err(("skipped", "not initialized".to_string()));
+    }
+
+    let branch = match &target.branch {
+        Some(branch) => branch.clone(),
+        None => get_default_branch(dir),
+    };
+    let head = get_head_state(dir).map_err(failed)?;
+
+    // Resetting a different branch than the checked out one would be surprising, so that needs
+    // --force, which checks out the tracked branch first
+    let switch = head.branch != branch;
+    if switch && !options.force {
+        let remark = if head.branch.is_empty() {
+            format!("detached HEAD (use --force to check out {})", branch)
+        } else {
+            format!(
+                "on {} instead of {} (use --force to switch)",
+                head.branch, branch
+            )
+        };
+        return Err(("skipped", remark));
+    }
+
+    let tracking = format!("origin/{}", branch);
+    let Ok(tracking_sha) = resolve_ref(dir, &format!("refs/remotes/{}", tracking)) else {
+        return Err(("skipped", format!("{} doesn't exist", tracking)));
+    };
+
+    let local = format!("refs/heads/{}", branch);
+    let commits = match resolve_ref(dir, &local) {
+        Ok(_) => {
+            let range = format!("{}..{}", tracking, local);
+            let count = git_stdout(dir, &["rev-list", "--count", &range]).map_err(failed)?;
+            count.trim().parse().unwrap_or(0)
+        }
+        Err(_) => 0,
+    };
+
+    let untracked = if options.clean {
+        let dry_run = git_stdout(dir, &["clean", "--dry-run", "-d"]).map_err(failed)?;
+        dry_run.lines().count()
+    } else {
+        0
+    };
+
+    if !switch && head.sha == tracking_sha && head.changed == 0 && untracked == 0 {
+        return Err(("unchanged", format!("already at {}", tracking)));
+    }
+
+    Ok(ResetPlan {
+        name: target.name.clone(),
+        dir: dir.clone(),
+        branch,
+        switch,
+        commits,
+        changed: head.changed,
+        untracked,
+    })
+}
+
+/// Describe what resetting the repo does, e.g. 'to origin/master, discarding 2 ahead, 1 changed'
+fn describe_reset(plan: &ResetPlan) -> String {
+    let mut remark = format!("to origin/{}", plan.branch);
+    if plan.switch {
+        remark.push_str(&format!(" (checking out {})", plan.branch));
+    }
+
+    let discarded: Vec<String> = [
+        (plan.commits, "ahead"),
+        (plan.changed, "changed"),
+        (plan.untracked, "untracked"),
+    ]
+    .iter()
+    .filter(|(count, _)| *count > 0)
+    .map(|(count, what)| format!("{} {}", count, what))
+    .collect();
+
+    if !discarded.is_empty() {
+        remark.push_str(&format!(", discarding {}", discarded.join(", ")));
+    }
+    remark
+}
+
+/// Reset the repo as planned, and remove untracked files with clean
+fn reset_repo(plan: &ResetPlan, clean: bool) -> Result<(), SuperError> {
+    let tracking = format!("origin/{}", plan.branch);
+    if plan.switch {
+        run_git(
+            &plan.dir,
+            &[
+                "checkout",
+                "--quiet",
+                "--force",
+                "-B",
+                &plan.branch,
+                &tracking,
+            ],
+        )?;
+    } else {
+        run_git(&plan.dir, &["reset", "--quiet", "--hard", &tracking])?;
+    }
+
+    if clean {
+        run_git(&plan.dir, &["clean", "--quiet", "--force", "-d"])?;
+    }
+
+    Ok(())
+}
@@ -0,0 +1,113 @@
+//! 'super stash', which stashes or pops the uncommitted changes of all repos
+
+use crate::cli::StashOptions;
+use crate::for_each_repo;
+use crate::get_status_repos;
+use crate::git_stdout;
+use crate::init_layout;
+use crate::initialized_repos;
+use crate::is_dirty;
+use crate::print_repo_line;
+use crate::run_git;
+use crate::SuperError;
+use std::path::Path;
+
+/// The message of the stash entries that 'super stash' makes, so that 'super stash pop' only pops
+/// those
+const STASH_MESSAGE: &str = "super stash";
+
+/// Stash the uncommitted changes of all repos that have any, or pop the stash entries that this
+/// made again, by running 'git stash' in all repos in parallel
+pub(crate) fn command_stash(options: &StashOptions) -> Result<(), SuperError> {
+    let repos = get_status_repos()?;
+    init_layout(repos.iter().map(|(name, _)| name.as_str()));
+
+    // Repos that are not initialized can't have any changes
+    let repos = initialized_repos(repos);
+    let results = for_each_repo(&repos, |(_, repo_dir)| {
+        if options.pop {
+            pop_repo(repo_dir)
+        } else {
+            stash_repo(repo_dir, options.include_untracked)
+        }
+    });
+
+    let mut conflicts = vec![];
+    for ((name, _), (status, remark)) in results {
+        print_repo_line(name, status, &remark);
+        if status == "conflict" {
+            conflicts.push(name.as_str());
+        }
+    }
+
+    if !conflicts.is_empty() {
+        println!(
+            "Conflicts in {}: {}. Their stash entries were kept, resolve the conflicts and run \
+             'git stash drop' in them.",
+            conflicts.len(),
+            conflicts.join(", ")
+        );
+    }
+
+    Ok(())
+}
+
+/// Stash the uncommitted changes of a single repo, and return its status and remark for
+/// 'super stash'
+fn stash_repo(repo_dir: &Path, include_untracked: bool) -> (&'static str, String) {
+    let dirty = if include_untracked {
+        git_stdout(repo_dir, &["status", "--porcelain"]).map(|status| !status.is_empty())
+    } else {
+        is_dirty(repo_dir)
+    };
+
+    match dirty {
+        Ok(true) => (),
+        Ok(false) => return ("clean", "nothing to stash".to_string()),
+        Err(error) => return ("failed", error.to_string()),
+    }
+
+    let mut args = vec!["stash", "push", "--quiet", "--message", STASH_MESSAGE];
+    if include_untracked {
+        args.push("--include-untracked");
+    }
+
+    match run_git(repo_dir, &args) {
+        Ok(_) => ("stashed", String::new()),
+        Err(error) => ("failed", error.to_string()),
+    }
+}
+
+/// Pop the stash entry that 'super stash' made in a single repo, and return its status and remark
+/// for 'super stash pop'. Stash entries that were made by hand are left alone.
+fn pop_repo(repo_dir: &Path) -> (&'static str, String) {
+    // The subject of a stash entry is '<branch>: <message>', or 'On <branch>: <message>'
+    let subject = match git_stdout(
+        repo_dir,
+        &["stash", "list", "--max-count=1", "--format=%gs"],
+    ) {
+        Ok(subject) => subject,
+        Err(error) => return ("failed", error.to_string()),
+    };
+    if !subject
+        .trim_end()
+        .ends_with(&format!(": {}", STASH_MESSAGE))
+    {
+        return ("clean", "nothing to pop".to_string());
+    }
+
+    match run_git(repo_dir, &["stash", "pop", "--quiet"]) {
+        Ok(_) => ("popped", String::new()),
+        Err(error) => {
+            // git keeps the stash entry if popping it conflicts, and leaves the conflicted files
+            // unmerged
+            match git_stdout(repo_dir, &["diff", "--name-only", "--diff-filter=U"]) {
+                Ok(unmerged) if !unmerged.trim().is_empty() => (
+                    "conflict",
+                    unmerged.lines().collect::<Vec<&str>>().join(", "),
+                ),
+                _ => ("failed", error.to_string()),
+            }
+        }
+    }
+}
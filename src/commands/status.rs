@@ -0,0 +1,304 @@
+//! 'super status', which shows the branch and the state of each repo
+
+use crate::check_git_locks;
+use crate::cli::PullOptions;
+use crate::cli::StatusOptions;
+use crate::count_status;
+use crate::for_each_repo;
+use crate::format_repo_status;
+use crate::get_default_branch;
+use crate::get_head_state;
+use crate::get_status_repos;
+use crate::get_uncommitted_submodules;
+use crate::git_fetch;
+use crate::init_layout;
+use crate::print_health_footer;
+use crate::print_repo_line;
+use crate::resolve_ref;
+use crate::short_hash;
+use crate::HeadState;
+use crate::LoggedOutput;
+use crate::SuperError;
+use git2::Repository;
+use std::path::Path;
+use std::path::PathBuf;
+use std::process::Command;
+
+/// How the branch of a repo can relate to its upstream, as shown by 'super status --fetch', in the
+/// order of its summary line
+const DIVERGENCES: [&str; 5] = ["can-ff", "behind", "ahead", "diverged", "up to date"];
+
+/// Show the branch and the state of the working tree of all repos, by querying them in parallel
+pub(crate) fn command_status(options: &StatusOptions) -> Result<(), SuperError> {
+    let repos = get_status_repos()?;
+
+    let names: Vec<&str> = repos.iter().map(|(name, _)| name.as_str()).collect();
+    if let Err(error) = options.repos.validate(&names) {
+        println!("{}", error);
+        return Ok(());
+    }
+    let repos: Vec<(String, PathBuf)> = repos
+        .into_iter()
+        .filter(|(name, _)| options.repos.includes(name))
+        .collect();
+
+    init_layout(repos.iter().map(|(name, _)| name.as_str()));
+
+    // Without a super repo, there are no commits of submodules that it could record
+    let pointers = match Repository::open(".") {
+        Ok(repo) if options.index => get_uncommitted_submodules(&repo)?,
+        _ => vec![],
+    };
+
+    // The repos are fetched just like 'super fetch' fetches them
+    let fetch_options = options.fetch.then(|| PullOptions {
+        fetch_only: true,
+        ..Default::default()
+    });
+
+    // None for the repos that are not initialized
+    let heads = for_each_repo(&repos, |(_, repo_dir)| {
+        // The directory of a submodule that is not initialized is empty, so git would run in the
+        // super repo instead
+        if !repo_dir.join(".git").exists() {
+            return None;
+        }
+
+        // After a fetch, the upstreams are fresh enough to tell what 'super pull' would do
+        let head = if let Some(fetch_options) = &fetch_options {
+            fetch_upstream(repo_dir, fetch_options)
+                .and_then(|_| get_head_state(repo_dir))
+                .and_then(|head| {
+                    let divergence = get_divergence(repo_dir, &head)?;
+                    Ok((head, divergence))
+                })
+        } else {
+            get_head_state(repo_dir).map(|head| (head, None))
+        };
+        Some(head)
+    });
+
+    let mut filtered = 0;
+    let mut divergences: Vec<&str> = vec![];
+    let mut health: Vec<(String, usize)> = vec![];
+    let (mut clean, mut dirty, mut failed) = (0, 0, 0);
+    for ((name, _), head) in heads {
+        let Some(head) = head else {
+            let remark = "not initialized, run 'super pull --init'";
+            failed += 1;
+            if options.porcelain {
+                eprintln!("{}: {}", name, remark);
+                print_porcelain_status_line(name, None);
+            } else {
+                print_repo_line(name, "missing", remark);
+                count_status(&mut health, "missing");
+            }
+            continue;
+        };
+
+        // The summary line counts all repos, including the ones that the filter leaves out
+        match &head {
+            Ok((head, _)) if head.changed > 0 => dirty += 1,
+            Ok(_) => clean += 1,
+            Err(_) => failed += 1,
+        }
+
+        match head {
+            Ok((head, _)) if options.filter.is_some_and(|filter| !filter.matches(&head)) => {
+                filtered += 1
+            }
+            Ok((head, _)) if options.porcelain => print_porcelain_status_line(name, Some(&head)),
+            Ok((head, divergence)) => {
+                let (status, mut remark) = format_repo_status(&head, options.ahead_behind);
+                if let Some(divergence) = divergence {
+                    remark.push_str(&format!(", {}", divergence));
+                    divergences.push(divergence);
+                }
+                if options.index {
+                    let pointer = pointers
+                        .iter()
+                        .find(|(pointer_name, _)| pointer_name == name)
+                        .map_or("unchanged", |(_, state)| state);
+                    remark.push_str(&format!(", worktree: {}, pointer: {}", status, pointer));
+                }
+                print_repo_line(name, status, &remark);
+                count_status(&mut health, health_category(&head));
+            }
+            Err(error) if options.porcelain => {
+                // stdout only has the status lines, so that scripts don't have to skip anything
+                eprintln!("{}: {}", name, error);
+                print_porcelain_status_line(name, None);
+            }
+            Err(error) => {
+                print_repo_line(name, "failed", &error.to_string());
+                count_status(&mut health, "failed");
+            }
+        }
+    }
+
+    if !divergences.is_empty() {
+        let counts: Vec<String> = DIVERGENCES
+            .iter()
+            .map(|class| (class, divergences.iter().filter(|d| *d == class).count()))
+            .filter(|(_, count)| *count > 0)
+            .map(|(class, count)| format!("{} {}", count, class))
+            .collect();
+        println!("{}", counts.join(", "));
+    }
+
+    if let Some(filter) = options.filter {
+        let message = format!(
+            "Filtered out {} repos that are not {}",
+            filtered,
+            filter.to_str()
+        );
+        if options.porcelain {
+            eprintln!("{}", message);
+        } else {
+            println!("{}", message);
+        }
+    }
+
+    print_health_footer(&health);
+
+    // A machine-readable summary with stable keys, like the one of 'super pull'
+    eprintln!(
+        "super: total={} clean={} dirty={} failed={}",
+        clean + dirty + failed,
+        clean,
+        dirty,
+        failed
+    );
+
+    Ok(())
+}
+
+/// Fetch the branch that the repo tracks for 'super status --fetch', with git_fetch like
+/// 'super pull' does. Fails if git left a lock file behind in the repo.
+fn fetch_upstream(repo_dir: &Path, options: &PullOptions) -> Result<(), SuperError> {
+    if let Some(remark) = check_git_locks(repo_dir, options.force_unlock)? {
+        return Err(SuperError::Locked(remark));
+    }
+
+    // Repos without an upstream on origin are fetched like 'super pull' fetches them
+    let head = get_head_state(repo_dir)?;
+    let branch = match head
+        .upstream
+        .as_deref()
+        .and_then(|upstream| upstream.strip_prefix("origin/"))
+    {
+        Some(branch) => branch.to_string(),
+        None => get_default_branch(repo_dir),
+    };
+
+    match git_fetch(repo_dir, &branch, options).error {
+        Some(error) => Err(error),
+        None => Ok(()),
+    }
+}
+
+/// Return the one state of the repo that the footer of 'super status' counts it under. A detached
+/// HEAD and uncommitted changes need attention first, so they take precedence over the distance to
+/// the upstream.
+fn health_category(head: &HeadState) -> &'static str {
+    if head.branch.is_empty() {
+        return "detached";
+    }
+    if head.changed > 0 {
+        return "dirty";
+    }
+
+    match head.ahead_behind {
+        Some((ahead, behind)) if ahead > 0 && behind > 0 => "diverged",
+        Some((_, behind)) if behind > 0 => "behind",
+        Some((ahead, _)) if ahead > 0 => "ahead",
+        _ => "clean",
+    }
+}
+
+/// Classify how the branch of the repo relates to its upstream, which tells what 'super pull' would
+/// do with it. Returns one of DIVERGENCES, or None if HEAD is detached or the branch has no
+/// upstream (anymore).
+fn get_divergence(repo_dir: &Path, head: &HeadState) -> Result<Option<&'static str>, SuperError> {
+    let Some(upstream) = head.upstream.as_deref().filter(|_| !head.branch.is_empty()) else {
+        return Ok(None);
+    };
+    let Ok(upstream_sha) = resolve_ref(repo_dir, upstream) else {
+        return Ok(None);
+    };
+    if upstream_sha == head.sha {
+        return Ok(Some("up to date"));
+    }
+
+    let divergence = if is_ancestor(repo_dir, &head.sha, &upstream_sha)? {
+        // git may refuse to fast-forward over uncommitted changes, unless they are stashed first
+        if head.changed == 0 {
+            "can-ff"
+        } else {
+            "behind"
+        }
+    } else if is_ancestor(repo_dir, &upstream_sha, &head.sha)? {
+        "ahead"
+    } else {
+        "diverged"
+    };
+    Ok(Some(divergence))
+}
+
+/// Check whether the first commit is an ancestor of the second, with
+/// 'git merge-base --is-ancestor'. It exits with 1 if it isn't, and with another status if it
+/// failed, e.g. because a shallow repo doesn't have one of the commits. run_git can't tell these
+/// apart, so git is run directly here.
+fn is_ancestor(repo_dir: &Path, ancestor: &str, descendant: &str) -> Result<bool, SuperError> {
+    let args = ["merge-base", "--is-ancestor", ancestor, descendant];
+    let output = Command::new("git")
+        .args(args)
+        .current_dir(repo_dir)
+        .logged_output()
+        .map_err(|error| SuperError::Spawn {
+            program: "git".to_string(),
+            error,
+        })?;
+
+    match output.status.code() {
+        Some(0) => Ok(true),
+        Some(1) => Ok(false),
+        _ => Err(SuperError::Git {
+            args: args.join(" "),
+            stderr: String::from_utf8_lossy(&output.stderr).to_string(),
+        }),
+    }
+}
+
+/// Print the status of the given repo for 'super status --porcelain', as
+/// 'name<TAB>status<TAB>branch<TAB>ahead<TAB>behind<TAB>short_sha'. Values that don't apply (e.g.
+/// the branch of a detached HEAD) are '-'. The status is 'failed' if the repo couldn't be queried.
+///
+/// Scripts rely on this format, so columns must only ever be added at the end.
+fn print_porcelain_status_line(repo: &str, head: Option<&HeadState>) {
+    let Some(head) = head else {
+        println!("{}\tfailed\t-\t-\t-\t-", repo);
+        return;
+    };
+
+    let status = if head.changed > 0 { "dirty" } else { "clean" };
+    let branch = if head.branch.is_empty() {
+        "-"
+    } else {
+        &head.branch
+    };
+    let (ahead, behind) = match head.ahead_behind {
+        Some((ahead, behind)) => (ahead.to_string(), behind.to_string()),
+        None => ("-".to_string(), "-".to_string()),
+    };
+    let sha = if head.sha.is_empty() {
+        "-"
+    } else {
+        short_hash(&head.sha)
+    };
+
+    println!(
+        "{}\t{}\t{}\t{}\t{}\t{}",
+        repo, status, branch, ahead, behind, sha
+    );
+}
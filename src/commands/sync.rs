@@ -0,0 +1,127 @@
+//! 'super sync', which clones and updates all submodules in parallel
+
+use crate::count_status;
+use crate::for_each_repo;
+use crate::get_head_sha;
+use crate::git_stdout;
+use crate::init_layout;
+use crate::no_submodules_message;
+use crate::print_health_footer;
+use crate::print_repo_line;
+use crate::run_git;
+use crate::short_hash;
+use crate::submodule_name;
+use crate::SuperError;
+use git2::Repository;
+use std::path::Path;
+
+/// Initialize all submodules, sync their URLs from .gitmodules and update them to the commits that
+/// the super repo records, like 'git submodule sync' and 'git submodule update --init' do, but with
+/// the submodules updated in parallel
+///
+/// git writes to the config of the super repo when it initializes or syncs a submodule, which can't
+/// be done from several processes at once, so that is done for all submodules in one go first.
+/// Only the clones and checkouts, which are the slow part, run in parallel.
+pub(crate) fn command_sync() -> Result<(), SuperError> {
+    let repo = Repository::open(".")?;
+    let submodules = repo.submodules()?;
+    if submodules.is_empty() {
+        println!("{}", no_submodules_message(0));
+        return Ok(());
+    }
+
+    let names: Vec<String> = submodules.iter().map(submodule_name).collect();
+    init_layout(names.iter().map(String::as_str));
+
+    // Vector of (repo_name, repo_path, origin) tuples, where origin is the URL that the submodule
+    // fetched from before the sync, or None if it isn't cloned yet
+    let mut targets = vec![];
+    let mut counts = vec![];
+    for (submodule, name) in submodules.iter().zip(names) {
+        if matches!(submodule.update_strategy(), git2::SubmoduleUpdate::None) {
+            print_repo_line(&name, "skipped", "update=none");
+            count_status(&mut counts, "skipped");
+            continue;
+        }
+
+        let path = submodule.path().to_path_buf();
+        let origin = if path.join(".git").exists() {
+            git_stdout(&path, &["remote", "get-url", "origin"]).ok()
+        } else {
+            None
+        };
+        targets.push((name, path, origin));
+    }
+
+    if targets.is_empty() {
+        print_health_footer(&counts);
+        return Ok(());
+    }
+
+    let paths: Vec<String> = targets
+        .iter()
+        .map(|(_, path, _)| path.to_string_lossy().to_string())
+        .collect();
+    let mut args = vec!["submodule", "--quiet", "init", "--"];
+    args.extend(paths.iter().map(String::as_str));
+    run_git(Path::new("."), &args)?;
+    args[2] = "sync";
+    run_git(Path::new("."), &args)?;
+
+    let results = for_each_repo(&targets, |(_, path, origin)| {
+        sync_repo(&path.to_string_lossy(), origin.as_deref())
+    });
+
+    let mut failed = vec![];
+    for ((name, _, _), (status, remark)) in results {
+        print_repo_line(name, status, &remark);
+        count_status(&mut counts, status);
+        if status == "failed" {
+            failed.push(name.as_str());
+        }
+    }
+
+    print_health_footer(&counts);
+    if !failed.is_empty() {
+        println!("Failed to sync {}: {}", failed.len(), failed.join(", "));
+    }
+
+    Ok(())
+}
+
+/// Update a single submodule to the commit that the super repo records, cloning it if necessary,
+/// and return its status and remark for 'super sync'. origin is the URL that it fetched from before
+/// its URL was synced, or None if it wasn't cloned yet.
+fn sync_repo(path: &str, origin: Option<&str>) -> (&'static str, String) {
+    let repo_dir = Path::new(path);
+    let head = match origin {
+        Some(_) => match get_head_sha(repo_dir) {
+            Ok(sha) => Some(sha),
+            Err(error) => return ("failed", error.to_string()),
+        },
+        None => None,
+    };
+
+    // Each submodule is updated by its own 'git submodule update', which only writes to the
+    // submodule itself, since it is initialized already
+    let args = ["submodule", "--quiet", "update", "--", path];
+    if let Err(error) = run_git(Path::new("."), &args) {
+        return ("failed", error.to_string());
+    }
+
+    let new_head = match get_head_sha(repo_dir) {
+        Ok(sha) => sha,
+        Err(error) => return ("failed", error.to_string()),
+    };
+    let new_origin = git_stdout(repo_dir, &["remote", "get-url", "origin"]).unwrap_or_default();
+
+    match (head, origin) {
+        (Some(head), _) if head != new_head => (
+            "updated",
+            format!("{} -> {}", short_hash(&head), short_hash(&new_head)),
+        ),
+        (Some(_), Some(origin)) if origin != new_origin => ("synced-url", new_origin),
+        (Some(_), _) => ("unchanged", format!("at {}", short_hash(&new_head))),
+        (None, _) => ("cloned", format!("at {}", short_hash(&new_head))),
+    }
+}
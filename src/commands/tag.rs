@@ -0,0 +1,99 @@
+//! 'super tag', which tags all repos at their current commit
+
+use crate::cli::TagOptions;
+use crate::for_each_repo;
+use crate::get_head_sha;
+use crate::get_status_repos;
+use crate::init_layout;
+use crate::initialized_repos;
+use crate::print_repo_line;
+use crate::resolve_ref;
+use crate::run_git;
+use crate::short_hash;
+use crate::SuperError;
+use std::path::Path;
+
+/// Tag the current commit of all repos with the same tag, by running 'git tag' in all repos in
+/// parallel
+pub(crate) fn command_tag(options: &TagOptions) -> Result<(), SuperError> {
+    // git would reject an invalid name in every single repo
+    if run_git(
+        Path::new("."),
+        &["check-ref-format", &format!("refs/tags/{}", options.name)],
+    )
+    .is_err()
+    {
+        println!("Not a valid tag name: {}", options.name);
+        return Ok(());
+    }
+
+    let repos = get_status_repos()?;
+    init_layout(repos.iter().map(|(name, _)| name.as_str()));
+
+    // Repos that are not initialized don't have a commit to tag
+    let repos = initialized_repos(repos);
+    let results = for_each_repo(&repos, |(_, repo_dir)| tag_repo(repo_dir, options));
+
+    let mut failed = vec![];
+    for ((name, _), (status, remark)) in results {
+        print_repo_line(name, status, &remark);
+        if status == "failed" {
+            failed.push(name.as_str());
+        }
+    }
+
+    if !failed.is_empty() {
+        println!("Failed to tag {}: {}", failed.len(), failed.join(", "));
+    }
+
+    Ok(())
+}
+
+/// Tag the current commit of a single repo, and push the tag if asked to. Returns the status and
+/// remark of the repo for 'super tag'.
+fn tag_repo(repo_dir: &Path, options: &TagOptions) -> (&'static str, String) {
+    let tag_ref = format!("refs/tags/{}", options.name);
+    if !options.force {
+        if let Ok(sha) = resolve_ref(repo_dir, &tag_ref) {
+            let remark = format!("tag already exists at {}", short_hash(&sha));
+            return ("skipped", remark);
+        }
+    }
+
+    let mut args = vec!["tag"];
+    if let Some(message) = &options.message {
+        args.extend(["--annotate", "--message", message]);
+    }
+    if options.force {
+        args.push("--force");
+    }
+    args.push(&options.name);
+
+    if let Err(error) = run_git(repo_dir, &args) {
+        return ("failed", error.to_string());
+    }
+    let sha = match get_head_sha(repo_dir) {
+        Ok(sha) => sha,
+        Err(error) => return ("failed", error.to_string()),
+    };
+    let short_sha = short_hash(&sha);
+
+    if !options.push {
+        return ("tagged", short_sha.to_string());
+    }
+
+    // The full ref, so that a branch with the same name doesn't get pushed instead
+    let mut args = vec!["push", "--quiet"];
+    if options.force {
+        args.push("--force");
+    }
+    args.extend(["origin", &tag_ref]);
+
+    match run_git(repo_dir, &args) {
+        Ok(_) => ("tagged", format!("{}, pushed", short_sha)),
+        Err(error) => (
+            "failed",
+            format!("tagged {}, but the push failed: {}", short_sha, error),
+        ),
+    }
+}
@@ -0,0 +1,301 @@
+//! 'super tui', which shows an interactive dashboard of all repos
+
+use crate::cli::PullOptions;
+use crate::format_repo_status;
+use crate::get_default_branch;
+use crate::get_head_state;
+use crate::get_pull_targets;
+use crate::git_fetch;
+use crate::git_stdout;
+use crate::pull_single_repo;
+use crate::Layout;
+use crate::PullTarget;
+use crate::SuperError;
+use std::io::IsTerminal;
+use std::sync::mpsc;
+use std::thread;
+use std::time::Duration;
+
+/// A repo in the list of 'super tui'
+struct TuiRepo {
+    target: PullTarget,
+    status: String,
+    remark: String,
+    /// Whether a pull or fetch of the repo is running, so that it isn't started twice
+    busy: bool,
+}
+
+/// What 'super tui' can do with a repo in the background
+#[derive(Clone, Copy)]
+enum TuiAction {
+    Status,
+    Fetch,
+    Pull,
+    Log,
+}
+
+/// What the threads of 'super tui' send back to the main thread, which is the only one that draws
+enum TuiUpdate {
+    /// The new status and remark of the repo with the given index
+    Status(usize, String, String),
+    /// The recent log of the repo with the given index
+    Log(usize, String),
+}
+
+/// Restores the terminal when 'super tui' ends, including when it ends with an error
+struct TerminalGuard;
+
+impl Drop for TerminalGuard {
+    fn drop(&mut self) {
+        let _ = crossterm::terminal::disable_raw_mode();
+        let _ = crossterm::execute!(std::io::stdout(), crossterm::terminal::LeaveAlternateScreen);
+    }
+}
+
+/// Show an interactive list of all repos with their status, in which the selected repo can be
+/// pulled (p), fetched (f), or its recent log shown (enter). All of this runs in background
+/// threads, so that the list stays responsive while repos are being fetched.
+pub(crate) fn command_tui() -> Result<(), SuperError> {
+    if !std::io::stdout().is_terminal() {
+        println!("super tui needs a terminal");
+        return Ok(());
+    }
+
+    let mut repos: Vec<TuiRepo> = get_pull_targets()?
+        .into_iter()
+        .map(|target| TuiRepo {
+            target,
+            status: "loading".to_string(),
+            remark: String::new(),
+            busy: false,
+        })
+        .collect();
+
+    let (sender, receiver) = mpsc::channel();
+    for (index, repo) in repos.iter().enumerate() {
+        spawn_tui_action(index, &repo.target, TuiAction::Status, &sender);
+    }
+
+    crossterm::terminal::enable_raw_mode().map_err(SuperError::Io)?;
+    let _guard = TerminalGuard;
+    crossterm::execute!(std::io::stdout(), crossterm::terminal::EnterAlternateScreen)
+        .map_err(SuperError::Io)?;
+    let backend = ratatui::backend::CrosstermBackend::new(std::io::stdout());
+    let mut terminal = ratatui::Terminal::new(backend).map_err(SuperError::Io)?;
+
+    let mut list_state = ratatui::widgets::ListState::default();
+    if !repos.is_empty() {
+        list_state.select(Some(0));
+    }
+    // The repo whose log is shown, and the log itself
+    let mut log: Option<(usize, String)> = None;
+
+    loop {
+        for update in receiver.try_iter() {
+            match update {
+                TuiUpdate::Status(index, status, remark) => {
+                    let repo = &mut repos[index];
+                    repo.status = status;
+                    repo.remark = remark;
+                    repo.busy = false;
+                }
+                TuiUpdate::Log(index, text) => log = Some((index, text)),
+            }
+        }
+
+        terminal
+            .draw(|frame| draw_tui(frame, &repos, &mut list_state, log.as_ref()))
+            .map_err(SuperError::Io)?;
+
+        // Wake up regularly even without key presses, to show the updates of the threads
+        if !crossterm::event::poll(Duration::from_millis(100)).map_err(SuperError::Io)? {
+            continue;
+        }
+        let crossterm::event::Event::Key(key) = crossterm::event::read().map_err(SuperError::Io)?
+        else {
+            continue;
+        };
+        if key.kind != crossterm::event::KeyEventKind::Press {
+            continue;
+        }
+
+        // In raw mode, Ctrl-C is a key press instead of an interrupt
+        use crossterm::event::KeyCode;
+        let ctrl = key
+            .modifiers
+            .contains(crossterm::event::KeyModifiers::CONTROL);
+        let Some(selected) = list_state.selected() else {
+            match key.code {
+                KeyCode::Char('q') | KeyCode::Esc => return Ok(()),
+                KeyCode::Char('c') if ctrl => return Ok(()),
+                _ => continue,
+            }
+        };
+
+        let action = match key.code {
+            KeyCode::Char('q') | KeyCode::Esc => return Ok(()),
+            KeyCode::Char('c') if ctrl => return Ok(()),
+            KeyCode::Up | KeyCode::Char('k') => {
+                list_state.select(Some(selected.saturating_sub(1)));
+                continue;
+            }
+            KeyCode::Down | KeyCode::Char('j') => {
+                list_state.select(Some((selected + 1).min(repos.len() - 1)));
+                continue;
+            }
+            KeyCode::Char('p') => TuiAction::Pull,
+            KeyCode::Char('f') => TuiAction::Fetch,
+            KeyCode::Enter => TuiAction::Log,
+            _ => continue,
+        };
+
+        let repo = &mut repos[selected];
+        match action {
+            TuiAction::Pull | TuiAction::Fetch if repo.busy => continue,
+            TuiAction::Pull => repo.status = "pulling".to_string(),
+            TuiAction::Fetch => repo.status = "fetching".to_string(),
+            TuiAction::Status | TuiAction::Log => (),
+        }
+        if matches!(action, TuiAction::Pull | TuiAction::Fetch) {
+            repo.busy = true;
+        }
+        spawn_tui_action(selected, &repo.target, action, &sender);
+    }
+}
+
+/// Run the action for the repo with the given index in a background thread, which sends the
+/// outcome back to the main thread of 'super tui'
+fn spawn_tui_action(
+    index: usize,
+    target: &PullTarget,
+    action: TuiAction,
+    sender: &mpsc::Sender<TuiUpdate>,
+) {
+    let target = target.clone();
+    let sender = sender.clone();
+    thread::spawn(move || {
+        let update = run_tui_action(index, &target, action);
+        // The dashboard may have been closed in the meantime, which is fine
+        let _ = sender.send(update);
+    });
+}
+
+/// Run the action for the repo with the given index, and return what changed
+fn run_tui_action(index: usize, target: &PullTarget, action: TuiAction) -> TuiUpdate {
+    // The directory of a submodule that is not initialized is empty, so git would run in the super
+    // repo instead
+    if !target.dir.join(".git").exists() {
+        let remark = "not initialized, run 'super pull --init'".to_string();
+        return TuiUpdate::Status(index, "missing".to_string(), remark);
+    }
+
+    let head = match action {
+        TuiAction::Pull => {
+            let result = pull_single_repo(target, &PullOptions::default());
+            return TuiUpdate::Status(index, result.status.to_string(), result.remark);
+        }
+        TuiAction::Log => {
+            let log = git_stdout(&target.dir, &["log", "--oneline", "--decorate", "-n", "50"])
+                .unwrap_or_else(|error| error.to_string());
+            return TuiUpdate::Log(index, log);
+        }
+        // The same branch that 'super pull' fetches, with the same retries
+        TuiAction::Fetch => {
+            let branch = match &target.branch {
+                Some(branch) => branch.clone(),
+                None => get_default_branch(&target.dir),
+            };
+            match git_fetch(&target.dir, &branch, &PullOptions::default()).error {
+                Some(error) => Err(error),
+                None => get_head_state(&target.dir),
+            }
+        }
+        TuiAction::Status => get_head_state(&target.dir),
+    };
+
+    match head {
+        Ok(head) => {
+            let (status, remark) = format_repo_status(&head, false);
+            TuiUpdate::Status(index, status.to_string(), remark)
+        }
+        Err(error) => TuiUpdate::Status(index, "failed".to_string(), error.to_string()),
+    }
+}
+
+/// Draw the list of repos of 'super tui', with the log of a repo below it if one was requested, and
+/// a line with the keys at the bottom
+fn draw_tui(
+    frame: &mut ratatui::Frame,
+    repos: &[TuiRepo],
+    list_state: &mut ratatui::widgets::ListState,
+    log: Option<&(usize, String)>,
+) {
+    use ratatui::layout::Constraint;
+    use ratatui::style::{Color, Modifier, Style};
+    use ratatui::text::{Line, Span};
+    use ratatui::widgets::{Block, Borders, List, ListItem, Paragraph};
+
+    let width = repos
+        .iter()
+        .map(|repo| repo.target.name.chars().count())
+        .max()
+        .unwrap_or(0);
+    let status_width = Layout::STATUS;
+
+    // The same colors as the output of the other commands, except that problems stand out
+    let items: Vec<ListItem> = repos
+        .iter()
+        .map(|repo| {
+            let status_color = match repo.status.as_str() {
+                "failed" | "stash conflict" | "missing" | "locked" => Color::Red,
+                "dirty" | "diverged" | "detached" => Color::Yellow,
+                "loading" | "pulling" | "fetching" => Color::DarkGray,
+                _ => Color::Cyan,
+            };
+            ListItem::new(Line::from(vec![
+                Span::styled(
+                    format!("{:width$} ", repo.target.name),
+                    Style::default()
+                        .fg(Color::Indexed(198))
+                        .add_modifier(Modifier::BOLD),
+                ),
+                Span::styled(
+                    format!("{:status_width$}   ", repo.status),
+                    Style::default()
+                        .fg(status_color)
+                        .add_modifier(Modifier::BOLD),
+                ),
+                Span::raw(repo.remark.as_str()),
+            ]))
+        })
+        .collect();
+    let list = List::new(items)
+        .block(Block::default().borders(Borders::ALL).title(" super "))
+        .highlight_style(Style::default().add_modifier(Modifier::REVERSED));
+
+    let constraints = match log {
+        Some(_) => vec![
+            Constraint::Min(3),
+            Constraint::Percentage(50),
+            Constraint::Length(1),
+        ],
+        None => vec![Constraint::Min(3), Constraint::Length(1)],
+    };
+    let areas = ratatui::layout::Layout::default()
+        .direction(ratatui::layout::Direction::Vertical)
+        .constraints(constraints)
+        .split(frame.size());
+
+    frame.render_stateful_widget(list, areas[0], list_state);
+    if let Some((index, text)) = log {
+        let title = format!(" log of {} ", repos[*index].target.name);
+        let paragraph = Paragraph::new(text.as_str())
+            .block(Block::default().borders(Borders::ALL).title(title));
+        frame.render_widget(paragraph, areas[1]);
+    }
+    let keys = "↑/↓ select   p pull   f fetch   enter log   q quit";
+    frame.render_widget(
+        Paragraph::new(Span::styled(keys, Style::default().fg(Color::DarkGray))),
+        areas[areas.len() - 1],
+    );
+}
@@ -0,0 +1,111 @@
+//! 'super undo', which resets repos to the commit that the super repo records
+
+use crate::checkout;
+use crate::cli::UndoOptions;
+use crate::confirm;
+use crate::init_layout;
+use crate::is_dirty;
+use crate::print_repo_line;
+use crate::short_hash;
+use crate::submodule_name;
+use crate::suggest_repo_names;
+use crate::SuperError;
+use git2::Repository;
+use std::path::PathBuf;
+
+/// Reset submodules to the commit that the super repo records for them in its index
+pub(crate) fn command_undo(options: &UndoOptions) -> Result<(), SuperError> {
+    let repo: Repository = Repository::open(".")?;
+    let submodules = repo.submodules()?;
+
+    let names: Vec<String> = submodules.iter().map(submodule_name).collect();
+    let name_refs: Vec<&str> = names.iter().map(String::as_str).collect();
+    if let Some(name) = &options.repo {
+        if !name_refs.contains(&name.as_str()) {
+            println!(
+                "Unknown repo: {}{}",
+                name,
+                suggest_repo_names(name, &name_refs)
+            );
+            return Ok(());
+        }
+    }
+
+    // First find all repos that have to be reset, so that we can ask for confirmation before we
+    // change anything. Each entry is a (name, repo_dir, current, pinned) tuple.
+    let mut drifted: Vec<(&str, PathBuf, String, String)> = vec![];
+    init_layout(name_refs.iter().copied());
+
+    for (submodule, name) in submodules.iter().zip(name_refs) {
+        if options.repo.as_ref().is_some_and(|repo| repo != name) {
+            continue;
+        }
+
+        let repo_dir = submodule.path().to_path_buf();
+
+        let Some(pinned) = submodule.index_id() else {
+            print_repo_line(name, "skipped", "not recorded in the super repo");
+            continue;
+        };
+        let Some(current) = submodule.workdir_id() else {
+            print_repo_line(name, "skipped", "not initialized");
+            continue;
+        };
+
+        let pinned = pinned.to_string();
+        let current = current.to_string();
+
+        if pinned == current {
+            // With --all we only report the repos that actually drifted
+            if options.repo.is_some() {
+                let remark = format!("already at {}", short_hash(&pinned));
+                print_repo_line(name, "unchanged", &remark);
+            }
+            continue;
+        }
+
+        drifted.push((name, repo_dir, current, pinned));
+    }
+
+    if options.force && !options.yes {
+        let dirty = drifted
+            .iter()
+            .filter(|(_, repo_dir, _, _)| is_dirty(repo_dir).unwrap_or(false))
+            .count();
+
+        if dirty > 0 {
+            let prompt = format!("This will discard changes in {} repos. Continue?", dirty);
+            if !confirm(&prompt)? {
+                println!("Aborted.");
+                return Ok(());
+            }
+        }
+    }
+
+    for (name, repo_dir, current, pinned) in drifted {
+        if !options.force {
+            match is_dirty(&repo_dir) {
+                Ok(false) => (),
+                Ok(true) => {
+                    let remark = "has uncommitted changes (use --force to discard them)";
+                    print_repo_line(name, "skipped", remark);
+                    continue;
+                }
+                Err(error) => {
+                    print_repo_line(name, "failed", &error.to_string());
+                    continue;
+                }
+            }
+        }
+
+        match checkout(&repo_dir, &pinned, options.force) {
+            Ok(()) => {
+                let remark = format!("{} -> {}", short_hash(&current), short_hash(&pinned));
+                print_repo_line(name, "reset", &remark);
+            }
+            Err(error) => print_repo_line(name, "failed", &error.to_string()),
+        }
+    }
+
+    Ok(())
+}
@@ -0,0 +1,140 @@
+//! 'super upgrade', which replaces super with its latest release
+
+use crate::confirm;
+use crate::download;
+use crate::LoggedOutput;
+use crate::SuperError;
+use std::env;
+use std::fs;
+use std::os::unix::fs::PermissionsExt;
+use std::path::Path;
+use std::process::Command;
+
+/// The GitHub repo that 'super upgrade' downloads new releases from
+const RELEASES_REPO: &str = "rkrzr/super";
+
+/// Replace the running binary with the latest release from GitHub, if that is newer than this
+/// version
+pub(crate) fn command_upgrade(yes: bool) -> Result<(), SuperError> {
+    let api_url = format!(
+        "https://api.github.com/repos/{}/releases/latest",
+        RELEASES_REPO
+    );
+    let release = String::from_utf8_lossy(&download(&api_url)?).to_string();
+    let Some(tag) = json_field(&release, "tag_name") else {
+        println!("Failed to find the latest release in {}", api_url);
+        return Ok(());
+    };
+
+    let current = env!("CARGO_PKG_VERSION");
+    if !is_newer_version(&tag, current) {
+        println!("super {} is already the latest version", current);
+        return Ok(());
+    }
+
+    println!("super {} is available, you have {}", tag, current);
+    println!(
+        "Changelog: https://github.com/{}/releases/tag/{}",
+        RELEASES_REPO, tag
+    );
+    if !yes && !confirm("Replace the super binary with the new version?")? {
+        println!("Aborted, nothing was changed");
+        return Ok(());
+    }
+
+    // Release binaries are named after the platform that they run on, e.g. 'super-x86_64-linux',
+    // and come with a '<binary>.sha256' file
+    let binary_url = format!(
+        "https://github.com/{}/releases/download/{}/super-{}-{}",
+        RELEASES_REPO,
+        tag,
+        env::consts::ARCH,
+        env::consts::OS
+    );
+    let checksum = String::from_utf8_lossy(&download(&format!("{}.sha256", binary_url))?)
+        .split_whitespace()
+        .next()
+        .unwrap_or("")
+        .to_lowercase();
+
+    // The new binary is written next to the old one, so that it can be renamed over it, which
+    // replaces it in one go, even while it is running
+    let binary = env::current_exe().map_err(SuperError::Io)?;
+    let download_path = binary.with_file_name(".super-upgrade");
+    fs::write(&download_path, download(&binary_url)?).map_err(SuperError::Io)?;
+
+    let actual = sha256(&download_path)?;
+    if actual != checksum {
+        let _ = fs::remove_file(&download_path);
+        return Err(SuperError::UpgradeFailed(format!(
+            "the checksum of {} is {}, but the release says it should be {}",
+            binary_url, actual, checksum
+        )));
+    }
+
+    fs::set_permissions(&download_path, fs::Permissions::from_mode(0o755))
+        .map_err(SuperError::Io)?;
+    fs::rename(&download_path, &binary).map_err(SuperError::Io)?;
+    println!("Upgraded super to {}", tag);
+
+    Ok(())
+}
+
+/// Return the SHA-256 checksum of the given file as a lowercase hex string
+fn sha256(path: &Path) -> Result<String, SuperError> {
+    let (program, args): (&str, &[&str]) = if cfg!(target_os = "macos") {
+        ("shasum", &["-a", "256"])
+    } else {
+        ("sha256sum", &[])
+    };
+
+    let output = Command::new(program)
+        .args(args)
+        .arg(path)
+        .logged_output()
+        .map_err(|error| SuperError::Spawn {
+            program: program.to_string(),
+            error,
+        })?;
+
+    // The output looks like '<checksum>  <path>'
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    match stdout.split_whitespace().next() {
+        Some(checksum) if output.status.success() => Ok(checksum.to_lowercase()),
+        _ => Err(SuperError::UpgradeFailed(format!(
+            "failed to compute the checksum of {}",
+            path.display()
+        ))),
+    }
+}
+
+/// Return the value of the string field with the given key in a JSON object, e.g. the 'tag_name'
+/// of a GitHub release. This is not a full JSON parser: it takes the first field with that key,
+/// and the value must not contain escaped quotes, which holds for the fields that we read.
+fn json_field(json: &str, key: &str) -> Option<String> {
+    let quoted_key = format!("\"{}\"", key);
+    let start = json.find(&quoted_key)? + quoted_key.len();
+    let value = json[start..]
+        .trim_start()
+        .strip_prefix(':')?
+        .trim_start()
+        .strip_prefix('"')?;
+    Some(value[..value.find('"')?].to_string())
+}
+
+/// Check whether the version of a release tag (e.g. 'v0.2.0') is newer than the given version
+fn is_newer_version(tag: &str, current: &str) -> bool {
+    // Pre-release suffixes like '-rc1' are ignored
+    let parse = |version: &str| -> Vec<u64> {
+        version
+            .trim_start_matches('v')
+            .split('.')
+            .map(|part| {
+                let digits: String = part.chars().take_while(char::is_ascii_digit).collect();
+                digits.parse().unwrap_or(0)
+            })
+            .collect()
+    };
+
+    parse(tag) > parse(current)
+}
@@ -0,0 +1,14 @@
+//! 'super version', which prints the version of super
+
+use std::env;
+
+/// Print the version of super, the commit it was built from, and the version of libgit2
+pub(crate) fn command_version() {
+    let (major, minor, patch) = git2::Version::get().libgit2_version();
+    println!(
+        "super {} ({})",
+        env!("CARGO_PKG_VERSION"),
+        env!("SUPER_GIT_HASH")
+    );
+    println!("libgit2 {}.{}.{}", major, minor, patch);
+}
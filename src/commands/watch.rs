@@ -0,0 +1,53 @@
+//! 'super watch', which shows the status, and redraws it whenever a repo changes
+
+use super::status::command_status;
+use crate::cli::StatusOptions;
+use crate::get_status_repos;
+use crate::SuperError;
+use notify::Watcher;
+use std::path::Path;
+use std::sync::mpsc;
+use std::time::Duration;
+
+/// Show the status of all repos like 'super status', and show it again whenever a file in one of
+/// the repos changes, until super is interrupted with Ctrl-C
+///
+/// Nothing is fetched, so that redrawing stays cheap.
+pub(crate) fn command_watch() -> Result<(), SuperError> {
+    let (sender, receiver) = mpsc::channel();
+    let mut watcher = notify::recommended_watcher(sender)?;
+    for (_, repo_dir) in get_status_repos()? {
+        // Repos that are not initialized can't change
+        if repo_dir.is_dir() {
+            watcher.watch(&repo_dir, notify::RecursiveMode::Recursive)?;
+        }
+    }
+
+    loop {
+        // Clear the screen and move the cursor to the top, so that the status is redrawn in place
+        print!("\x1b[2J\x1b[H");
+        command_status(&StatusOptions::default())?;
+
+        // Wait for a change. Changes inside .git directories are ignored, because 'git status'
+        // itself can update the index, which would redraw the status over and over again.
+        loop {
+            match receiver.recv() {
+                Ok(Ok(event)) if event.paths.iter().any(|path| !is_in_git_dir(path)) => break,
+                Ok(Ok(_event)) => continue,
+                Ok(Err(error)) => return Err(SuperError::Watch(error)),
+                // The watcher is gone, so no more changes will come in
+                Err(_) => return Ok(()),
+            }
+        }
+
+        // Debounce: an editor or a git command usually changes several files in a row, so we wait
+        // until things have been quiet for a moment before redrawing
+        while receiver.recv_timeout(Duration::from_millis(200)).is_ok() {}
+    }
+}
+
+/// Check whether the path is inside a .git directory
+fn is_in_git_dir(path: &Path) -> bool {
+    path.components()
+        .any(|component| component.as_os_str() == ".git")
+}
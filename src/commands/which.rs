@@ -0,0 +1,60 @@
+//! 'super which', which shows whether a command is built in or a custom command
+
+use crate::get_executables;
+use crate::COMMANDS;
+use std::ffi::OsString;
+use std::path::PathBuf;
+
+/// Get the directory of the user's custom commands, i.e. ~/.config/super/commands
+fn get_commands_dir() -> Option<PathBuf> {
+    dirs::home_dir().map(|home_dir| home_dir.join(".config/super/commands"))
+}
+
+/// Get the user's custom commands from ~/.config/super/commands
+fn get_commands() -> Vec<OsString> {
+    let Some(commands_dir) = get_commands_dir() else {
+        eprintln!("Unable to determine the home directory");
+        return Vec::new();
+    };
+
+    // Custom commands are optional, so a missing directory is not an error
+    if !commands_dir.exists() {
+        return Vec::new();
+    }
+
+    match get_executables(&commands_dir) {
+        Ok(commands) => commands,
+        Err(error) => {
+            eprintln!("Error reading {:?}: {:?}", &commands_dir, error);
+            Vec::new()
+        }
+    }
+}
+
+/// Show whether the name is a built-in command of super or a custom command, and where the custom
+/// command is. A custom command with the name of a built-in one is shadowed by it.
+pub(crate) fn command_which(name: &str) {
+    let builtin = COMMANDS.iter().any(|(command, _)| *command == name);
+    let custom = get_commands()
+        .iter()
+        .any(|command| command.as_os_str() == name);
+    let path = get_commands_dir()
+        .map(|dir| dir.join(name))
+        .unwrap_or_default();
+
+    match (builtin, custom) {
+        (true, false) => println!("{} is a built-in command", name),
+        (false, true) => println!("{} is a custom command: {}", name, path.display()),
+        (true, true) => {
+            println!("{} is a built-in command", name);
+            println!(
+                "Warning: the custom command {} has the same name, and is shadowed by it",
+                path.display()
+            );
+        }
+        (false, false) => println!(
+            "{} is not a command. Run 'super --help' for a list of commands.",
+            name
+        ),
+    }
+}
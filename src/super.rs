@@ -2,15 +2,308 @@ const DOCUMENTATION: &str = "NAME
         super - manage all of your git repos in one super repository
 
 SYNOPSIS
-        super init - Initialize a new super repo for the first time. This is just a convenience wrapper
-             around 'git init'.
-
-        super add - Add a new repo to the super repo. This is just a convenience wrapper
-            around 'git submodule add'.
-
-        super pull - Update all repos in the super repo.
-
-        super foreach <command> - [TODO] Run a regular shell command for each repo in parallel
+        super [-v | --verbose] [-y | --yes] [--root <path>] [--color (always | auto | never)]
+            [--log-file <path>] <command>
+            - Global options come before the command. With -v, super logs every git command that it
+            runs. 'super pull' and 'super fetch' then also show how long each repo took, e.g.
+            'master(1a2b3c4) (2.5s)', and list the 5 slowest repos at the end, to find the ones that
+            slow down the pull. RUST_LOG can be used to set the log level as well. With -y,
+            destructive commands don't ask for confirmation, which is needed to run them
+            non-interactively. With --root, super runs as if it was started in <path> instead of the
+            current directory. By default, the output is colored if it goes to a terminal and
+            NO_COLOR is not set. --color always colors it anyway, e.g. for a pager that understands
+            colors, and --color never doesn't color it at all. With --log-file, 'super pull' and
+            'super foreach' append a line for each repo to <path>, e.g. '2023-11-05T14:03:27Z
+            command=foreach repo=\"foo\" status=failed exit_code=1 message=\"...\"', where the
+            message is the (truncated) stderr of the command, or the remark of the pull. The file is
+            created if it doesn't exist yet, which makes it a record of what failed during
+            unattended runs.
+
+        Just like git, super can be run from any subdirectory of the super repo, including from inside
+        a submodule. It always runs in the root of the super repo, which is the topmost git repo that
+        contains a .gitmodules file. For scripts and cron jobs, the SUPER_ROOT environment variable
+        can be set instead of --root, which takes precedence over it. Either way, the path has to be
+        a git repo, or contain git repos.
+
+        super [status] [--repos <names> | --repos-from <file>] [--group <name>]
+            [--exclude <names> | --exclude-from <file>] [--filter (dirty | clean)] [--ahead-behind]
+            [--fetch] [--porcelain] [--index] - Show the branch of each repo, whether it has
+            uncommitted changes, and how far it is ahead or behind of its upstream. This is what
+            running 'super' without a command does, unless .superconfig sets another default
+            command. With --filter, only the repos with (dirty) or without (clean) uncommitted
+            changes are shown. With --ahead-behind, the distance to the upstream is always shown,
+            e.g. '↑2 ↓1', or '—' for repos without an upstream. It is based on the remote branches
+            that were fetched last, unless --fetch is given, which fetches all repos first, like
+            'super fetch' does. After the fetch, each branch is compared with its upstream, to show
+            what 'super pull' would do without changing anything: can-ff (it can be fast-forwarded),
+            behind (it is behind, but has uncommitted changes that may be in the way), ahead (it
+            only has local commits), diverged (both have new commits) or up to date. A line at the
+            end counts the repos of each kind. With --porcelain, each repo gets a tab-separated line
+            'name<TAB>status<TAB>branch<TAB>ahead<TAB>behind<TAB>short_sha' instead, without colors
+            or padding, e.g. for awk. The status is dirty, clean or failed, and values that don't
+            apply (e.g. the branch of a detached HEAD) are '-'. This format stays stable: new
+            columns are only ever added at the end. With --index, each repo also shows two separate
+            things that are easily confused: whether it has uncommitted changes of its own
+            ('worktree: dirty' or 'worktree: clean'), and whether the super repo will record a new
+            commit for it ('pointer: staged', 'pointer: modified-not-staged' if it moved further
+            than what is staged, or 'pointer: unchanged'). Submodules that are not initialized are
+            shown as missing, or as failed with --porcelain.
+
+        super watch - Show the same table as 'super status', and redraw it whenever a file in one of the
+            repos changes, until you press Ctrl-C. Nothing is fetched, so this is cheap to leave
+            running.
+
+        super tui - Show an interactive list of all repos and their status, which is updated in the
+            background. Select a repo with the arrow keys (or j and k), and press p to pull it, f to
+            fetch it, or enter to show its recent log. q quits.
+
+        super help [<command>] - Print this documentation, or only the documentation of the given
+            command, which 'super <command> --help' prints as well. 'super --help' lists all
+            commands with a short description.
+
+        super version - Print the version of super, the commit that it was built from, and the version
+            of libgit2 that it uses.
+
+        super upgrade [--yes] - Replace the super binary with the latest release from GitHub, if it
+            is newer. super prints the link to the changelog, and asks for confirmation before it
+            replaces the binary, unless --yes is given. The download is checked against the
+            checksum that is published with the release. This needs curl, and sha256sum (or shasum
+            on macOS).
+
+        super init [--add <repo>...] - Initialize a new super repo for the first time. This is just a
+             convenience wrapper around 'git init'. With --add, the given repos are added right
+             away, like with 'super add'. If a repo can't be added, the others are still added, and
+             the ones that failed are listed at the end.
+
+        super add [--branch-from-head] <repo> - Add a new repo to the super repo. This is just a
+            convenience wrapper around 'git submodule add'. The repo can be given as a shorthand:
+            'gh:me/repo' expands to 'git@github.com:me/repo.git', and 'gl:me/repo' to
+            'git@gitlab.com:me/repo.git'. Adding a repo that already is a submodule changes nothing.
+            If its directory is not initialized yet, e.g. after a partial checkout, super offers to
+            initialize it with 'git submodule update --init'. Anything that doesn't look like a URL
+            or the path of a repo is rejected before git runs. With --branch-from-head, the default
+            branch of the remote is recorded as the branch that the submodule tracks in .gitmodules,
+            so that 'super pull' keeps tracking it even if the remote changes its default branch
+            later.
+
+        super add --no-clone <repo> <dir> - Add the repo that is already checked out in <dir> as a
+            submodule, without cloning it again. super warns if the origin of the existing repo
+            doesn't match <repo>.
+
+        super add (--all | --recursive) <dir> - Add all git repos in the subdirectories of <dir> as
+            submodules, using their origin remote as the URL. Repos without an origin remote, and repos
+            that already are submodules, are skipped.
+
+        super pull [--retries <n>] [--autostash] [--prune] [--init] [--tags]
+            [--depth <n> | --unshallow] [--set-upstream] [--serial] [--only-changed-remote]
+            [--force-unlock] [--report <path> [--report-format (markdown | html)]]
+            [--repos <names> | --repos-from <file>] [--group <name>]
+            [--exclude <names> | --exclude-from <file>] - Update all repos in the super repo. All
+            repos are pulled at the same time, unless --serial is given, which pulls them one after
+            the other in the order of .gitmodules, and shows the progress (e.g. '[3/12] foo') on
+            stderr. This is easier on slow connections, and easier to follow when a single repo
+            fails. With --only-changed-remote, super first asks each remote with 'git ls-remote'
+            whether the branch moved, which is much cheaper than a fetch, and only fetches the repos
+            where it did. The others are reported as up to date (no remote change), and options like
+            --prune and --tags don't affect them. Each submodule is updated to the latest commit of
+            the branch that it tracks in .gitmodules, or of the default branch of its remote if it
+            doesn't track one. Failed fetches are retried up to <n> times (default: 0) with
+            exponential backoff, unless the failure is not transient (e.g. an authentication error).
+            With --autostash, uncommitted changes are stashed before the fast-forward and popped
+            again afterwards. With --prune, remote-tracking branches whose branch was deleted on the
+            remote are removed. With --tags, all tags are fetched as well. With --depth, at most <n>
+            commits of history are fetched, and with --unshallow, shallow repos fetch their complete
+            history. Shallow repos are marked as such. With --set-upstream, branches without an
+            upstream get the branch of the same name on origin as their upstream, if it exists, and
+            the repos where this happened are marked as such. Submodules that are not initialized
+            yet (e.g. after a clone without --recurse-submodules) are skipped, unless --init is
+            given, which clones them. Repos with local commits that are not on the remote can't be
+            fast-forwarded, and are reported as diverged. Repos that are checked out at a tag are
+            reported as pinned, and are not fast-forwarded either. So are submodules with a detached
+            HEAD at the commit that the super repo records for them, which is their normal state
+            after 'git submodule update'. Submodules that are detached at a different commit are
+            reported as detached (drifted). Submodules with 'update = none' in .gitmodules are
+            skipped, and submodules with 'ignore = dirty' or 'ignore = all' are not checked for
+            uncommitted changes. Repos with a lock file that git left behind, e.g. .git/index.lock
+            after a git process was killed, are reported as locked instead of being pulled. With
+            --force-unlock, lock files that are older than 10 minutes are removed first, since no
+            git process holds a lock that long. If the super repo already has uncommitted changes to
+            the commits of its submodules, the pull warns about them first, since it will move the
+            submodules further. It still goes ahead, and 'super commit' records all of the changes
+            afterwards. With --report, a report of the pull is written to the given file, which it
+            overwrites: a table with the status of each repo and the commits it moved between, and
+            the subjects of the new commits of each repo that moved. It is in Markdown, or in HTML
+            with --report-format html, e.g. to paste into the notes of a team meeting.
+
+        super fetch [--retries <n>] [--prune] [--tags] [--force-unlock]
+            [--repos <names> | --repos-from <file>] [--group <name>]
+            [--exclude <names> | --exclude-from <file>] - Fetch all repos like 'super pull' does,
+            but don't fast-forward any of them, so that their working trees are left alone. This
+            shows what is available before deciding to update. Each repo is reported as fetched if
+            new commits arrived for the branch that it tracks, or as up to date if nothing changed.
+            The options work like the ones of 'super pull'.
+
+        super sync - Bring all submodules in line with the super repo, e.g. right after a clone
+            without --recurse-submodules: submodules that are not initialized are cloned, the URLs
+            in .gitmodules are synced to the submodules like 'git submodule sync' does, and every
+            submodule is checked out at the commit that the super repo records, like
+            'git submodule update --init' does. Unlike git, super clones and updates the submodules
+            in parallel, which is much faster in a super repo with many submodules. Each repo is
+            reported as cloned, updated, synced-url (only its URL changed) or unchanged. Repos that
+            fail, e.g. because of a wrong URL or missing credentials, are reported as failed, and
+            the others are still updated. Submodules with 'update = none' in .gitmodules are
+            skipped.
+
+        super branch [--repos <names> | --repos-from <file>]
+            [--exclude <names> | --exclude-from <file>] [--format json] - Show which branch each
+            repo is on, and highlight repos that are not on the branch that they track in
+            .gitmodules.
+
+        super undo (<repo> | --all) [--force] [--yes] - Reset a repo (or all repos that have drifted) to
+            the commit that is recorded for it in the super repo. Repos with uncommitted changes are
+            skipped, unless --force is given, which discards the changes after asking for confirmation.
+
+        super reset --hard [--clean] [--force] [--yes] - Reset every repo to origin/<branch>, where
+            <branch> is the branch that it tracks, discarding its local commits and uncommitted
+            changes. With --clean, untracked files are removed too. super first lists every repo
+            that it will reset and what will be discarded, and asks for confirmation. Repos that
+            are on another branch or have a detached HEAD are skipped, unless --force is given,
+            which checks out the tracked branch in them.
+
+        super clean [--force] [-x] [--yes] - List the untracked files and directories of all repos,
+            e.g. the artifacts that builds leave behind, with the number of files and directories
+            for each repo. Nothing is removed without --force, which removes the listed files after
+            asking for confirmation, like 'git clean -d --force'. With -x, ignored files are removed
+            as well.
+
+        super open (<repo> | --shell-init) - Print the absolute path of a repo, e.g. for
+            'cd $(super open <repo>)'. A prefix of the name of the repo is enough, and so are its
+            characters in the right order (e.g. 'fe' for 'frontend'), as long as only one repo
+            matches. Add 'eval \"$(super open --shell-init)\"' to your shell config, to make
+            'super open <repo>' change into the directory of the repo directly.
+
+        super stash [pop | --include-untracked] - Stash the uncommitted changes of all repos that have
+            any, e.g. before a big rebase. With --include-untracked, untracked files are stashed as
+            well. 'super stash pop' pops the stash entries that 'super stash' made again, and leaves
+            other stash entries alone. Repos where popping conflicts are listed at the end, and
+            keep their stash entry, so that nothing is lost.
+
+        super tag <name> [-m <message>] [--push] [--force] - Tag the current commit of all repos
+            with the same tag, e.g. for a release that spans several repos. With -m, the tag is an
+            annotated tag with the given message. Repos that already have a tag with this name are
+            reported and left alone, unless --force is given, which moves the tag to the current
+            commit. With --push, the tag is pushed to origin as well, which is forced too with
+            --force.
+
+        super log [-n <count>] [--since <date>] - Show the latest commits of all repos in a single
+            list, newest first, to see what happened recently across the whole super repo. Each
+            commit is shown with its repo, its hash, its date, its subject and its author. -n sets
+            the number of commits (10 by default), and --since only shows the commits after the
+            given date, which is passed on to 'git log --since', e.g. '2 weeks ago'.
+
+        super prune-gone [--force] - Delete the local branches of all repos whose upstream branch was
+            deleted on the remote, e.g. after their pull request was merged. This cleans up after
+            'super pull --prune', which only removes the remote-tracking branches. Branches that are
+            not merged are kept, unless --force is given, and the current branch is always kept.
+            The deleted branches are listed for each repo.
+
+        super mv <repo> <new_path> - Move a submodule to a new path. Unlike a plain 'git mv', this
+            also keeps the path in .gitmodules and the config of the submodule in sync. The repo can
+            be given by its name or by its path.
+
+        super config <repo> <key> [<value>] - Print or set a setting of a submodule in .gitmodules.
+            The key is one of branch, url, update, ignore or group, e.g. 'super config foo branch
+            develop' makes 'super pull' track the develop branch of foo. Changes are synced to the
+            submodule with 'git submodule sync', so that a new url takes effect right away.
+
+        super list [--paths] - List all repos, with the group that each of them is in and its path.
+            With --paths, only the paths are printed, one per line and relative to the root of the
+            super repo, without any colors, e.g. for 'super list --paths | fzf'.
+
+        super which <command> - Show whether <command> is a built-in command of super or a custom
+            command, i.e. an executable in ~/.config/super/commands, and print the path of a custom
+            command. super warns if a custom command has the same name as a built-in one, which
+            shadows it.
+
+        super freeze - Write the commit that each submodule is at to super.lock. Unlike committing the
+            super repo, this also pins commits that are not on any branch yet.
+
+        super thaw - Check out the commits that super.lock records for the submodules. Commits that
+            are not available locally are reported as missing, and have to be fetched first.
+
+        super diff - Show the submodules that are checked out at a different commit than the one that
+            is staged in the super repo, with the subjects of both commits. These are the changes
+            that committing the super repo would record.
+
+        super commit -m <message> [--describe] - Commit all submodules that are at a different commit
+            than the one the super repo records for them. With --describe, the message gets a list of
+            these submodules, with the commits they moved between and how many commits they
+            advanced, e.g. 'foo: 1a2b3c4..5d6e7f8 (3 commits)'.
+
+        super foreach [--git] [--shell] [--include-super] [--parallel-output-prefix]
+            [--repos <names> | --repos-from <file>] [--group <name>]
+            [--exclude <names> | --exclude-from <file>] [--filter (dirty | clean)]
+            [--on-dir <path>]... [--keep-going | --max-failures <n>] [--print-cmd] [--dry-run]
+            [--max-output-lines <n>] [--expect-codes <codes>] [--timeout <seconds>] <command> - Run
+            a command in each repo in parallel. With --git the command is run as a git subcommand,
+            e.g. 'super foreach --git status -s'. The command is run directly, not through a shell.
+            With --shell, it runs with 'sh -c' (or 'cmd /C' on Windows) instead, so that pipes,
+            redirects and globs work, e.g. \"super foreach --shell 'git log | head -3'\". Quote the
+            command as a whole then, so that it is interpreted by the shell in each repo, and not by
+            your own shell. With --include-super, the command also runs in the super repo itself.
+            With --repos, it only runs in the given repos, and with --filter only in the repos with
+            (dirty) or without (clean) uncommitted changes. With --on-dir, which can be given more
+            than once, the command runs in the given directories instead of in the submodules, e.g.
+            in the projects of a monorepo. They don't have to be git repos, but they can't be
+            combined with --repos or --group. By default, the output of each repo is printed in one
+            block once its command is done, below a green header if the command succeeded, or a red
+            one with its exit code if it failed. stderr is indented below stdout. With
+            --parallel-output-prefix, the output is streamed instead, and each line starts with the
+            name of its repo, in a color that stays the same for the whole run. Either way, the
+            repos where the command failed are listed at the end. With --max-failures, the command
+            runs in at most as many repos at once as there are CPUs, and no new repos are started
+            once it has failed in <n> of them. The repos that were skipped because of this are
+            listed as well. --keep-going runs the command in all repos, no matter how many fail,
+            which is the default. With --print-cmd, a dimmed line like '[foo] $ git status -s' is
+            printed before the command starts in each repo. With --dry-run, only these lines are
+            printed, and the command doesn't run anywhere, which shows what a command would do
+            before it runs in all repos. With --max-output-lines, only the first <n> lines of the
+            stdout of each repo are printed, followed by '… (truncated, <m> more lines)'. The full
+            output is still written to the --log-file, if one is given. This can't be combined with
+            --parallel-output-prefix. With --expect-codes, which takes a comma-separated list like
+            '0,1', the command counts as successful if it exits with any of these codes, instead of
+            only with 0. This is for commands that use their exit code to report a result, e.g.
+            'super foreach --expect-codes 0,1 grep -q TODO README.md', where grep exits with 1 if
+            nothing matched. With --timeout, the command is killed in the repos where it runs longer
+            than the given number of seconds, and reported as timeout, while it keeps running in the
+            other repos. The command never gets any input, so a command that asks for input fails
+            right away instead of waiting forever. Submodules that are not initialized are skipped,
+            and reported with a hint to run 'super pull --init'.
+
+        After 'super pull', 'super status' and 'super foreach', a single summary line is printed to
+        stderr, e.g. 'super: total=50 updated=3 uptodate=45 failed=2 skipped=0' after a pull,
+        'super: total=50 clean=45 dirty=3 failed=2' after status, where dirty counts the repos with
+        uncommitted changes, or 'super: total=50 succeeded=48 failed=2 skipped=0' after foreach. The
+        keys are stable, so that scripts can parse them. 'super pull' and 'super foreach' also list
+        the repos that failed, and exit with status 1 if there are any.
+
+        'super status', 'super pull' and 'super fetch' end with a footer that counts the repos in
+        each state, the most common ones first, e.g. '50 repos: 45 clean, 3 dirty, 1 behind,
+        1 detached'. 'super status' counts each repo once: as detached or dirty if it is, and
+        otherwise as diverged, behind, ahead or clean, depending on its upstream.
+
+        The commands that accept --repos take a comma-separated list of repo names. With
+        --repos-from, the names are read from a file instead, one per line. Blank lines and lines
+        that start with '#' are ignored, and unknown names are reported with their line number.
+        --exclude and --exclude-from take names the same way, and leave those repos out of whatever
+        --repos and --group select, e.g. to skip a repo that is known to be broken. Excluding a repo
+        that doesn't exist only prints a warning.
+
+        Submodules can be put into groups, e.g. to tell frontend, backend and infra repos apart, by
+        adding 'group = backend' to their section in .gitmodules, or with
+        'super config <repo> group backend'. Repos without a group are in the 'default' group.
+        'super pull', 'super status' and 'super foreach' only operate on the repos in one group
+        with --group <name>, which can be combined with --repos and --exclude.
 
 DESCRIPTION
         Super is a tool that enables you to manage all of your git repos in one centralized repository.
@@ -22,6 +315,32 @@ DESCRIPTION
         with the goal to make using submodules more convenient by adding an intuitive CLI and a colorful
         terminal UI.
 
+CONFIGURATION
+        Super reads its settings from a .superconfig file in the super repo, which has the same format
+        as git's config files. Shorthands for 'super add' are defined like this:
+
+            [shorthand \"work\"]
+                url = git@gitlab.example.com:
+
+        With this, 'super add work:team/repo' adds 'git@gitlab.example.com:team/repo.git'. The 'gh'
+        and 'gl' shorthands can be overridden in the same way.
+
+        Running super without a command shows the status of all repos. Another command (with its
+        arguments) can be set as the default instead:
+
+            [super]
+                defaultCommand = status --fetch
+
+HOOKS
+        Executables in ~/.config/super/hooks are run before and after commands: pre-pull and
+        post-pull around 'super pull', and pre-foreach and post-foreach around 'super foreach'. They
+        run in the root of the super repo. If a pre- hook fails, the command is not run at all. If a
+        post- hook fails, super only warns about it.
+
+        Post- hooks get the names of the affected repos as arguments: the repos that were updated
+        by 'super pull', or all repos that 'super foreach' ran in. SUPER_REPO_STATUSES has a
+        '<name><TAB><status>' line for each repo.
+
 AUTHOR
         Written by Robert Kreuzer.
 
@@ -32,29 +351,163 @@ COPYRIGHT
         Copyright © 2023 Robert Kreuzer.  License BSD-3-Clause: The 3-Clause BSD License <https://opensource.org/license/bsd-3-clause/>.
         This is free software: you are free to change and redistribute it.  There is NO WARRANTY, to the extent permitted by law.";
 
+mod cli;
+mod commands;
+
+use crate::cli::parse_command;
+use crate::cli::parse_global_options;
+use crate::cli::usage;
+use crate::cli::ColorChoice;
+use crate::cli::PullOptions;
 use git2::Repository;
 use std::env;
+use std::ffi::OsStr;
+use std::ffi::OsString;
 use std::fs;
+use std::io::IsTerminal;
+use std::io::Write;
+use std::os::unix::ffi::OsStrExt;
 use std::os::unix::fs::PermissionsExt;
+use std::os::unix::process::CommandExt;
+use std::path::Path;
 use std::path::PathBuf;
 use std::process::Command;
 use std::process::Output;
+use std::process::Stdio;
+use std::sync::mpsc;
+use std::sync::Mutex;
+use std::sync::OnceLock;
 use std::thread;
+use std::time::Duration;
+use std::time::SystemTime;
+
+/// The file that 'super freeze' writes the commits of all submodules to
+const LOCK_FILE: &str = "super.lock";
+
+/// The config file of the super repo, which uses the same format as git's own config files
+const CONFIG_FILE: &str = ".superconfig";
+
+/// The group of the repos that don't set one in .gitmodules
+const DEFAULT_GROUP: &str = "default";
+
+/// The process ids of the subprocesses that are currently running, so that they can be killed when
+/// super is interrupted, and whether each of them leads a process group of its own
+static CHILDREN: Mutex<Vec<(u32, bool)>> = Mutex::new(Vec::new());
+
+/// Whether the output is colored. It is decided once per run, from --color, NO_COLOR and whether
+/// stdout is a terminal.
+static COLOR: OnceLock<bool> = OnceLock::new();
+
+/// The file that --log-file appends a record of each repo result to. The mutex keeps the records
+/// from interleaving.
+static LOG_FILE: OnceLock<Mutex<fs::File>> = OnceLock::new();
+
+/// The column widths of the per-repo output. It is set once per run, so that all lines line up.
+static LAYOUT: OnceLock<Layout> = OnceLock::new();
+
+/// The commands of super with a short description, for 'super --help'. Their full documentation is
+/// in DOCUMENTATION.
+const COMMANDS: [(&str, &str); 29] = [
+    (
+        "status",
+        "Show the branch and the state of each repo (the default)",
+    ),
+    (
+        "watch",
+        "Show the status, and redraw it whenever a repo changes",
+    ),
+    ("tui", "Show an interactive dashboard of all repos"),
+    (
+        "help",
+        "Print the documentation of super, or of a single command",
+    ),
+    ("version", "Print the version of super"),
+    ("upgrade", "Replace super with its latest release"),
+    ("init", "Initialize a new super repo"),
+    ("add", "Add a repo to the super repo"),
+    ("pull", "Fetch all repos and fast-forward them"),
+    ("fetch", "Fetch all repos without fast-forwarding them"),
+    ("sync", "Clone and update all submodules in parallel"),
+    ("branch", "Show which branch each repo is on"),
+    (
+        "undo",
+        "Reset repos to the commit that the super repo records",
+    ),
+    ("reset", "Reset all repos to the branch that they track"),
+    ("clean", "Remove the untracked files of all repos"),
+    ("open", "Print the path of a repo"),
+    ("stash", "Stash or pop the uncommitted changes of all repos"),
+    ("tag", "Tag all repos at their current commit"),
+    ("log", "Show the latest commits of all repos in one list"),
+    (
+        "prune-gone",
+        "Delete the branches whose upstream was deleted",
+    ),
+    ("mv", "Move a submodule to a new path"),
+    ("config", "Print or set a setting of a submodule"),
+    ("list", "List all repos with their group and path"),
+    (
+        "which",
+        "Show whether a command is built in or a custom command",
+    ),
+    ("freeze", "Write the commit of each submodule to super.lock"),
+    ("thaw", "Check out the commits from super.lock"),
+    (
+        "diff",
+        "Show the submodules that moved since the last commit",
+    ),
+    ("commit", "Commit the submodules that moved"),
+    ("foreach", "Run a command in each repo"),
+];
+
+/// The lock files that git creates in the git directory of a repo while it changes it. A git
+/// process that is killed leaves them behind, and every git command that needs them fails after
+/// that.
+const GIT_LOCK_FILES: [&str; 4] = [
+    "index.lock",
+    "HEAD.lock",
+    "shallow.lock",
+    "packed-refs.lock",
+];
+
+/// How old a lock file has to be before --force-unlock removes it. A younger one may still belong
+/// to a git process that is running right now.
+const STALE_LOCK_AGE: Duration = Duration::from_secs(10 * 60);
 
 /// The status of the pull operation
 #[derive(PartialEq)]
 enum PullStatus {
     Unchanged,
+    Skipped,
     Updated,
     UpToDate,
+    Failed,
+    StashConflict,
+    Diverged,
+    Initialized,
+    Pinned,
+    Missing,
+    Detached,
+    Fetched,
+    Locked,
 }
 
 impl PullStatus {
     fn to_str(&self) -> &str {
         match *self {
             PullStatus::Unchanged => "unchanged",
+            PullStatus::Skipped => "skipped",
             PullStatus::Updated => "updated",
             PullStatus::UpToDate => "up to date",
+            PullStatus::Failed => "failed",
+            PullStatus::StashConflict => "stash conflict",
+            PullStatus::Diverged => "diverged",
+            PullStatus::Initialized => "initialized",
+            PullStatus::Pinned => "pinned",
+            PullStatus::Missing => "missing",
+            PullStatus::Detached => "detached",
+            PullStatus::Fetched => "fetched",
+            PullStatus::Locked => "locked",
         }
     }
 }
@@ -65,426 +518,1844 @@ impl std::fmt::Display for PullStatus {
     }
 }
 
-// The main function. It parses CLI args and calls the right handler function.
-fn main() {
-    let args: Vec<String> = env::args().collect();
+/// The errors that can occur while running super
+#[derive(Debug)]
+enum SuperError {
+    /// A subprocess could not be started, e.g. because git is not installed
+    Spawn {
+        program: String,
+        error: std::io::Error,
+    },
+    /// git ran, but exited with a non-zero status
+    Git { args: String, stderr: String },
+    /// An error from libgit2
+    Git2(git2::Error),
+    /// Reading from or writing to the terminal or a file failed
+    Io(std::io::Error),
+    /// super.lock could not be parsed
+    InvalidLockFile(String),
+    /// A hook from ~/.config/super/hooks exited with a non-zero status
+    HookFailed {
+        hook: String,
+        status: std::process::ExitStatus,
+    },
+    /// We need a confirmation from the user, but can't ask for it
+    NotInteractive,
+    /// Downloading or installing a new release failed
+    UpgradeFailed(String),
+    /// Watching the repos for changes failed
+    Watch(notify::Error),
+    /// A lock file that git left behind keeps git from running in a repo
+    Locked(String),
+    /// The remote rejected all credentials that we tried
+    #[cfg(feature = "libgit2-fetch")]
+    AuthenticationFailed { url: String, message: String },
+}
 
-    if args.len() < 2 {
-        // Print the docs with usage instructions
-        println!("{}", DOCUMENTATION);
-        println!("Git repos: {:?}", get_git_repos());
-    } else {
-        if args[1] == "add" {
-            if args.len() != 3 {
-                println!("Usage: super add <repo_path>")
-            } else {
-                let repo_path = &args[2];
-                command_add(repo_path)
+impl std::fmt::Display for SuperError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            SuperError::Spawn { program, error } => {
+                write!(f, "failed to run {}: {}", program, error)
             }
-        } else if args[1] == "init" {
-            if args.len() != 2 {
-                println!("Usage: super init")
-            } else {
-                command_init()
+            // git's own error messages are usually the most helpful, so we show them as they are
+            SuperError::Git { args, stderr } if stderr.trim().is_empty() => {
+                write!(f, "'git {}' failed", args)
             }
-        } else if args[1] == "pull" {
-            if args.len() != 2 {
-                println!("Usage: super pull")
-            } else {
-                match command_pull() {
-                    Ok(_) => (),
-                    Err(error) => println!("Error pulling your repos: {:?}", error),
-                }
+            SuperError::Git { stderr, .. } => write!(f, "{}", stderr.trim()),
+            SuperError::Git2(error) => write!(f, "{}", error.message()),
+            SuperError::Io(error) => write!(f, "{}", error),
+            SuperError::InvalidLockFile(message) => write!(f, "invalid {}: {}", LOCK_FILE, message),
+            SuperError::HookFailed { hook, status } => {
+                write!(f, "the {} hook failed ({})", hook, status)
             }
-        } else if args[1] == "foreach" {
-            // Note: all arguments after "super foreach" are interpreted as the command to
-            // run in each submodule.
-            if args.len() < 3 {
-                println!("Usage: super foreach <command>");
-            } else {
-                match command_foreach(&args[2..]) {
-                    Ok(_) => (),
-                    Err(error) => println!("Error running command: {:?}", error),
-                }
+            SuperError::Watch(error) => write!(f, "failed to watch for changes: {}", error),
+            SuperError::Locked(remark) => write!(f, "{}", remark),
+            SuperError::NotInteractive => write!(
+                f,
+                "stdin is not a terminal, so we can't ask for confirmation (use --yes to skip it)"
+            ),
+            SuperError::UpgradeFailed(message) => write!(f, "{}", message),
+            #[cfg(feature = "libgit2-fetch")]
+            SuperError::AuthenticationFailed { url, message } => {
+                write!(f, "authentication failed for {}: {}", url, message)
             }
-        } else {
-            println!("We only support the 'super add' command right now.");
         }
     }
 }
 
-/// Initialize the super repo for the first time
-///
-/// You have to call this in the directory that you want to initialize
-fn command_init() {
-    let output = Command::new("git")
-        .arg("init")
-        .output()
-        .expect("failed to execute process");
+impl From<notify::Error> for SuperError {
+    fn from(error: notify::Error) -> SuperError {
+        SuperError::Watch(error)
+    }
+}
 
-    if output.status.success() {
-        println!("The super repo was initialized successfully.");
-        println!("You can now add your repos with 'super add <pathspec>")
-    } else {
-        print!(
-            "Failed to initialize the super repo. Error: {}",
-            String::from_utf8_lossy(&output.stderr)
-        );
+impl From<git2::Error> for SuperError {
+    fn from(error: git2::Error) -> SuperError {
+        SuperError::Git2(error)
     }
 }
 
-/// Add a new repo to the super repo
+/// A repo that 'super pull' updates
+#[derive(Clone)]
+struct PullTarget {
+    dir: PathBuf,
+    /// The name of the repo, which is only used for display
+    name: String,
+    /// The branch that is pulled. If this is None, the default branch of the remote is pulled.
+    branch: Option<String>,
+    /// Don't check the repo for uncommitted changes, because .gitmodules says 'ignore = dirty' (or
+    /// 'ignore = all') for it
+    ignore_dirty: bool,
+    /// Don't pull the repo at all, because .gitmodules says 'update = none' for it
+    manual: bool,
+    /// The commit that the super repo records for the submodule, or None for repos that are not
+    /// submodules
+    recorded: Option<String>,
+}
+
+/// The result of pulling a single repo
+struct RepoResult {
+    name: String,
+    status: PullStatus,
+    remark: String,
+    /// The commits that the repo moved between, for the --report of 'super pull'. Only set for
+    /// repos that moved, and only if a report was asked for.
+    change: Option<RepoChange>,
+    /// How long the fetch and fast-forward of the repo took, if it was measured
+    duration: Option<Duration>,
+}
+
+impl RepoResult {
+    fn new(name: &str, status: PullStatus, remark: &str) -> RepoResult {
+        RepoResult {
+            name: name.to_string(),
+            status,
+            remark: remark.to_string(),
+            change: None,
+            duration: None,
+        }
+    }
+
+    /// The remark, followed by how long the repo took, if that was measured
+    fn timed_remark(&self) -> String {
+        match self.duration {
+            Some(duration) => format!("{} ({})", self.remark, format_duration(duration)),
+            None => self.remark.clone(),
+        }
+    }
+}
+
+/// The commits that a repo moved between during a pull
+struct RepoChange {
+    before: String,
+    after: String,
+    /// The short hashes and subjects of the new commits, newest first
+    commits: Vec<(String, String)>,
+}
+
+/// How many repos ended up in each status, for the machine-readable summary and the exit code
+#[derive(Default)]
+struct Summary {
+    total: usize,
+    updated: usize,
+    up_to_date: usize,
+    failed: usize,
+    skipped: usize,
+    /// The names of the repos that failed, in the order in which they finished
+    failed_repos: Vec<String>,
+    /// How many repos ended up in each status
+    by_status: Vec<(String, usize)>,
+}
+
+/// Run commands and log what is being run
+trait LoggedOutput {
+    /// Like Command::output, but logs the command, its working directory and its exit status
+    fn logged_output(&mut self) -> std::io::Result<Output> {
+        self.logged_output_with_timeout(None)
+            .map(|(output, _)| output)
+    }
+
+    /// Like logged_output, but kills the command if it is still running after the timeout. Also
+    /// returns whether it was killed.
+    fn logged_output_with_timeout(
+        &mut self,
+        timeout: Option<Duration>,
+    ) -> std::io::Result<(Output, bool)>;
+}
+
+impl LoggedOutput for Command {
+    fn logged_output_with_timeout(
+        &mut self,
+        timeout: Option<Duration>,
+    ) -> std::io::Result<(Output, bool)> {
+        // The command is only logged, so a lossy conversion is fine
+        let program = self.get_program().to_string_lossy().to_string();
+        let args: Vec<String> = self
+            .get_args()
+            .map(|arg| arg.to_string_lossy().to_string())
+            .collect();
+        let dir = match self.get_current_dir() {
+            Some(dir) => dir.display().to_string(),
+            None => ".".to_string(),
+        };
+
+        log::debug!("Running '{} {}' in {}", program, args.join(" "), dir);
+
+        // This is what Command::output does, except that the child is tracked while it runs, so
+        // that it can be killed if super is interrupted. With a timeout, it gets a process group
+        // of its own, so that its own children are killed along with it. Without one it stays in
+        // the group of super, because a process outside of the foreground group is stopped as
+        // soon as it reads from the terminal, e.g. when ssh asks for a passphrase.
+        if timeout.is_some() {
+            self.process_group(0);
+        }
+        let output = self
+            .stdin(Stdio::null())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn()
+            .and_then(|child| {
+                let id = child.id();
+                track_child(id, timeout.is_some());
+                let watchdog = timeout.map(|timeout| watch_timeout(id, timeout));
+                let output = child.wait_with_output();
+                untrack_child(id);
+                let timed_out = stop_watchdog(watchdog);
+                output.map(|output| (output, timed_out))
+            });
+
+        match &output {
+            Ok((_, true)) => log::debug!("'{}' in {} was killed after the timeout", program, dir),
+            Ok((output, false)) => {
+                log::debug!("'{}' in {} exited with {}", program, dir, output.status)
+            }
+            Err(error) => log::debug!("'{}' in {} failed to run: {}", program, dir, error),
+        }
+
+        output
+    }
+}
+
+/// Kill the subprocess with the given id, and everything in its process group, if it is still
+/// running after the timeout. The subprocess must lead a process group of its own. Dropping the
+/// returned sender tells the watchdog that the subprocess is done. Its thread returns whether it
+/// killed the subprocess.
 ///
-/// This will add the repo as a submodule and will also initialize it
-fn command_add(repo_path: &String) {
-    let output = Command::new("git")
-        .arg("submodule")
-        .arg("add")
-        // TODO: We might want to pass along all optional arguments here
-        .arg(repo_path)
-        .output()
-        .expect("failed to execute process");
+/// Killing only the subprocess is not enough, e.g. 'sh -c' forks its commands, and they would keep
+/// the pipes of its output open until they exit.
+fn watch_timeout(id: u32, timeout: Duration) -> (mpsc::Sender<()>, thread::JoinHandle<bool>) {
+    let (done, receiver) = mpsc::channel::<()>();
+    let handle = thread::spawn(move || match receiver.recv_timeout(timeout) {
+        Err(mpsc::RecvTimeoutError::Timeout) => {
+            let _ = Command::new("kill")
+                .args(["-KILL", "--"])
+                .arg(format!("-{}", id))
+                .status();
+            true
+        }
+        // The subprocess is done
+        _ => false,
+    });
+    (done, handle)
+}
 
-    if output.status.success() {
-        println!("The submodule {} was added successfully.", repo_path);
-        println!("You probably will want to commit this (along with .gitmodules, if this is the first submodule.")
-    } else {
-        print!(
-            "Failed to add the submodule. Error: {}",
-            String::from_utf8_lossy(&output.stderr)
-        );
+/// Stop the watchdog of a subprocess that is done, and return whether it killed the subprocess
+fn stop_watchdog(watchdog: Option<(mpsc::Sender<()>, thread::JoinHandle<bool>)>) -> bool {
+    match watchdog {
+        Some((done, handle)) => {
+            drop(done);
+            handle.join().unwrap_or(false)
+        }
+        None => false,
     }
 }
 
-// Run the given command for each submodule in parallel
-fn command_foreach(command: &[String]) -> Result<(), git2::Error> {
-    // TODO: Deduplicate the next two lines.
-    let repo: Repository = Repository::open(".")?;
-    let current_dir: std::path::PathBuf =
-        env::current_dir().expect("Failed to get current directory");
+/// The currently checked out branch and commit of a repo
+struct HeadState {
+    /// The name of the branch, or an empty string if HEAD is detached
+    branch: String,
+    /// The commit hash that HEAD points to
+    sha: String,
+    /// The upstream of the branch, e.g. 'origin/master', if it has one
+    upstream: Option<String>,
+    /// How many commits the branch is ahead and behind of its upstream, if it has one
+    ahead_behind: Option<(u32, u32)>,
+    /// The number of tracked files with uncommitted changes
+    changed: usize,
+}
 
-    // Run the given command as a subprocess for each submodule
-    let mut threads = vec![];
+/// The widths of the columns that print_repo_line prints
+struct Layout {
+    /// The width of the repo column, which fits the longest repo name
+    repo: usize,
+    /// The width of the remark column, if the output goes to a terminal. Longer remarks are
+    /// truncated, so that each repo stays on a single line.
+    remark: Option<usize>,
+}
 
-    for submodule in repo.submodules()? {
-        let name = submodule.name().unwrap_or("").to_string();
-        let repo_dir = current_dir.join(name.clone());
+impl Layout {
+    /// The width of the status column
+    const STATUS: usize = 10;
 
-        let cmd: Vec<String> = command.to_vec();
-        let handle = thread::spawn(move || run_command(&repo_dir, cmd));
-        threads.push(handle);
+    fn new(repo: usize) -> Layout {
+        // The repo and status columns, and the spaces between the columns
+        let used = repo + Layout::STATUS + 5;
+        let remark = terminal_size::terminal_size()
+            .map(|(terminal_size::Width(width), _)| (width as usize).saturating_sub(used).max(10));
+
+        Layout { repo, remark }
     }
+}
 
-    // Wait for all threads to finish
-    for handle in threads {
-        handle.join().unwrap();
+/// The outcome of fast-forwarding a branch to its remote-tracking branch
+enum ForwardResult {
+    FastForwarded,
+    /// The local branch has commits that are not on the remote, so it can't be fast-forwarded
+    Diverged,
+}
+
+/// The outcome of fetching a single repo, including all retries
+struct FetchOutcome {
+    /// How many times 'git fetch' was run
+    attempts: u32,
+    /// How many stale remote-tracking branches were removed
+    pruned: usize,
+    /// Whether the fetch moved the remote-tracking branch of the pulled branch, i.e. whether it
+    /// downloaded new commits for it
+    updated: bool,
+    /// The error of the last attempt, if no attempt succeeded
+    error: Option<SuperError>,
+}
+
+// The main function. It parses CLI args and calls the right handler function.
+fn main() {
+    let mut args: Vec<String> = env::args().collect();
+
+    let global_options = parse_global_options(&mut args);
+    init_logging(global_options.verbose);
+    let _ = COLOR.set(global_options.color.enabled());
+    init_interrupt_handler();
+
+    // The log file is opened before we change to the root of the super repo, so that a relative
+    // path is relative to where super was started
+    if let Some(path) = &global_options.log_file {
+        match fs::OpenOptions::new().create(true).append(true).open(path) {
+            Ok(file) => {
+                let _ = LOG_FILE.set(Mutex::new(file));
+            }
+            Err(error) => {
+                println!("Failed to open {}: {}", path.display(), error);
+                return;
+            }
+        }
     }
 
-    Ok(())
+    // Just like git, super works from any subdirectory of the super repo, by running all commands
+    // in its root. 'super init' is the exception, because it creates a new repo right here.
+    let invoked_from: PathBuf = env::current_dir().expect("Failed to get current directory");
+    if args.len() < 2 || args[1] != "init" {
+        // --root and SUPER_ROOT take the place of the current directory, e.g. for cron jobs. The
+        // flag takes precedence over the environment variable.
+        let root_option = global_options.root.clone().or_else(|| {
+            env::var_os("SUPER_ROOT")
+                .filter(|root| !root.is_empty())
+                .map(PathBuf::from)
+        });
+        let root = match root_option {
+            Some(dir) => match validate_root(&dir) {
+                // A directory that only contains repos is used as it is, like the current
+                // directory would be
+                Ok(dir) => Some(find_super_root(&dir).unwrap_or(dir)),
+                Err(message) => {
+                    println!("{}", message);
+                    return;
+                }
+            },
+            None => find_super_root(&invoked_from),
+        };
+        if let Some(root) = root {
+            log::debug!("Using the super repo in {}", root.display());
+            if let Err(error) = env::set_current_dir(&root) {
+                println!("Failed to change to {}: {}", root.display(), error);
+                return;
+            }
+        }
+    }
+
+    // Without a command, super runs the default command from .superconfig, or shows the status
+    if args.len() < 2 {
+        if let Some(command) = get_default_command() {
+            args.extend(command.split_whitespace().map(str::to_string));
+        }
+    }
+
+    match parse_command(&args[1..], &global_options, &invoked_from) {
+        Some(subcommand) => commands::run(subcommand),
+        None => match usage(&args[1]) {
+            Some(usage) => println!("{}", usage),
+            None => println!(
+                "Unknown command: {}. Run 'super --help' for a list of commands.",
+                args[1]
+            ),
+        },
+    }
 }
 
-// Run the given command as a subprocess (but not in a sub-shell).
-// The output of the command is printed to stdout.
-fn run_command(repo_path: &PathBuf, cmd: Vec<String>) -> () {
-    let mut command = Command::new(cmd[0].clone());
+/// Find the root of the super repo that the given directory is in, which is the topmost git repo
+/// that contains a .gitmodules file. If none of the repos has a .gitmodules file, the innermost
+/// repo is used. Returns None if the directory is not in a git repo at all.
+fn find_super_root(dir: &Path) -> Option<PathBuf> {
+    let mut root: Option<PathBuf> = None;
+    let mut search_from = Some(dir.to_path_buf());
+
+    // Repository::discover only finds the innermost repo (e.g. a submodule), so we keep searching
+    // upwards from the parent of each repo that we find
+    while let Some(dir) = search_from {
+        let Ok(repo) = Repository::discover(&dir) else {
+            break;
+        };
+        let Some(workdir) = repo.workdir() else {
+            break;
+        };
+
+        if root.is_none() || workdir.join(".gitmodules").exists() {
+            root = Some(workdir.to_path_buf());
+        }
+        search_from = workdir.parent().map(Path::to_path_buf);
+    }
+
+    root
+}
 
-    // Add all arguments to the command
-    if cmd.len() > 1 {
-        command.args(&cmd[1..]);
+/// Check the directory that was given with --root or SUPER_ROOT, which has to be a git repo, or
+/// contain git repos. Returns the absolute path of the directory, or a message that explains the
+/// problem.
+fn validate_root(dir: &Path) -> Result<PathBuf, String> {
+    if !dir.is_dir() {
+        return Err(format!("The root {} is not a directory", dir.display()));
     }
 
-    let output: Output = command
-        .current_dir(repo_path)
-        .output()
-        .expect("failed to execute process");
+    let dir = dir
+        .canonicalize()
+        .map_err(|error| format!("Failed to resolve the root {}: {}", dir.display(), error))?;
 
-    if !output.status.success() {
-        print!(
-            "Failed to run the command in the submodule. Error: {}",
-            String::from_utf8_lossy(&output.stderr)
-        );
+    if Repository::discover(&dir).is_err() && get_git_repos(&dir).is_empty() {
+        return Err(format!(
+            "The root {} is not a git repo, and doesn't contain any",
+            dir.display()
+        ));
+    }
+
+    Ok(dir)
+}
+
+/// Convert a path that is relative to the directory super was invoked in, to a path that is
+/// relative to the current directory, i.e. the root of the super repo
+fn relative_to_root(invoked_from: &Path, path: &str) -> String {
+    let root = env::current_dir().expect("Failed to get current directory");
+    let absolute = invoked_from.join(path);
+
+    match absolute.strip_prefix(&root) {
+        Ok(relative) if relative.as_os_str().is_empty() => ".".to_string(),
+        Ok(relative) => relative.display().to_string(),
+        Err(_) => path.to_string(),
+    }
+}
+
+/// Kill all running subprocesses when super is interrupted with Ctrl-C, and exit with 130 like a
+/// shell does. Otherwise, e.g. a 'git fetch' may keep running in the background, and hold the locks
+/// of its repo.
+///
+/// A subprocess that runs with a timeout leads a process group of its own, which doesn't get the
+/// Ctrl-C from the terminal, so the whole group is killed. The others are killed as well, in case
+/// the signal didn't come from the terminal.
+fn init_interrupt_handler() {
+    let result = ctrlc::set_handler(|| {
+        // A subprocess that leads a process group is killed along with its group
+        let targets: Vec<String> = CHILDREN
+            .lock()
+            .unwrap_or_else(|error| error.into_inner())
+            .iter()
+            .map(|(id, own_group)| match own_group {
+                true => format!("-{}", id),
+                false => id.to_string(),
+            })
+            .collect();
+
+        if !targets.is_empty() {
+            let _ = Command::new("kill")
+                .args(["-TERM", "--"])
+                .args(&targets)
+                .status();
+        }
+        std::process::exit(130);
+    });
+
+    if let Err(error) = result {
+        log::warn!("Failed to install the handler for Ctrl-C: {}", error);
+    }
+}
+
+/// Remember that a subprocess with the given process id is running, and whether it leads a
+/// process group of its own
+fn track_child(id: u32, own_group: bool) {
+    CHILDREN
+        .lock()
+        .unwrap_or_else(|error| error.into_inner())
+        .push((id, own_group));
+}
+
+/// Forget about a subprocess that has exited
+fn untrack_child(id: u32) {
+    CHILDREN
+        .lock()
+        .unwrap_or_else(|error| error.into_inner())
+        .retain(|(child, _)| *child != id);
+}
+
+/// Set up logging. By default only warnings are logged, and -v enables debug logs. If RUST_LOG is
+/// set, it takes precedence over both.
+fn init_logging(verbose: bool) {
+    let default_level = if verbose { "debug" } else { "warn" };
+
+    env_logger::Builder::from_env(env_logger::Env::default().default_filter_or(default_level))
+        .format_timestamp(None)
+        .format_target(false)
+        .init();
+}
+
+/// Download the given URL with curl, and return the response body
+fn download(url: &str) -> Result<Vec<u8>, SuperError> {
+    let output = Command::new("curl")
+        .args(["--fail", "--silent", "--show-error", "--location", url])
+        .logged_output()
+        .map_err(|error| SuperError::Spawn {
+            program: "curl".to_string(),
+            error,
+        })?;
+
+    if output.status.success() {
+        Ok(output.stdout)
     } else {
-        println!("{}", String::from_utf8_lossy(&output.stdout));
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        Err(SuperError::UpgradeFailed(format!(
+            "failed to download {}: {}",
+            url,
+            stderr.trim()
+        )))
     }
 }
 
-// Pull all submodules in the given repo in parallel
-fn pull_in_parallel(current_dir: &PathBuf) -> Result<(), git2::Error> {
-    let mut threads = vec![];
+/// Get the command that super runs when it is started without one, from 'super.defaultCommand' in
+/// .superconfig. It can include arguments, e.g. 'status --fetch'.
+fn get_default_command() -> Option<String> {
+    git2::Config::open(Path::new(CONFIG_FILE))
+        .and_then(|config| config.get_string("super.defaultCommand"))
+        .ok()
+        .filter(|command| !command.trim().is_empty())
+}
 
-    // Vector of (repo_path, repo_name, branch) tuples
-    let mut repos: Vec<(PathBuf, String, String)> = vec![];
+/// Return the group of the repo with the given name, which is set with 'group = <name>' in its
+/// section of .gitmodules. Repos without a group, and repos that are not submodules, are in the
+/// default group.
+fn get_group(name: &str) -> String {
+    git2::Config::open(Path::new(".gitmodules"))
+        .and_then(|config| config.get_string(&format!("submodule.{}.group", name)))
+        .unwrap_or_else(|_| DEFAULT_GROUP.to_string())
+}
 
-    match Repository::open(".") {
-        // Case 1: The directory that 'super' was called in, is a git repo itself
-        Ok(repo) => {
-            match repo.submodules() {
-                Ok(submodules) => {
-                    for submodule in submodules {
-                        let name = submodule.name().unwrap_or("").to_string();
-                        let repo_dir = current_dir.join(name.clone());
+/// Run f for all repos at the same time, each in a thread of its own, and return every repo with
+/// its result. The results are in the order of the repos, not in the order in which the threads
+/// finished, so that the output is the same from run to run.
+fn for_each_repo<R, T, F>(repos: &[R], f: F) -> Vec<(&R, T)>
+where
+    R: Sync,
+    T: Send,
+    F: Fn(&R) -> T + Sync,
+{
+    thread::scope(|scope| {
+        let f = &f;
+        let handles: Vec<_> = repos
+            .iter()
+            .map(|repo| scope.spawn(move || f(repo)))
+            .collect();
+        repos
+            .iter()
+            .zip(handles)
+            .map(|(repo, handle)| (repo, handle.join().unwrap()))
+            .collect()
+    })
+}
 
-                        // submodules can specify a default branch in .gitmodules. We pull that branch by
-                        // default, and otherwise we pull "master"
-                        let branch = submodule.branch().unwrap_or("master").to_string();
+/// Return (repo_name, repo_path) tuples for the repos that 'super status' shows, which are the same
+/// repos that 'super pull' updates
+fn get_status_repos() -> Result<Vec<(String, PathBuf)>, SuperError> {
+    let repos = match Repository::open(".") {
+        Ok(repo) => repo
+            .submodules()?
+            .iter()
+            .map(|submodule| (submodule_name(submodule), submodule.path().to_path_buf()))
+            .collect(),
+        // The path is only converted for display, and the repo is still found by its path
+        Err(_error) => get_git_repos(Path::new("."))
+            .into_iter()
+            .map(|repo_path| (repo_path.display().to_string(), repo_path))
+            .collect(),
+    };
 
-                        repos.push((repo_dir, name, branch))
-                    }
-                }
-                Err(error) => {
-                    println!("Failed to get submodules: {}", error)
-                }
-            }
-        }
-        // Case 2: The directory that 'super' was called in, is *not* a git repo itself
-        Err(_error) => {
-            for repo_name in get_git_repos() {
-                let repo_dir = current_dir.join(&repo_name);
+    Ok(repos)
+}
 
-                // We want to pull the currently checked out branch
-                let branch = get_current_branch(&repo_dir);
+/// Return the repos that are initialized, i.e. the submodules that are cloned
+fn initialized_repos(repos: Vec<(String, PathBuf)>) -> Vec<(String, PathBuf)> {
+    repos
+        .into_iter()
+        .filter(|(_, repo_dir)| repo_dir.join(".git").exists())
+        .collect()
+}
 
-                repos.push((repo_dir, repo_name, branch))
-            }
+/// Return the repos that 'super pull' updates, with the branch that each of them tracks. 'super tui'
+/// shows the same repos, and 'super reset' resets them.
+fn get_pull_targets() -> Result<Vec<PullTarget>, SuperError> {
+    let targets = match Repository::open(".") {
+        Ok(repo) => repo
+            .submodules()?
+            .iter()
+            .map(|submodule| PullTarget {
+                dir: submodule.path().to_path_buf(),
+                name: submodule_name(submodule),
+                branch: submodule.branch().map(str::to_string),
+                ignore_dirty: matches!(
+                    submodule.ignore_rule(),
+                    git2::SubmoduleIgnore::Dirty | git2::SubmoduleIgnore::All
+                ),
+                manual: matches!(submodule.update_strategy(), git2::SubmoduleUpdate::None),
+                recorded: submodule.index_id().map(|id| id.to_string()),
+            })
+            .collect(),
+        // Like 'super pull', we pull the currently checked out branch of repos that are not
+        // submodules
+        Err(_error) => get_git_repos(Path::new("."))
+            .into_iter()
+            .map(|repo_path| PullTarget {
+                name: repo_path.display().to_string(),
+                branch: get_current_branch(&repo_path).ok(),
+                dir: repo_path,
+                ignore_dirty: false,
+                manual: false,
+                recorded: None,
+            })
+            .collect(),
+    };
+
+    Ok(targets)
+}
+
+/// Return the name of the submodule for display. Names that are not valid UTF-8 (which git allows,
+/// because the name is the path by default) are converted lossily, instead of becoming empty.
+fn submodule_name(submodule: &git2::Submodule) -> String {
+    String::from_utf8_lossy(submodule.name_bytes()).to_string()
+}
+
+/// Return the status ('dirty' or 'clean') and the remark that 'super status' shows for a repo
+fn format_repo_status(head: &HeadState, ahead_behind: bool) -> (&'static str, String) {
+    let mut remark = if head.branch.is_empty() {
+        format!("(detached @ {})", short_hash(&head.sha))
+    } else {
+        format!("{}({})", head.branch, short_hash(&head.sha))
+    };
+
+    if ahead_behind {
+        // A repo without an upstream has nothing to compare against, which isn't an error
+        match head.ahead_behind {
+            Some((ahead, behind)) => remark.push_str(&format!(", ↑{} ↓{}", ahead, behind)),
+            None => remark.push_str(", —"),
+        }
+    } else if let Some((ahead, behind)) = head.ahead_behind {
+        if ahead > 0 {
+            remark.push_str(&format!(", {} ahead", ahead));
+        }
+        if behind > 0 {
+            remark.push_str(&format!(", {} behind", behind));
         }
     }
 
-    for (repo_dir, repo_name, branch) in repos.into_iter() {
-        let handle = thread::spawn(move || {
-            pull_single_repo(&repo_dir, &repo_name, &branch);
-        });
-        threads.push(handle);
+    if head.changed > 0 {
+        remark.push_str(&format!(", {} changed", head.changed));
+        ("dirty", remark)
+    } else {
+        ("clean", remark)
     }
+}
+
+/// Return a hint with the repo names that are closest to the given unknown name, e.g.
+/// ", did you mean `frontend`?", or an empty string if none of them is close enough to be a typo
+fn suggest_repo_names(name: &str, names: &[&str]) -> String {
+    // Allow about one typo per three characters, but at least one
+    let max_distance = (name.chars().count() / 3).max(1);
+
+    let distances: Vec<(usize, &str)> = names
+        .iter()
+        .map(|candidate| (levenshtein(name, candidate), *candidate))
+        .filter(|(distance, _)| *distance <= max_distance)
+        .collect();
+    let Some(closest) = distances.iter().map(|(distance, _)| *distance).min() else {
+        return String::new();
+    };
 
-    // Wait for all threads to finish
-    for handle in threads {
-        handle.join().unwrap();
+    let suggestions: Vec<String> = distances
+        .iter()
+        .filter(|(distance, _)| *distance == closest)
+        .map(|(_, candidate)| format!("`{}`", candidate))
+        .collect();
+    format!(", did you mean {}?", suggestions.join(" or "))
+}
+
+/// Return the Levenshtein distance of the two strings, i.e. how many characters have to be
+/// inserted, deleted or replaced to turn one into the other
+fn levenshtein(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    // The distances between the prefix of a that was processed so far, and each prefix of b
+    let mut previous: Vec<usize> = (0..=b.len()).collect();
+
+    for (i, a_char) in a.chars().enumerate() {
+        let mut current = vec![i + 1];
+        for (j, b_char) in b.iter().enumerate() {
+            let substitution = previous[j] + usize::from(a_char != *b_char);
+            let deletion = previous[j + 1] + 1;
+            let insertion = current[j] + 1;
+            current.push(substitution.min(deletion).min(insertion));
+        }
+        previous = current;
     }
 
-    Ok(())
+    previous[b.len()]
+}
+
+/// Format a duration in seconds with one decimal, e.g. '2.5s'
+fn format_duration(duration: Duration) -> String {
+    format!("{:.1}s", duration.as_secs_f64())
+}
+
+/// Return the message for a super repo that has no submodules to work on, with a hint on how to
+/// get some. `declared` is the number of submodules in .gitmodules, none of which are initialized,
+/// which needs a different hint than a super repo without any submodules.
+fn no_submodules_message(declared: usize) -> String {
+    if declared > 0 {
+        format!(
+            "None of the {} submodules are initialized, run 'super pull --init' to clone them",
+            declared
+        )
+    } else {
+        "No submodules found in this super repo, add one with 'super add <repo>'".to_string()
+    }
 }
 
 // Fetch the latest commits for the given branch, and do a fast-forward merge
 // if, and only if, the repo is on the given branch and has no uncommitted changes.
-fn pull_single_repo(repo_dir: &PathBuf, name: &str, branch: &str) -> () {
-    let hash_before = get_head_sha(repo_dir);
-    // Fetch the latest commits
-    git_fetch(repo_dir, branch);
+fn pull_single_repo(target: &PullTarget, options: &PullOptions) -> RepoResult {
+    let repo_dir: &Path = &target.dir;
+    let name: &str = &target.name;
+    // Looking up the default branch of the remote can take a network call, so it's done here, in
+    // parallel with the other repos
+    let branch: &str = &match &target.branch {
+        Some(branch) => branch.clone(),
+        None => get_default_branch(repo_dir),
+    };
 
-    // Get the currently checked out branch
-    let branch_name = get_current_branch(repo_dir);
+    // A lock file that a killed (or still running) git process left behind would make git fail
+    // halfway through the pull
+    match check_git_locks(repo_dir, options.force_unlock) {
+        Ok(None) => (),
+        Ok(Some(remark)) => return RepoResult::new(name, PullStatus::Locked, &remark),
+        Err(error) => return RepoResult::new(name, PullStatus::Failed, &error.to_string()),
+    }
 
-    if branch_name != branch {
-        print_status_line(name, &PullStatus::Unchanged, "not on tracked branch");
-        return;
+    // Get the currently checked out branch and commit with a single subprocess
+    let head_before = match get_head_state(repo_dir) {
+        Ok(head) => head,
+        Err(error) => {
+            return RepoResult::new(name, PullStatus::Failed, &error.to_string());
+        }
+    };
+
+    // With --set-upstream, a local branch without an upstream gets the branch of the same name on
+    // origin as its upstream, if there is one. Branches that already have an upstream are left
+    // alone.
+    let mut upstream_set = String::new();
+    if options.set_upstream && !head_before.branch.is_empty() && head_before.upstream.is_none() {
+        let tracking = format!("origin/{}", head_before.branch);
+        if resolve_ref(repo_dir, &format!("refs/remotes/{}", tracking)).is_ok() {
+            let set_upstream_to = format!("--set-upstream-to={}", tracking);
+            if let Err(error) = run_git(repo_dir, &["branch", "--quiet", &set_upstream_to]) {
+                let remark = format!("failed to set the upstream: {}", error);
+                return RepoResult::new(name, PullStatus::Failed, &remark);
+            }
+            upstream_set = format!(" (upstream set to {})", tracking);
+        }
     }
+    // Fetch the latest commits. With --only-changed-remote, a cheap 'git ls-remote' tells whether
+    // the branch moved on the remote at all, and the fetch is skipped if it didn't.
+    let shallow_before = is_shallow(repo_dir);
+    let remote_unchanged = options.only_changed_remote && is_remote_unchanged(repo_dir, branch);
+    let fetch = if remote_unchanged {
+        FetchOutcome {
+            attempts: 0,
+            pruned: 0,
+            updated: false,
+            error: None,
+        }
+    } else {
+        git_fetch(repo_dir, branch, options)
+    };
+    let no_remote_change = if remote_unchanged {
+        " (no remote change)"
+    } else {
+        ""
+    };
 
-    forward_branch(repo_dir, branch);
+    // Only mention the attempts if we actually had to retry
+    let attempts = if fetch.attempts > 1 {
+        format!(" ({} attempts)", fetch.attempts)
+    } else {
+        String::new()
+    };
+    let pruned = if fetch.pruned > 0 {
+        format!(" ({} pruned)", fetch.pruned)
+    } else {
+        String::new()
+    };
 
-    let hash_after = get_head_sha(repo_dir);
-    let short_hash_before = get_short_hash(repo_dir, &hash_before);
-    let short_hash_after = get_short_hash(repo_dir, &hash_after);
+    if let Some(error) = fetch.error {
+        let remark = format!("fetch failed{attempts}: {}", error);
+        return RepoResult::new(name, PullStatus::Failed, &remark);
+    }
+
+    // Shallow repos only have part of their history, which is worth pointing out
+    let shallow = if shallow_before && !is_shallow(repo_dir) {
+        " (unshallowed)"
+    } else if is_shallow(repo_dir) {
+        " (shallow)"
+    } else if options.unshallow {
+        " (already complete)"
+    } else {
+        ""
+    };
 
-    if hash_before == hash_after {
+    // 'super fetch' is done at this point, without having touched the working tree
+    if options.fetch_only {
+        let status = if fetch.updated {
+            PullStatus::Fetched
+        } else {
+            PullStatus::UpToDate
+        };
+        let remark = format!("origin/{branch}{attempts}{pruned}{shallow}");
+        return RepoResult::new(name, status, &remark);
+    }
+
+    // Submodules that are pinned to a tag are checked out at the tag, and are never fast-forwarded
+    if head_before.branch.is_empty() {
+        if let Ok(Some(tag)) = get_tag(repo_dir, "HEAD") {
+            let remark = format!("tag {}({})", tag, short_hash(&head_before.sha));
+            return RepoResult::new(name, PullStatus::Pinned, &remark);
+        }
+    }
+
+    // A detached HEAD at the commit that the super repo records is the normal state of a
+    // submodule after 'git submodule update', so it is only worth pointing out if the submodule
+    // has drifted away from that commit
+    if head_before.branch.is_empty() {
+        if let Some(recorded) = &target.recorded {
+            let short_sha = short_hash(&head_before.sha);
+            return if head_before.sha == *recorded {
+                let remark = format!("detached @ {}, as recorded in the super repo", short_sha);
+                RepoResult::new(name, PullStatus::Pinned, &remark)
+            } else {
+                let remark = format!(
+                    "(drifted) @ {}, the super repo records {}",
+                    short_sha,
+                    short_hash(recorded)
+                );
+                RepoResult::new(name, PullStatus::Detached, &remark)
+            };
+        }
+    }
+
+    if head_before.branch != branch {
+        let remark = format!("not on tracked branch{upstream_set}");
+        return RepoResult::new(name, PullStatus::Unchanged, &remark);
+    }
+
+    // If the fetch didn't bring in new commits, and the branch already contains its
+    // remote-tracking branch, there is nothing to fast-forward. This saves running the merge and
+    // looking up HEAD again, which adds up for the many repos that are usually up to date.
+    let tracking = format!("origin/{}", branch);
+    if !fetch.updated
+        && head_before.upstream.as_deref() == Some(tracking.as_str())
+        && head_before
+            .ahead_behind
+            .is_some_and(|(_ahead, behind)| behind == 0)
+    {
+        let short_hash_before = short_hash(&head_before.sha);
+        let remark: String = format!(
+            "{branch}({short_hash_before}){attempts}{pruned}{shallow}{upstream_set}{no_remote_change}"
+        );
+        return RepoResult::new(name, PullStatus::UpToDate, &remark);
+    }
+
+    // With --autostash, uncommitted changes are put aside during the fast-forward, like git's own
+    // pull.autostash does. Repos that ignore dirty changes in .gitmodules are never stashed.
+    let mut stashed = false;
+    if options.autostash && !target.ignore_dirty {
+        let stash = match is_dirty(repo_dir) {
+            Ok(true) => stash_push(repo_dir).map(|_| true),
+            Ok(false) => Ok(false),
+            Err(error) => Err(error),
+        };
+
+        match stash {
+            Ok(stash) => stashed = stash,
+            Err(error) => {
+                let remark = format!("failed to stash your changes: {}", error);
+                return RepoResult::new(name, PullStatus::Failed, &remark);
+            }
+        }
+    }
+
+    let forward = forward_branch(repo_dir, branch);
+
+    let stash_conflict = stashed && stash_pop(repo_dir).is_err();
+
+    match forward {
+        Ok(ForwardResult::FastForwarded) => (),
+        Ok(ForwardResult::Diverged) => {
+            let short_hash_before = short_hash(&head_before.sha);
+            let remark = format!("{branch}({short_hash_before}) has diverged from origin");
+            return RepoResult::new(name, PullStatus::Diverged, &remark);
+        }
+        Err(error) => {
+            let remark = format!("fast-forward failed: {}", error);
+            return RepoResult::new(name, PullStatus::Failed, &remark);
+        }
+    }
+
+    let hash_after = match get_head_sha(repo_dir) {
+        Ok(hash) => hash,
+        Err(error) => {
+            return RepoResult::new(name, PullStatus::Failed, &error.to_string());
+        }
+    };
+    let short_hash_before = short_hash(&head_before.sha);
+    let short_hash_after = short_hash(&hash_after);
+
+    // Listing the new commits takes another git process, so it's only done for the report
+    let change = if options.report.is_some() && head_before.sha != hash_after {
+        Some(RepoChange {
+            before: short_hash_before.to_string(),
+            after: short_hash_after.to_string(),
+            commits: get_new_commits(repo_dir, &head_before.sha, &hash_after),
+        })
+    } else {
+        None
+    };
+
+    if stash_conflict {
+        // git keeps the stash entry when popping it fails, so nothing is lost
+        let status = PullStatus::StashConflict;
+        let remark: String = format!(
+            "{branch}({short_hash_before}) -> {branch}({short_hash_after}), your changes are still in 'git stash list'"
+        );
+        RepoResult {
+            change,
+            ..RepoResult::new(name, status, &remark)
+        }
+    } else if head_before.sha == hash_after {
         let status = PullStatus::UpToDate;
-        let remark: String = format!("{branch}({short_hash_before})");
-        print_status_line(name, &status, &remark);
+        let remark: String = format!(
+            "{branch}({short_hash_before}){attempts}{pruned}{shallow}{upstream_set}{no_remote_change}"
+        );
+        RepoResult::new(name, status, &remark)
     } else {
         let status = PullStatus::Updated;
-        let remark: String =
-            format!("{branch}({short_hash_before}) -> {branch}({short_hash_after})");
-        print_status_line(name, &status, &remark);
-    };
+        let remark: String = format!(
+            "{branch}({short_hash_before}) -> {branch}({short_hash_after}){attempts}{pruned}{shallow}{upstream_set}"
+        );
+        RepoResult {
+            change,
+            ..RepoResult::new(name, status, &remark)
+        }
+    }
+}
+
+/// Return the short hashes and subjects of the commits between before and after, newest first.
+/// The report is only a summary, so a log that can't be read just leaves the list empty.
+fn get_new_commits(repo_dir: &Path, before: &str, after: &str) -> Vec<(String, String)> {
+    let range = format!("{}..{}", before, after);
+    let log = git_stdout(repo_dir, &["log", "--format=%h%x09%s", &range]).unwrap_or_default();
+
+    log.lines()
+        .filter_map(|line| line.split_once('\t'))
+        .map(|(sha, subject)| (sha.to_string(), subject.to_string()))
+        .collect()
 }
 
 /// Get the current branch of the repo
-fn get_current_branch(repo_dir: &PathBuf) -> String {
-    let output: Output = Command::new("git")
-        .arg("branch")
-        .arg("--show-current")
-        .current_dir(repo_dir)
-        .output()
-        .expect("failed to execute process");
+fn get_current_branch(repo_dir: &Path) -> Result<String, SuperError> {
+    git_stdout(repo_dir, &["branch", "--show-current"])
+}
 
-    if !output.status.success() {
-        print!(
-            "Failed to fetch the repo. Error: {}",
-            String::from_utf8_lossy(&output.stderr)
-        );
+/// Get the default branch of the origin remote, which is what origin/HEAD points to. git only sets
+/// origin/HEAD when it clones a repo, so if it's missing, we look it up on the remote once, and
+/// git keeps it for the next time. Falls back to "master" if the remote can't tell.
+fn get_default_branch(repo_dir: &Path) -> String {
+    let origin_head = || {
+        git_stdout(
+            repo_dir,
+            &["symbolic-ref", "--short", "refs/remotes/origin/HEAD"],
+        )
+    };
+
+    let head = origin_head().or_else(|_| {
+        run_git(repo_dir, &["remote", "set-head", "origin", "--auto"])?;
+        origin_head()
+    });
+
+    match head {
+        Ok(head) => head.strip_prefix("origin/").unwrap_or(&head).to_string(),
+        Err(error) => {
+            log::debug!(
+                "Failed to get the default branch of {}, using master: {}",
+                repo_dir.display(),
+                error
+            );
+            "master".to_string()
+        }
+    }
+}
+
+/// Get the current branch, the commit hash of HEAD, and the state of the working tree with a
+/// single call to 'git status'
+///
+/// The branch is empty if HEAD is detached, and the hash is empty if there are no commits yet.
+fn get_head_state(repo_dir: &Path) -> Result<HeadState, SuperError> {
+    // Skip the (potentially slow) search for untracked files, we only count changes to tracked files
+    let status = git_stdout(
+        repo_dir,
+        &[
+            "status",
+            "--porcelain=v2",
+            "--branch",
+            "--untracked-files=no",
+        ],
+    )?;
+
+    let mut head = HeadState {
+        branch: String::new(),
+        sha: String::new(),
+        upstream: None,
+        ahead_behind: None,
+        changed: 0,
+    };
+
+    // The header lines look like this: '# branch.oid <commit> | (initial)',
+    // '# branch.head <branch> | (detached)', '# branch.upstream <upstream>' and
+    // '# branch.ab +<ahead> -<behind>'. Every other line is a changed file.
+    for line in status.lines() {
+        if let Some(sha) = line.strip_prefix("# branch.oid ") {
+            if sha != "(initial)" {
+                head.sha = sha.to_string();
+            }
+        } else if let Some(branch) = line.strip_prefix("# branch.head ") {
+            if branch != "(detached)" {
+                head.branch = branch.to_string();
+            }
+        } else if let Some(upstream) = line.strip_prefix("# branch.upstream ") {
+            head.upstream = Some(upstream.to_string());
+        } else if let Some(ab) = line.strip_prefix("# branch.ab ") {
+            head.ahead_behind = ab.split_once(' ').map(|(ahead, behind)| {
+                (
+                    ahead.trim_start_matches('+').parse().unwrap_or(0),
+                    behind.trim_start_matches('-').parse().unwrap_or(0),
+                )
+            });
+        } else if !line.starts_with('#') {
+            head.changed += 1;
+        }
+    }
+
+    Ok(head)
+}
+
+/// Return the names of the submodules whose commit has changed in the super repo without being
+/// committed, with 'staged' if the new commit is staged, or 'modified-not-staged' if the submodule
+/// has moved further than what is staged. A submodule that only has uncommitted changes of its own
+/// doesn't count.
+fn get_uncommitted_submodules(
+    repo: &Repository,
+) -> Result<Vec<(String, &'static str)>, SuperError> {
+    let mut options = git2::StatusOptions::new();
+    options.include_untracked(false).exclude_submodules(false);
+    let statuses = repo.statuses(Some(&mut options))?;
+    let changed: Vec<String> = statuses
+        .iter()
+        .filter_map(|entry| entry.path().map(str::to_string))
+        .collect();
+
+    // git reports a submodule as modified for changes in its working tree as well, so only the
+    // submodules whose commit differs between HEAD, the index and the working tree are kept
+    let mut names = vec![];
+    for submodule in repo.submodules()? {
+        let path = submodule.path().to_string_lossy();
+        if !changed.iter().any(|changed| *changed == path) {
+            continue;
+        }
+        let head = submodule.head_id();
+        let index = submodule.index_id();
+        if submodule.workdir_id().is_some() && submodule.workdir_id() != index {
+            names.push((submodule_name(&submodule), "modified-not-staged"));
+        } else if head != index {
+            names.push((submodule_name(&submodule), "staged"));
+        }
     }
 
-    let stdout = output.stdout.to_ascii_lowercase();
-    return String::from_utf8_lossy(&stdout).trim().to_string();
+    Ok(names)
 }
 
-/// Pull the latest code for all submodules in the super repo
-fn command_pull() -> Result<(), git2::Error> {
-    let current_dir: std::path::PathBuf =
-        env::current_dir().expect("Failed to get current directory");
+/// Count one more repo in the given status
+fn count_status(counts: &mut Vec<(String, usize)>, status: &str) {
+    match counts.iter_mut().find(|(name, _)| name == status) {
+        Some((_, count)) => *count += 1,
+        None => counts.push((status.to_string(), 1)),
+    }
+}
 
-    pull_in_parallel(&current_dir)
+/// Print a footer that counts the repos in each status, the most common ones first, e.g.
+/// '50 repos: 45 clean, 3 dirty, 1 behind, 1 detached'
+fn print_health_footer(counts: &[(String, usize)]) {
+    if counts.is_empty() {
+        return;
+    }
+
+    let total: usize = counts.iter().map(|(_, count)| count).sum();
+    let mut counts = counts.to_vec();
+    // The repos finish in any order, so statuses with the same count are sorted by name, to keep the
+    // footer the same from run to run
+    counts.sort_by(|(a_status, a), (b_status, b)| b.cmp(a).then(a_status.cmp(b_status)));
+    let counts: Vec<String> = counts
+        .iter()
+        .map(|(status, count)| format!("{} {}", count, status))
+        .collect();
+
+    println!("{} repos: {}", total, counts.join(", "));
+}
+
+/// Print a single machine-readable line with the counts of the summary to stderr. The keys are
+/// stable, so scripts can parse them.
+fn print_summary_line(summary: &Summary) {
+    eprintln!(
+        "super: total={} updated={} uptodate={} failed={} skipped={}",
+        summary.total, summary.updated, summary.up_to_date, summary.failed, summary.skipped
+    );
+}
+
+/// Check the git directory of the repo for lock files that a git process left behind. With
+/// force_unlock, the ones that are older than STALE_LOCK_AGE are removed. Returns the remark for a
+/// repo that is still locked, or None if git can safely run in it.
+fn check_git_locks(repo_dir: &Path, force_unlock: bool) -> Result<Option<String>, SuperError> {
+    // The git directory of a submodule is in the .git/modules directory of the super repo
+    let git_dir = PathBuf::from(git_stdout(repo_dir, &["rev-parse", "--absolute-git-dir"])?);
+
+    let mut locked: Vec<&str> = vec![];
+    for lock in GIT_LOCK_FILES {
+        let path = git_dir.join(lock);
+        let Ok(metadata) = fs::metadata(&path) else {
+            continue;
+        };
+
+        let age = metadata
+            .modified()
+            .ok()
+            .and_then(|modified| modified.elapsed().ok())
+            .unwrap_or_default();
+        if force_unlock && age >= STALE_LOCK_AGE {
+            log::debug!("Removing the stale lock file {}", path.display());
+            fs::remove_file(&path).map_err(SuperError::Io)?;
+        } else {
+            locked.push(lock);
+        }
+    }
+
+    if locked.is_empty() {
+        return Ok(None);
+    }
+
+    let remedy = if force_unlock {
+        "it's too recent to be stale, is another git process running?"
+    } else {
+        "is another git process running? If not, use --force-unlock"
+    };
+    Ok(Some(format!("{} exists, {}", locked.join(", "), remedy)))
+}
+
+/// Check with 'git ls-remote', which doesn't transfer any objects, whether the given branch on
+/// origin is still at the commit that its remote-tracking branch points to. Errors count as a
+/// change, so that the fetch runs and reports them.
+fn is_remote_unchanged(repo_dir: &Path, branch: &str) -> bool {
+    let head = format!("refs/heads/{}", branch);
+    let Ok(remote) = git_stdout(repo_dir, &["ls-remote", "origin", &head]) else {
+        return false;
+    };
+    // The output looks like '<sha><TAB>refs/heads/<branch>'
+    let Some(remote_sha) = remote.split_whitespace().next() else {
+        return false;
+    };
+
+    resolve_ref(repo_dir, &format!("refs/remotes/origin/{}", branch))
+        .is_ok_and(|tracking_sha| tracking_sha == remote_sha)
 }
 
 /// Fetch the branch that is specified in .gitmodules.
-fn git_fetch(repo_dir: &PathBuf, branch: &str) {
-    let output: Output = Command::new("git")
-        .arg("fetch")
-        // TODO: Don't specify the remote here? Git, by default, will use the
-        // origin remote, unless there's an upstream branch configured for the current
-        // branch
-        .arg("origin")
-        .arg(branch)
-        .current_dir(repo_dir)
-        .output()
-        .expect("failed to execute process");
+///
+/// Transient failures (e.g. network hiccups) are retried up to `retries` times with exponential
+/// backoff. Hard failures (e.g. authentication errors) are never retried.
+///
+/// With --prune, all branches of origin are fetched instead, because git can only tell which
+/// remote-tracking branches are stale by comparing them against all branches on the remote.
+fn git_fetch(repo_dir: &Path, branch: &str, options: &PullOptions) -> FetchOutcome {
+    let mut attempts = 0;
+
+    loop {
+        attempts += 1;
+
+        match git_fetch_once(repo_dir, branch, options) {
+            Ok((pruned, updated)) => {
+                return FetchOutcome {
+                    attempts,
+                    pruned,
+                    updated,
+                    error: None,
+                }
+            }
+            Err(error) => {
+                if attempts > options.retries || !is_transient_fetch_error(&error.to_string()) {
+                    return FetchOutcome {
+                        attempts,
+                        pruned: 0,
+                        updated: false,
+                        error: Some(error),
+                    };
+                }
 
-    if !output.status.success() {
-        print!(
-            "Failed to fetch the repo. Error: {}",
-            String::from_utf8_lossy(&output.stderr)
-        );
+                // Wait 1s, 2s, 4s, ... between attempts, but never longer than a minute
+                let backoff = Duration::from_secs((1 << (attempts - 1).min(5)).min(60));
+                thread::sleep(backoff);
+            }
+        }
     }
 }
 
+/// Run 'git fetch' once. Returns how many remote-tracking branches were pruned, and whether the
+/// remote-tracking branch of the given branch moved.
+#[cfg(not(feature = "libgit2-fetch"))]
+fn git_fetch_once(
+    repo_dir: &Path,
+    branch: &str,
+    options: &PullOptions,
+) -> Result<(usize, bool), SuperError> {
+    // The compact output format abbreviates the names of the remote-tracking branches, which we
+    // parse below
+    let mut args = vec!["-c", "fetch.output=full", "fetch"];
+    if options.prune {
+        args.push("--prune");
+    }
+    if options.tags {
+        args.push("--tags");
+    }
+    let depth = options.depth.map(|depth| format!("--depth={}", depth));
+    if let Some(depth) = &depth {
+        args.push(depth);
+    }
+    // git refuses to unshallow a complete repo, but for us that just means there is nothing to do
+    if options.unshallow && is_shallow(repo_dir) {
+        args.push("--unshallow");
+    }
+
+    // TODO: Don't specify the remote here? Git, by default, will use the
+    // origin remote, unless there's an upstream branch configured for the current
+    // branch
+    args.push("origin");
+    if !options.prune {
+        args.push(branch);
+    }
+
+    let output = run_git(repo_dir, &args)?;
+
+    // git reports every pruned branch on stderr, e.g.
+    // ' - [deleted]         (none)     -> origin/old-feature', and every remote-tracking branch that
+    // it moved, e.g. '   1a2b3c4..5d6e7f8  master     -> origin/master'. Branches that were already
+    // up to date are not reported at all.
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    let pruned = stderr
+        .lines()
+        .filter(|line| line.trim_start().starts_with("- [deleted]"))
+        .count();
+    let tracking = format!("origin/{}", branch);
+    let updated = stderr.lines().any(|line| {
+        !line.trim_start().starts_with("- [deleted]")
+            && line
+                .split_once(" -> ")
+                .is_some_and(|(_, to)| to.split_whitespace().next() == Some(tracking.as_str()))
+    });
+
+    Ok((pruned, updated))
+}
+
+/// Fetch the given branch from origin once, with libgit2 instead of the git executable. Returns
+/// how many remote-tracking branches were pruned, and whether the remote-tracking branch of the
+/// given branch moved.
+#[cfg(feature = "libgit2-fetch")]
+fn git_fetch_once(
+    repo_dir: &Path,
+    branch: &str,
+    options: &PullOptions,
+) -> Result<(usize, bool), SuperError> {
+    // The version of libgit2 that we use can't fetch shallow
+    if options.depth.is_some() || options.unshallow {
+        let message = "--depth and --unshallow are not supported with libgit2-fetch";
+        return Err(SuperError::Git2(git2::Error::from_str(message)));
+    }
+
+    let repo = Repository::open(repo_dir)?;
+    let config = repo.config()?;
+    let mut remote = repo.find_remote("origin")?;
+    let url = remote.url().unwrap_or("origin").to_string();
+
+    // libgit2 reports a pruned branch as a tip that was updated to the zero oid. It only calls
+    // the callback for tips that actually changed.
+    let pruned = std::rc::Rc::new(std::cell::Cell::new(0));
+    let updated = std::rc::Rc::new(std::cell::Cell::new(false));
+    let mut callbacks = credential_callbacks(config);
+    let counter = std::rc::Rc::clone(&pruned);
+    let tracking_updated = std::rc::Rc::clone(&updated);
+    let tracking = format!("refs/remotes/origin/{branch}");
+    callbacks.update_tips(move |refname, _old, new| {
+        if new.is_zero() {
+            counter.set(counter.get() + 1);
+        } else if refname == tracking {
+            tracking_updated.set(true);
+        }
+        true
+    });
+
+    let mut fetch_options = git2::FetchOptions::new();
+    fetch_options.remote_callbacks(callbacks);
+
+    // Update the remote-tracking branch, just like 'git fetch origin <branch>' does. Pruning
+    // needs the configured refspecs of origin, which libgit2 uses when we pass none.
+    if options.tags {
+        fetch_options.download_tags(git2::AutotagOption::All);
+    }
+    let refspecs = if options.prune {
+        fetch_options.prune(git2::FetchPrune::On);
+        vec![]
+    } else {
+        vec![format!("+refs/heads/{branch}:refs/remotes/origin/{branch}")]
+    };
+
+    remote
+        .fetch(&refspecs, Some(&mut fetch_options), None)
+        .map(|_| (pruned.get(), updated.get()))
+        .map_err(|error| {
+            if error.code() == git2::ErrorCode::Auth {
+                SuperError::AuthenticationFailed {
+                    url,
+                    message: error.message().to_string(),
+                }
+            } else {
+                SuperError::Git2(error)
+            }
+        })
+}
+
+/// Callbacks that provide credentials to libgit2, in the same order that git itself tries them:
+///
+/// 1. For SSH remotes: the SSH agent, followed by the default keys in ~/.ssh
+/// 2. For HTTPS remotes: the configured git credential helper
+///
+/// libgit2 calls the callback again whenever the previous credentials were rejected, so we keep
+/// track of what we already tried and give up once everything has been rejected.
+#[cfg(feature = "libgit2-fetch")]
+fn credential_callbacks(config: git2::Config) -> git2::RemoteCallbacks<'static> {
+    let ssh_keys: Vec<PathBuf> = match dirs::home_dir() {
+        Some(home_dir) => ["id_ed25519", "id_ecdsa", "id_rsa"]
+            .iter()
+            .map(|key| home_dir.join(".ssh").join(key))
+            .filter(|key| key.exists())
+            .collect(),
+        None => Vec::new(),
+    };
+    let mut ssh_keys = ssh_keys.into_iter();
+    let mut tried_ssh_agent = false;
+    let mut tried_credential_helper = false;
+
+    let mut callbacks = git2::RemoteCallbacks::new();
+    callbacks.credentials(move |url, username_from_url, allowed_types| {
+        let username = username_from_url.unwrap_or("git");
+
+        if allowed_types.contains(git2::CredentialType::USERNAME) {
+            return git2::Cred::username(username);
+        }
+
+        if allowed_types.contains(git2::CredentialType::SSH_KEY) {
+            if !tried_ssh_agent {
+                tried_ssh_agent = true;
+                return git2::Cred::ssh_key_from_agent(username);
+            }
+
+            if let Some(key) = ssh_keys.next() {
+                return git2::Cred::ssh_key(username, None, &key, None);
+            }
+        }
+
+        if allowed_types.contains(git2::CredentialType::USER_PASS_PLAINTEXT)
+            && !tried_credential_helper
+        {
+            tried_credential_helper = true;
+            return git2::Cred::credential_helper(&config, url, username_from_url);
+        }
+
+        Err(git2::Error::from_str(
+            "authentication failed: no more credentials to try",
+        ))
+    });
+
+    callbacks
+}
+
+/// Return true if a failed fetch is worth retrying, based on the stderr of 'git fetch'.
+fn is_transient_fetch_error(stderr: &str) -> bool {
+    // Failures that will not go away by themselves, no matter how often we retry
+    const HARD_FAILURES: [&str; 6] = [
+        "authentication failed",
+        "permission denied",
+        "could not read username",
+        "repository not found",
+        "does not appear to be a git repository",
+        "couldn't find remote ref",
+    ];
+    // Failures that are typically caused by a flaky network
+    const TRANSIENT_FAILURES: [&str; 12] = [
+        "could not resolve host",
+        "connection timed out",
+        "operation timed out",
+        "connection reset",
+        "connection refused",
+        "network is unreachable",
+        "temporary failure",
+        "the remote end hung up unexpectedly",
+        "early eof",
+        "rpc failed",
+        // libgit2 phrases some of these differently from git
+        "failed to resolve address",
+        "failed to connect",
+    ];
+
+    let stderr = stderr.to_lowercase();
+
+    if HARD_FAILURES.iter().any(|failure| stderr.contains(failure)) {
+        return false;
+    }
+
+    TRANSIENT_FAILURES
+        .iter()
+        .any(|failure| stderr.contains(failure))
+}
+
+/// Ask the user to confirm a destructive action. Returns true if they answered yes.
+///
+/// This fails if stdin is not a terminal, since there is nobody who could answer (and we don't want
+/// to hang). Non-interactive callers have to pass --yes instead.
+fn confirm(prompt: &str) -> Result<bool, SuperError> {
+    if !std::io::stdin().is_terminal() {
+        return Err(SuperError::NotInteractive);
+    }
+
+    print!("{} [y/N] ", prompt);
+    std::io::stdout().flush().map_err(SuperError::Io)?;
+
+    let mut answer = String::new();
+    std::io::stdin()
+        .read_line(&mut answer)
+        .map_err(SuperError::Io)?;
+
+    Ok(matches!(answer.trim().to_lowercase().as_str(), "y" | "yes"))
+}
+
+/// Check out the given commit. With force, uncommitted changes are discarded.
+fn checkout(repo_dir: &Path, committish: &str, force: bool) -> Result<(), SuperError> {
+    if force {
+        run_git(repo_dir, &["checkout", "--quiet", "--force", committish])?;
+    } else {
+        run_git(repo_dir, &["checkout", "--quiet", committish])?;
+    }
+    Ok(())
+}
+
+/// Stash all uncommitted changes to tracked files
+fn stash_push(repo_dir: &Path) -> Result<(), SuperError> {
+    run_git(
+        repo_dir,
+        &["stash", "push", "--quiet", "--message", "super autostash"],
+    )?;
+    Ok(())
+}
+
+/// Apply the most recent stash entry and drop it. If applying it conflicts, git keeps the entry.
+fn stash_pop(repo_dir: &Path) -> Result<(), SuperError> {
+    run_git(repo_dir, &["stash", "pop", "--quiet"])?;
+    Ok(())
+}
+
+/// Return true if the repo is shallow, i.e. only has part of its history
+fn is_shallow(repo_dir: &Path) -> bool {
+    Repository::open(repo_dir).is_ok_and(|repo| repo.is_shallow())
+}
+
+/// Return true if the repo has uncommitted changes to tracked files
+fn is_dirty(repo_dir: &Path) -> Result<bool, SuperError> {
+    let status = git_stdout(repo_dir, &["status", "--porcelain", "--untracked-files=no"])?;
+    Ok(!status.is_empty())
+}
+
 /// Fast-forward the given branch, in the given repo.
-fn forward_branch(repo_dir: &PathBuf, branch: &str) {
-    let output: Output = Command::new("git")
-        .arg("merge")
-        .arg("--ff-only")
-        // TODO: Don't hardcode the remote here
-        .arg("origin")
-        .arg(branch)
-        .current_dir(repo_dir)
-        .output()
-        .expect("failed to execute process");
+fn forward_branch(repo_dir: &Path, branch: &str) -> Result<ForwardResult, SuperError> {
+    // TODO: Don't hardcode the remote here. Just 'origin' would mean origin/HEAD, which is the
+    // default branch of the remote, and not necessarily the tracked branch.
+    let tracking = format!("origin/{}", branch);
+    match run_git(repo_dir, &["merge", "--ff-only", &tracking]) {
+        Ok(_) => Ok(ForwardResult::FastForwarded),
+        // git refuses to merge with this message when the branches have diverged, but it also
+        // fails for other reasons, e.g. when uncommitted changes would be overwritten
+        Err(SuperError::Git { stderr, .. }) if stderr.contains("Not possible to fast-forward") => {
+            Ok(ForwardResult::Diverged)
+        }
+        Err(error) => Err(error),
+    }
+}
 
-    if !output.status.success() {
-        print!(
-            "Failed to fast-forward the repo. Error: {}",
-            String::from_utf8_lossy(&output.stderr)
-        );
+/// Append a record of a repo result to the --log-file, if one was given. Each record is a single
+/// line of key=value pairs, e.g.
+/// '2023-11-05T14:03:27Z command=foreach repo="foo" status=failed exit_code=1 message="..."', so
+/// that the file can be grepped.
+fn log_repo_result(command: &str, repo: &str, status: &str, exit_code: Option<i32>, message: &str) {
+    let Some(file) = LOG_FILE.get() else {
+        return;
+    };
+
+    let exit_code = exit_code.map_or("-".to_string(), |code| code.to_string());
+    let record = format!(
+        "{} command={} repo={} status={} exit_code={} message={}\n",
+        timestamp(),
+        command,
+        json_string(repo),
+        status,
+        exit_code,
+        json_string(&truncate(message.trim(), 200))
+    );
+
+    write_log_record(file, &record);
+}
+
+/// Append a record to the --log-file
+fn write_log_record(file: &Mutex<fs::File>, record: &str) {
+    // Failing to log shouldn't fail the command itself
+    let mut file = file.lock().unwrap();
+    if let Err(error) = file.write_all(record.as_bytes()) {
+        log::warn!("Failed to write to the log file: {}", error);
+    }
+}
+
+/// Return the current time in UTC, e.g. '2023-11-05T14:03:27Z'
+fn timestamp() -> String {
+    let seconds = SystemTime::now()
+        .duration_since(SystemTime::UNIX_EPOCH)
+        .map_or(0, |duration| duration.as_secs());
+    let (days, time) = (seconds / 86400, seconds % 86400);
+
+    // Convert the days since 1970-01-01 to a date, with the civil_from_days algorithm from
+    // http://howardhinnant.github.io/date_algorithms.html
+    let days = days + 719468;
+    let era = days / 146097;
+    let day_of_era = days - era * 146097;
+    let year_of_era =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36524 - day_of_era / 146096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let shifted_month = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * shifted_month + 2) / 5 + 1;
+    let month = if shifted_month < 10 {
+        shifted_month + 3
+    } else {
+        shifted_month - 9
+    };
+    let year = year_of_era + era * 400 + u64::from(month <= 2);
+
+    format!(
+        "{:04}-{:02}-{:02}T{:02}:{:02}:{:02}Z",
+        year,
+        month,
+        day,
+        time / 3600,
+        time % 3600 / 60,
+        time % 60
+    )
+}
+
+/// Size the repo column of the output to the longest of the given repo names. This has to be
+/// called before the first line is printed, because later calls have no effect.
+fn init_layout<'a>(names: impl Iterator<Item = &'a str>) {
+    let longest = names.map(|name| name.chars().count()).max().unwrap_or(0);
+    LAYOUT.get_or_init(|| Layout::new(longest));
+}
+
+/// Return the layout of this run. Commands that don't know their repos upfront get the default
+/// layout.
+fn layout() -> &'static Layout {
+    LAYOUT.get_or_init(|| Layout::new(16))
+}
+
+/// Shorten the text to the given number of characters, and mark it with an ellipsis if it was
+/// truncated.
+fn truncate(text: &str, width: usize) -> String {
+    if text.chars().count() <= width {
+        text.to_string()
+    } else {
+        let mut truncated: String = text.chars().take(width.saturating_sub(1)).collect();
+        truncated.push('…');
+        truncated
     }
 }
 
-/// Print the status of the given repo
-fn print_status_line(repo: &str, status: &PullStatus, remark: &str) {
-    // Note: We have to convert the pull status to a string first, because we want to align the string,
-    // and alignment is not implemented for the Debug trait.
-    let status_str = status.to_str();
+/// Print a status line for the given repo. This is used by all commands that report on each repo,
+/// so that their output looks the same.
+fn print_repo_line(repo: &str, status: &str, remark: &str) {
+    let layout = layout();
+    let width = layout.repo;
+    let status_width = Layout::STATUS;
+    let remark = match layout.remark {
+        Some(remark_width) => truncate(remark, remark_width),
+        None => remark.to_string(),
+    };
 
-    // neon pink (\x1b[38;5;198;1m), bright cyan(\x1b[1;36), white (\x1b[1;37m)
-    println!("\x1b[38;5;198;1m{repo:16} \x1b[1;36m{status_str:10} \x1b[1;37m   {remark}\x1b[0m")
+    // neon pink, bright cyan, white
+    println!(
+        "{} {} {}",
+        paint("38;5;198;1", &format!("{repo:width$}")),
+        paint("1;36", &format!("{status:status_width$}")),
+        paint("1;37", &format!("   {remark}"))
+    )
+}
+
+/// Wrap the text in the given ANSI style (e.g. '1;36' for bright cyan), unless colors are disabled
+fn paint(style: &str, text: &str) -> String {
+    if *COLOR.get_or_init(|| ColorChoice::Auto.enabled()) {
+        format!("\x1b[{}m{}\x1b[0m", style, text)
+    } else {
+        text.to_string()
+    }
 }
 
 /// Return the commit hash that HEAD points to.
-fn get_head_sha(repo_dir: &PathBuf) -> String {
-    return resolve_ref(repo_dir, "HEAD".to_string());
+fn get_head_sha(repo_dir: &Path) -> Result<String, SuperError> {
+    resolve_ref(repo_dir, "HEAD")
+}
+
+/// Return the name of the tag that points to the given commit, or None if there is no tag. Unlike
+/// resolve_ref, this tells tags and branches apart.
+fn get_tag(repo_dir: &Path, committish: &str) -> Result<Option<String>, SuperError> {
+    match run_git(
+        repo_dir,
+        &["describe", "--tags", "--exact-match", committish],
+    ) {
+        Ok(output) => Ok(Some(
+            String::from_utf8_lossy(&output.stdout).trim().to_string(),
+        )),
+        // git describe fails with this message, if no tag points to the commit
+        Err(SuperError::Git { stderr, .. }) if stderr.contains("no tag exactly matches") => {
+            Ok(None)
+        }
+        Err(error) => Err(error),
+    }
 }
 
 /// Return the hash of the commit (or tag) that the ref points to.
-fn resolve_ref(repo_dir: &PathBuf, committish: String) -> String {
-    let output: Output = Command::new("git")
-        .arg("log")
-        .arg("-1")
-        .arg("--format=format:%H")
-        .arg(committish)
-        .current_dir(repo_dir)
-        .output()
-        .expect("failed to execute process");
+fn resolve_ref(repo_dir: &Path, committish: &str) -> Result<String, SuperError> {
+    git_stdout(repo_dir, &["log", "-1", "--format=format:%H", committish])
+}
 
-    if !output.status.success() {
-        print!(
-            "Failed to resolve the given reference. Error: {}",
-            String::from_utf8_lossy(&output.stderr)
-        );
+/// Run git with the given arguments in the given directory.
+///
+/// All git subprocesses should be spawned through this function, so that they are logged and their
+/// errors are handled in the same way. Fails if git can't be started or exits with a non-zero
+/// status.
+///
+/// Nobody can answer a prompt for credentials, so git and ssh fail instead of asking for them. An
+/// ssh command that the user set with GIT_SSH_COMMAND or GIT_SSH is left alone.
+fn run_git(dir: &Path, args: &[&str]) -> Result<Output, SuperError> {
+    let mut command = Command::new("git");
+    command.env("GIT_TERMINAL_PROMPT", "0");
+    if env::var_os("GIT_SSH_COMMAND").is_none() && env::var_os("GIT_SSH").is_none() {
+        command.env("GIT_SSH_COMMAND", "ssh -o BatchMode=yes");
     }
+    let output = command
+        .args(args)
+        .current_dir(dir)
+        .logged_output()
+        .map_err(|error| SuperError::Spawn {
+            program: "git".to_string(),
+            error,
+        })?;
 
-    return output.stdout.escape_ascii().to_string();
+    if output.status.success() {
+        Ok(output)
+    } else {
+        Err(SuperError::Git {
+            args: args.join(" "),
+            stderr: String::from_utf8_lossy(&output.stderr).to_string(),
+        })
+    }
 }
 
-/// Return a 7 character long hash for a given commit.
-fn get_short_hash(repo_dir: &PathBuf, committish: &String) -> String {
-    let output: Output = Command::new("git")
-        .arg("rev-parse")
-        .arg("--short")
-        .arg(committish)
-        .current_dir(repo_dir)
-        .output()
-        .expect("failed to execute process");
+/// Run git like run_git, and return its stdout without surrounding whitespace
+fn git_stdout(dir: &Path, args: &[&str]) -> Result<String, SuperError> {
+    let output = run_git(dir, args)?;
+    Ok(String::from_utf8_lossy(&output.stdout).trim().to_string())
+}
 
-    if !output.status.success() {
-        print!(
-            "Failed to get a short hash for the given commit. Error: {}",
-            String::from_utf8_lossy(&output.stderr)
-        );
+/// Return a 7 character long hash for a given commit hash.
+///
+/// This is what 'git rev-parse --short' does in the common case, without the extra subprocess.
+fn short_hash(hash: &str) -> &str {
+    hash.get(..7).unwrap_or(hash)
+}
+
+/// Quote and escape the given string, so that it can be used as a JSON string
+fn json_string(s: &str) -> String {
+    let mut quoted = String::with_capacity(s.len() + 2);
+    quoted.push('"');
+
+    for c in s.chars() {
+        match c {
+            '"' => quoted.push_str("\\\""),
+            '\\' => quoted.push_str("\\\\"),
+            '\n' => quoted.push_str("\\n"),
+            '\r' => quoted.push_str("\\r"),
+            '\t' => quoted.push_str("\\t"),
+            c if (c as u32) < 0x20 => quoted.push_str(&format!("\\u{:04x}", c as u32)),
+            c => quoted.push(c),
+        }
     }
 
-    let stdout = String::from_utf8_lossy(&output.stdout);
-    return stdout.trim().to_string();
-}
-
-/// Get the user's custom commands from ~/.config/super/commands
-fn get_commands() -> Vec<String> {
-    if let Some(home_dir) = dirs::home_dir() {
-        // Get all executable files
-        let directory_path: PathBuf = PathBuf::from(".config/super/commands");
-        let combined_path = home_dir.join(&directory_path);
-
-        // Collect all commands
-        let mut commands: Vec<String> = Vec::new();
-
-        match fs::read_dir(&combined_path) {
-            Ok(entries) => {
-                for entry in entries {
-                    if let Ok(entry) = entry {
-                        let file_name = entry.file_name();
-
-                        if let Ok(metadata) = entry.metadata() {
-                            if metadata.is_dir() {
-                                println!("Skipping {:?} because it is a directory.", entry.path())
-                            } else {
-                                // We only want to add executable files to the list of commands
-                                if metadata.permissions().mode() & 0o111 != 0 {
-                                    commands.push(file_name.to_string_lossy().to_string());
-                                } else {
-                                    println!(
-                                        "Skipping {:?} because it is not executable.",
-                                        entry.path()
-                                    )
-                                }
-                            }
-                        } else {
-                            println!("Skipping {:?} because of invalid metadata.", entry.path())
-                        }
-                    }
-                }
+    quoted.push('"');
+    quoted
+}
 
-                return commands;
-            }
-            Err(error) => {
-                eprintln!("Error reading {:?}: {:?}", &combined_path, error);
-                return Vec::new();
+/// Get the names of all executable files in the given directory. The names are kept as they are, so
+/// that they can be run even if they are not valid UTF-8.
+fn get_executables(directory: &Path) -> std::io::Result<Vec<OsString>> {
+    let mut executables: Vec<OsString> = Vec::new();
+
+    for entry in fs::read_dir(directory)?.flatten() {
+        let file_name = entry.file_name();
+
+        if let Ok(metadata) = entry.metadata() {
+            if metadata.is_dir() {
+                println!("Skipping {:?} because it is a directory.", entry.path())
+            } else {
+                // We only want to add executable files to the list
+                if metadata.permissions().mode() & 0o111 != 0 {
+                    executables.push(file_name);
+                } else {
+                    println!("Skipping {:?} because it is not executable.", entry.path())
+                }
             }
+        } else {
+            println!("Skipping {:?} because of invalid metadata.", entry.path())
         }
+    }
+
+    Ok(executables)
+}
+
+/// Run the hook with the given name (e.g. 'pre-pull') from ~/.config/super/hooks, if there is one.
+/// Does nothing if the hook doesn't exist.
+///
+/// The names of the affected repos are passed as arguments, and SUPER_REPO_STATUSES has a
+/// '<name>\t<status>' line for every repo. The hook runs in the root of the super repo.
+fn run_hook(hook: &str, affected: &[&str], statuses: &[(&str, &str)]) -> Result<(), SuperError> {
+    let Some(home_dir) = dirs::home_dir() else {
+        return Ok(());
+    };
+    let hooks_dir = home_dir.join(".config/super/hooks");
+
+    // Hooks are optional, so a missing hooks directory is not an error
+    let hooks = get_executables(&hooks_dir).unwrap_or_default();
+    if !hooks.iter().any(|name| name.as_os_str() == hook) {
+        return Ok(());
+    }
+
+    let statuses: Vec<String> = statuses
+        .iter()
+        .map(|(name, status)| format!("{}\t{}", name, status))
+        .collect();
+
+    log::debug!("Running the {} hook for {} repos", hook, affected.len());
+
+    let status = Command::new(hooks_dir.join(hook))
+        .args(affected)
+        .env("SUPER_REPO_STATUSES", statuses.join("\n"))
+        .status()
+        .map_err(|error| SuperError::Spawn {
+            program: hook.to_string(),
+            error,
+        })?;
+
+    if status.success() {
+        Ok(())
     } else {
-        eprintln!("Unable to determine the home directory");
-        return Vec::new();
+        Err(SuperError::HookFailed {
+            hook: hook.to_string(),
+            status,
+        })
     }
 }
 
-// This function discovers all git repos in the current directory
-// that super is invoked in.
-fn get_git_repos() -> Vec<String> {
+// This function discovers all git repos in the given directory (typically the one
+// that super is invoked in), including the directory itself.
+//
+// The paths are returned as they are, so paths with spaces or non-UTF-8 characters are preserved.
+fn get_git_repos(dir: &Path) -> Vec<PathBuf> {
     // We use 'find' to discover all repos with a .git directory
-    let output: Output = Command::new("find")
-        .arg(".")
+    let output: Output = match Command::new("find")
+        .arg(dir)
         // Optimization: Limit search depth to 2 levels
         .arg("-maxdepth")
         .arg("2")
         .arg("-name")
         .arg(".git")
-        .output()
-        .expect("failed to execute the find process");
+        // Separate the paths with NUL bytes, since paths can contain newlines
+        .arg("-print0")
+        .logged_output()
+    {
+        Ok(output) => output,
+        Err(error) => {
+            println!(
+                "Failed to run find to discover all git repos. Error: {}",
+                error
+            );
+            return Vec::new();
+        }
+    };
 
     if !output.status.success() {
         print!(
@@ -493,12 +2364,14 @@ fn get_git_repos() -> Vec<String> {
         );
         return Vec::new();
     } else {
-        let lines = String::from_utf8_lossy(&output.stdout)
-            .trim()
-            .lines()
+        let repos = output
+            .stdout
+            .split(|byte| *byte == b'\0')
+            .filter(|path| !path.is_empty())
             // Drop the "/.git" at the end of the path
-            .map(|x| x.to_string().replace("/.git", ""))
+            .filter_map(|path| Path::new(OsStr::from_bytes(path)).parent())
+            .map(|path| path.to_path_buf())
             .collect();
-        return lines;
+        return repos;
     }
 }
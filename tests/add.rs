@@ -0,0 +1,179 @@
+//! Tests for 'super add'
+
+mod common;
+
+use common::{create_remote, create_super_repo, git, run_super, super_repo_with, super_stdout};
+
+#[test]
+fn add_expands_shorthands_from_superconfig() {
+    let tmp = tempfile::tempdir().unwrap();
+    let remotes = tmp.path().join("remotes");
+    let remote = remotes.join("repo.git");
+    let super_repo = tmp.path().join("super");
+    create_remote(&remote);
+    create_super_repo(&super_repo);
+
+    let config = format!("[shorthand \"local\"]\n\turl = {}/\n", remotes.display());
+    std::fs::write(super_repo.join(".superconfig"), config).unwrap();
+
+    run_super(&super_repo, &["add", "local:repo"]);
+
+    let gitmodules = std::fs::read_to_string(super_repo.join(".gitmodules")).unwrap();
+    assert!(
+        gitmodules.contains(remote.to_str().unwrap()),
+        "unexpected .gitmodules: {}",
+        gitmodules
+    );
+}
+
+#[test]
+fn add_no_clone_adopts_an_existing_checkout() {
+    let (_tmp, remote, super_repo) = super_repo_with(&[]);
+    git(
+        &super_repo,
+        &["clone", "--quiet", remote.to_str().unwrap(), "existing"],
+    );
+    // A local change shows that the directory was adopted, not cloned again
+    std::fs::write(super_repo.join("existing").join("local.txt"), "").unwrap();
+
+    let output = run_super(
+        &super_repo,
+        &["add", "--no-clone", remote.to_str().unwrap(), "existing"],
+    );
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(!stdout.contains("Warning"), "unexpected output: {}", stdout);
+
+    let gitmodules = std::fs::read_to_string(super_repo.join(".gitmodules")).unwrap();
+    assert!(
+        gitmodules.contains("path = existing"),
+        "unexpected .gitmodules: {}",
+        gitmodules
+    );
+    assert!(super_repo.join("existing").join("local.txt").exists());
+}
+
+#[test]
+fn init_add_adds_the_repos_and_reports_the_ones_that_failed() {
+    let tmp = tempfile::tempdir().unwrap();
+    let remote = tmp.path().join("remote");
+    let missing = tmp.path().join("missing");
+    let super_repo = tmp.path().join("super");
+    create_remote(&remote);
+    std::fs::create_dir(&super_repo).unwrap();
+
+    let output = run_super(
+        &super_repo,
+        &[
+            "init",
+            "--add",
+            missing.to_str().unwrap(),
+            remote.to_str().unwrap(),
+        ],
+    );
+    let stdout = String::from_utf8_lossy(&output.stdout);
+
+    // The repo after the one that failed is still added
+    assert!(super_repo.join("remote").join("file.txt").exists());
+    assert!(
+        stdout.contains(&format!("Failed to add 1: {}", missing.display())),
+        "unexpected output: {}",
+        stdout
+    );
+}
+
+#[test]
+fn add_leaves_duplicates_alone_and_rejects_invalid_repos() {
+    let (_tmp, remote, super_repo) = super_repo_with(&[]);
+    let url = remote.to_str().unwrap();
+    run_super(&super_repo, &["add", url]);
+
+    let stdout = super_stdout(&super_repo, &["add", url]);
+    assert!(
+        stdout.contains("remote is already a submodule at remote, so there is nothing to add"),
+        "unexpected output: {}",
+        stdout
+    );
+
+    let stdout = super_stdout(&super_repo, &["add", "not a repo"]);
+    assert!(
+        stdout.contains("is neither a URL nor the path of a repo"),
+        "unexpected output: {}",
+        stdout
+    );
+}
+
+#[test]
+fn add_outside_a_git_repo_suggests_init() {
+    let tmp = tempfile::tempdir().unwrap();
+    let remote = tmp.path().join("remote");
+    let workspace = tmp.path().join("workspace");
+    create_remote(&remote);
+    std::fs::create_dir(&workspace).unwrap();
+
+    let stdout = super_stdout(&workspace, &["add", remote.to_str().unwrap()]);
+    assert!(
+        stdout.contains("run 'super init'"),
+        "unexpected output: {}",
+        stdout
+    );
+    assert!(!workspace.join("remote").exists());
+}
+
+#[test]
+fn add_branch_from_head_records_the_default_branch_of_the_remote() {
+    let (_tmp, remote, super_repo) = super_repo_with(&[]);
+    git(&remote, &["checkout", "--quiet", "-b", "develop"]);
+
+    run_super(
+        &super_repo,
+        &["add", "--branch-from-head", remote.to_str().unwrap()],
+    );
+
+    let branch = git(
+        &super_repo,
+        &["config", "-f", ".gitmodules", "submodule.remote.branch"],
+    );
+    assert_eq!(branch.trim(), "develop");
+}
+
+#[test]
+fn add_skips_submodules_that_are_not_initialized_without_a_terminal() {
+    let (_tmp, remote, super_repo) = super_repo_with(&[]);
+    let url = remote.to_str().unwrap();
+    run_super(&super_repo, &["add", url]);
+    git(
+        &super_repo,
+        &["submodule", "--quiet", "deinit", "--force", "remote"],
+    );
+
+    // There is no terminal to ask whether to initialize it, so nothing changes
+    let output = run_super(&super_repo, &["add", url]);
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(output.status.success(), "unexpected output: {}", stdout);
+    assert!(
+        stdout.contains("Skipped remote, run 'super pull --init'"),
+        "unexpected output: {}",
+        stdout
+    );
+    assert!(!super_repo.join("remote").join(".git").exists());
+}
+
+#[test]
+fn add_explains_repos_that_are_in_the_index_but_not_in_gitmodules() {
+    let (_tmp, remote, super_repo) = super_repo_with(&[]);
+    let url = remote.to_str().unwrap();
+    git(&super_repo, &["clone", "--quiet", url, "remote"]);
+    git(&super_repo, &["add", "remote"]);
+
+    let stdout = super_stdout(&super_repo, &["add", url]);
+    assert!(
+        stdout.contains("remote is already in the index of the super repo"),
+        "unexpected output: {}",
+        stdout
+    );
+    assert!(
+        stdout.contains("git rm --cached remote"),
+        "unexpected output: {}",
+        stdout
+    );
+}
@@ -0,0 +1,42 @@
+//! Tests for 'super clean'
+
+mod common;
+
+use common::{line_for, run_super, super_repo_with, super_stdout};
+
+#[test]
+fn clean_only_lists_the_untracked_files_without_force() {
+    let (_tmp, _remote, super_repo) = super_repo_with(&["alpha", "beta"]);
+    let alpha = super_repo.join("alpha");
+    std::fs::write(alpha.join("artifact.o"), "").unwrap();
+    std::fs::create_dir(alpha.join("build")).unwrap();
+    std::fs::write(alpha.join("build").join("output"), "").unwrap();
+
+    let stdout = super_stdout(&super_repo, &["clean"]);
+    assert!(
+        line_for(&stdout, "alpha").contains("1 files, 1 directories"),
+        "unexpected output: {}",
+        stdout
+    );
+    assert!(
+        line_for(&stdout, "beta").contains("nothing to remove"),
+        "unexpected output: {}",
+        stdout
+    );
+    assert!(
+        stdout.contains("    build/"),
+        "unexpected output: {}",
+        stdout
+    );
+    assert!(alpha.join("artifact.o").exists());
+
+    // Without a terminal, --force needs --yes
+    run_super(&super_repo, &["clean", "--force"]);
+    assert!(alpha.join("artifact.o").exists());
+
+    let stdout = super_stdout(&super_repo, &["clean", "--force", "--yes"]);
+    assert!(stdout.contains("removed"), "unexpected output: {}", stdout);
+    assert!(!alpha.join("artifact.o").exists());
+    assert!(!alpha.join("build").exists());
+    assert!(alpha.join("file.txt").exists());
+}
@@ -0,0 +1,35 @@
+//! Tests for 'super commit'
+
+mod common;
+
+use common::{commit_file, git, super_repo_with, super_stdout};
+
+#[test]
+fn commit_describes_the_submodules_that_moved() {
+    let (_tmp, _remote, super_repo) = super_repo_with(&["moved", "unmoved"]);
+    git(&super_repo, &["commit", "--quiet", "-m", "add submodules"]);
+
+    let moved = super_repo.join("moved");
+    commit_file(&moved, "second");
+    commit_file(&moved, "third");
+
+    let stdout = super_stdout(
+        &super_repo,
+        &["commit", "-m", "weekly update", "--describe"],
+    );
+    assert!(
+        stdout.contains("committed"),
+        "unexpected output: {}",
+        stdout
+    );
+
+    let message = git(&super_repo, &["log", "-1", "--format=%B"]);
+    assert!(message.starts_with("weekly update\n\n"), "{}", message);
+    assert!(message.contains("moved: "), "{}", message);
+    assert!(message.contains("(2 commits)"), "{}", message);
+    assert!(!message.contains("unmoved"), "{}", message);
+
+    // The new commit of the submodule is recorded in the super repo
+    let status = git(&super_repo, &["status", "--porcelain"]);
+    assert!(status.trim().is_empty(), "{}", status);
+}
@@ -0,0 +1,130 @@
+//! Helpers for the integration tests, which run the super binary against real git repos in
+//! temporary directories
+
+// Every test file only uses some of the helpers
+#![allow(dead_code)]
+
+use std::path::{Path, PathBuf};
+use std::process::{Command, Output};
+use tempfile::TempDir;
+
+/// Run git in the given directory, and return its stdout. Panics if git fails.
+pub fn git(dir: &Path, args: &[&str]) -> String {
+    let output = Command::new("git")
+        .args([
+            "-c",
+            "user.name=super",
+            "-c",
+            "user.email=super@example.com",
+        ])
+        // Allow local paths as submodule URLs
+        .args(["-c", "protocol.file.allow=always"])
+        .args(args)
+        .current_dir(dir)
+        .output()
+        .expect("failed to execute git");
+
+    assert!(
+        output.status.success(),
+        "git {:?} failed: {}",
+        args,
+        String::from_utf8_lossy(&output.stderr)
+    );
+
+    String::from_utf8_lossy(&output.stdout).to_string()
+}
+
+/// Return a command that runs the super binary in the given directory, for tests that need to
+/// customize it (e.g. its environment)
+pub fn super_command(dir: &Path) -> Command {
+    let mut command = Command::new(env!("CARGO_BIN_EXE_super"));
+    command
+        // Allow local paths as submodule URLs in the git commands that super runs
+        .env("GIT_CONFIG_COUNT", "1")
+        .env("GIT_CONFIG_KEY_0", "protocol.file.allow")
+        .env("GIT_CONFIG_VALUE_0", "always")
+        // Commits that super makes need an identity, which the test machine may not have
+        .env("GIT_AUTHOR_NAME", "super")
+        .env("GIT_AUTHOR_EMAIL", "super@example.com")
+        .env("GIT_COMMITTER_NAME", "super")
+        .env("GIT_COMMITTER_EMAIL", "super@example.com")
+        .current_dir(dir);
+    command
+}
+
+/// Run the super binary in the given directory
+pub fn run_super(dir: &Path, args: &[&str]) -> Output {
+    super_command(dir)
+        .args(args)
+        .output()
+        .expect("failed to execute super")
+}
+
+/// Run the super binary in the given directory, and return its stdout
+pub fn super_stdout(dir: &Path, args: &[&str]) -> String {
+    String::from_utf8_lossy(&run_super(dir, args).stdout).to_string()
+}
+
+/// Commit the given content to 'file.txt' in the given repo
+pub fn commit_file(dir: &Path, content: &str) {
+    std::fs::write(dir.join("file.txt"), content).unwrap();
+    git(dir, &["add", "file.txt"]);
+    git(dir, &["commit", "--quiet", "-m", content]);
+}
+
+/// Create a repo on the master branch with a single commit, that can be used as a remote
+pub fn create_remote(dir: &Path) {
+    std::fs::create_dir_all(dir).unwrap();
+    git(dir, &["init", "--quiet", "--initial-branch=master"]);
+    commit_file(dir, "first");
+}
+
+/// Create an empty super repo
+pub fn create_super_repo(dir: &Path) {
+    std::fs::create_dir_all(dir).unwrap();
+    git(dir, &["init", "--quiet"]);
+}
+
+/// Add the given remote as a submodule at the given path, and check out its master branch
+pub fn add_submodule(super_repo: &Path, remote: &Path, path: &str) {
+    git(
+        super_repo,
+        &[
+            "submodule",
+            "--quiet",
+            "add",
+            remote.to_str().unwrap(),
+            path,
+        ],
+    );
+    // 'git submodule add' leaves the submodule on a detached HEAD
+    git(&super_repo.join(path), &["checkout", "--quiet", "master"]);
+}
+
+/// Create a temporary directory with a remote and a super repo that has the remote as a submodule
+/// under each of the given names, which is where most tests start. Returns the directory, which is
+/// deleted when it is dropped, and the paths of the remote and the super repo.
+pub fn super_repo_with(names: &[&str]) -> (TempDir, PathBuf, PathBuf) {
+    let tmp = tempfile::tempdir().unwrap();
+    let remote = tmp.path().join("remote");
+    let super_repo = tmp.path().join("super");
+    create_remote(&remote);
+    create_super_repo(&super_repo);
+    for name in names {
+        add_submodule(&super_repo, &remote, name);
+    }
+    (tmp, remote, super_repo)
+}
+
+/// Return the commit hash of HEAD in the given repo
+pub fn head_sha(dir: &Path) -> String {
+    git(dir, &["rev-parse", "HEAD"]).trim().to_string()
+}
+
+/// Return the line that super printed for the given repo
+pub fn line_for<'a>(stdout: &'a str, repo: &str) -> &'a str {
+    stdout
+        .lines()
+        .find(|line| line.contains(repo))
+        .unwrap_or_else(|| panic!("no line for {} in output: {}", repo, stdout))
+}
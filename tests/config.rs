@@ -0,0 +1,51 @@
+//! Tests for 'super config'
+
+mod common;
+
+use common::{git, super_repo_with, super_stdout};
+
+#[test]
+fn config_sets_and_prints_the_branch_of_a_submodule() {
+    let (_tmp, _remote, super_repo) = super_repo_with(&["sub"]);
+
+    super_stdout(&super_repo, &["config", "sub", "branch", "develop"]);
+
+    let branch = git(
+        &super_repo,
+        &["config", "-f", ".gitmodules", "submodule.sub.branch"],
+    );
+    assert_eq!(branch.trim(), "develop");
+
+    let stdout = super_stdout(&super_repo, &["config", "sub", "branch"]);
+    assert_eq!(stdout.trim(), "develop");
+}
+
+#[test]
+fn config_rejects_unknown_keys_and_invalid_values() {
+    let (_tmp, _remote, super_repo) = super_repo_with(&["sub"]);
+    let before = std::fs::read_to_string(super_repo.join(".gitmodules")).unwrap();
+
+    let stdout = super_stdout(&super_repo, &["config", "sub", "fetchRecurse", "yes"]);
+    assert!(
+        stdout.contains("Unknown key"),
+        "unexpected output: {}",
+        stdout
+    );
+
+    let stdout = super_stdout(&super_repo, &["config", "sub", "update", "!rm -rf ."]);
+    assert!(
+        stdout.contains("Invalid update"),
+        "unexpected output: {}",
+        stdout
+    );
+
+    let stdout = super_stdout(&super_repo, &["config", "sub", "branch", "a..b"]);
+    assert!(
+        stdout.contains("Invalid branch"),
+        "unexpected output: {}",
+        stdout
+    );
+
+    let after = std::fs::read_to_string(super_repo.join(".gitmodules")).unwrap();
+    assert_eq!(before, after);
+}
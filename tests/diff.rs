@@ -0,0 +1,37 @@
+//! Tests for 'super diff'
+
+mod common;
+
+use common::{commit_file, git, super_repo_with, super_stdout};
+
+#[test]
+fn diff_shows_submodules_that_moved_since_they_were_staged() {
+    let (_tmp, _remote, super_repo) = super_repo_with(&["moved", "unmoved"]);
+
+    let stdout = super_stdout(&super_repo, &["diff"]);
+    assert!(
+        stdout.contains("No pending changes"),
+        "unexpected output: {}",
+        stdout
+    );
+
+    commit_file(&super_repo.join("moved"), "second");
+
+    let stdout = super_stdout(&super_repo, &["diff"]);
+    let line = stdout
+        .lines()
+        .find(|line| line.contains("changed"))
+        .unwrap_or_else(|| panic!("unexpected output: {}", stdout));
+    assert!(line.contains("moved"), "{}", line);
+    assert!(line.contains("first -> "), "{}", line);
+    assert!(line.contains(" second"), "{}", line);
+    assert!(!stdout.contains("unmoved"), "unexpected output: {}", stdout);
+
+    git(&super_repo, &["add", "moved"]);
+    let stdout = super_stdout(&super_repo, &["diff"]);
+    assert!(
+        stdout.contains("No pending changes"),
+        "unexpected output: {}",
+        stdout
+    );
+}
@@ -0,0 +1,29 @@
+//! Tests for 'super fetch'
+
+mod common;
+
+use common::{commit_file, head_sha, line_for, super_repo_with, super_stdout};
+
+#[test]
+fn fetch_downloads_new_commits_without_fast_forwarding() {
+    let (_tmp, remote, super_repo) = super_repo_with(&["alpha"]);
+    let alpha = super_repo.join("alpha");
+    let before = head_sha(&alpha);
+    commit_file(&remote, "second");
+
+    let stdout = super_stdout(&super_repo, &["fetch"]);
+    assert!(
+        line_for(&stdout, "alpha").contains("fetched"),
+        "unexpected output: {}",
+        stdout
+    );
+    assert_eq!(head_sha(&alpha), before, "fetch moved the branch");
+
+    // The new commits are already there, so fetching again doesn't download anything
+    let stdout = super_stdout(&super_repo, &["fetch"]);
+    assert!(
+        line_for(&stdout, "alpha").contains("up to date"),
+        "unexpected output: {}",
+        stdout
+    );
+}
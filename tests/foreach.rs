@@ -0,0 +1,422 @@
+//! Tests for 'super foreach'
+
+mod common;
+
+use common::{
+    add_submodule, create_remote, create_super_repo, git, line_for, run_super, super_repo_with,
+    super_stdout,
+};
+
+#[test]
+fn foreach_prefixes_each_line_with_the_repo() {
+    let (_tmp, _remote, super_repo) = super_repo_with(&["alpha", "beta"]);
+
+    let stdout = super_stdout(
+        &super_repo,
+        &[
+            "foreach",
+            "--parallel-output-prefix",
+            "printf",
+            "one\\ntwo\\n",
+        ],
+    );
+
+    for repo in ["alpha", "beta"] {
+        let lines: Vec<&str> = stdout
+            .lines()
+            .filter(|line| line.contains(&format!("[{}]", repo)))
+            .collect();
+        assert_eq!(lines.len(), 2, "unexpected output: {}", stdout);
+        assert!(lines[0].ends_with(" one"), "{}", lines[0]);
+        assert!(lines[1].ends_with(" two"), "{}", lines[1]);
+    }
+}
+
+#[test]
+fn foreach_reports_the_repos_where_the_command_failed() {
+    let (_tmp, _remote, super_repo) = super_repo_with(&["alpha", "beta"]);
+    std::fs::write(super_repo.join("beta").join("marker"), "").unwrap();
+
+    let stdout = super_stdout(
+        &super_repo,
+        &[
+            "foreach",
+            "sh",
+            "-c",
+            "test -f marker || { echo oops >&2; exit 3; }",
+        ],
+    );
+
+    assert!(
+        stdout.contains("alpha (exit code 3)"),
+        "unexpected output: {}",
+        stdout
+    );
+    assert!(stdout.contains("    oops"), "unexpected output: {}", stdout);
+    assert!(
+        !stdout.contains("beta (exit code"),
+        "unexpected output: {}",
+        stdout
+    );
+    assert!(
+        stdout.contains("Failed in 1: alpha"),
+        "unexpected output: {}",
+        stdout
+    );
+}
+
+#[test]
+fn foreach_only_runs_in_dirty_repos_with_filter_dirty() {
+    let (_tmp, _remote, super_repo) = super_repo_with(&["alpha", "beta"]);
+    std::fs::write(super_repo.join("beta").join("file.txt"), "changed").unwrap();
+
+    let stdout = super_stdout(
+        &super_repo,
+        &["foreach", "--filter", "dirty", "echo", "ran here"],
+    );
+
+    assert_eq!(
+        stdout.matches("ran here").count(),
+        1,
+        "unexpected output: {}",
+        stdout
+    );
+    assert!(stdout.contains("beta"), "unexpected output: {}", stdout);
+    assert!(!stdout.contains("alpha"), "unexpected output: {}", stdout);
+    assert!(
+        stdout.contains("Filtered out 1 repos that are not dirty"),
+        "unexpected output: {}",
+        stdout
+    );
+}
+
+#[test]
+fn foreach_accounts_for_every_repo_with_max_failures() {
+    let (_tmp, remote, super_repo) = super_repo_with(&[]);
+    for name in ["alpha", "beta", "gamma"] {
+        add_submodule(&super_repo, &remote, name);
+    }
+
+    let output = run_super(&super_repo, &["foreach", "--max-failures", "1", "false"]);
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    let summary = stderr
+        .lines()
+        .find(|line| line.starts_with("super: "))
+        .unwrap_or_else(|| panic!("no summary in: {}", stderr));
+
+    // How many repos are skipped depends on how many run at once, but each repo either ran and
+    // failed, or was skipped
+    let count = |key: &str| -> usize {
+        summary
+            .split(' ')
+            .find_map(|pair| pair.strip_prefix(key))
+            .unwrap()
+            .parse()
+            .unwrap()
+    };
+    assert_eq!(count("total="), 3, "{}", summary);
+    assert_eq!(count("succeeded="), 0, "{}", summary);
+    assert!(count("failed=") >= 1, "{}", summary);
+    assert_eq!(count("failed=") + count("skipped="), 3, "{}", summary);
+}
+
+#[test]
+fn foreach_interprets_pipes_with_shell() {
+    let (_tmp, _remote, super_repo) = super_repo_with(&["sub"]);
+
+    let stdout = super_stdout(
+        &super_repo,
+        &[
+            "foreach",
+            "--shell",
+            "printf 'a\\nb\\n' | wc -l > lines; cat lines",
+        ],
+    );
+
+    assert!(
+        stdout.lines().any(|line| line.trim() == "2"),
+        "unexpected output: {}",
+        stdout
+    );
+}
+
+#[test]
+fn foreach_runs_in_the_directories_from_on_dir() {
+    let tmp = tempfile::tempdir().unwrap();
+    let monorepo = tmp.path().join("monorepo");
+    std::fs::create_dir_all(monorepo.join("projects").join("api")).unwrap();
+    std::fs::create_dir_all(monorepo.join("projects").join("web")).unwrap();
+    std::fs::create_dir_all(monorepo.join("docs")).unwrap();
+
+    // The directories are relative to where super is started, and don't have to be git repos
+    let stdout = super_stdout(
+        &monorepo.join("projects"),
+        &["foreach", "--on-dir", "api", "--on-dir", "web", "pwd"],
+    );
+    let projects = monorepo.canonicalize().unwrap().join("projects");
+    for project in ["api", "web"] {
+        let expected = projects.join(project);
+        assert!(
+            stdout.contains(expected.to_str().unwrap()),
+            "unexpected output: {}",
+            stdout
+        );
+    }
+    assert!(!stdout.contains("docs"), "unexpected output: {}", stdout);
+
+    let stdout = super_stdout(&monorepo, &["foreach", "--on-dir", "missing", "pwd"]);
+    assert!(
+        stdout.contains("Not a directory: missing"),
+        "unexpected output: {}",
+        stdout
+    );
+}
+
+#[test]
+fn log_file_gets_a_record_for_each_repo() {
+    let (tmp, _remote, super_repo) = super_repo_with(&["alpha", "beta"]);
+    let log_file = tmp.path().join("super.log");
+    std::fs::remove_file(super_repo.join("beta").join("file.txt")).unwrap();
+
+    // Records are appended, so running twice gives two records per repo
+    for _ in 0..2 {
+        run_super(
+            &super_repo,
+            &[
+                "--log-file",
+                log_file.to_str().unwrap(),
+                "foreach",
+                "cat",
+                "file.txt",
+            ],
+        );
+    }
+
+    let log = std::fs::read_to_string(&log_file).unwrap();
+    assert_eq!(log.lines().count(), 4, "unexpected log: {}", log);
+    let alpha = log
+        .lines()
+        .find(|line| line.contains("repo=\"alpha\""))
+        .unwrap();
+    assert!(
+        alpha.contains("command=foreach status=succeeded exit_code=0"),
+        "unexpected log: {}",
+        log
+    );
+    let beta = log
+        .lines()
+        .find(|line| line.contains("repo=\"beta\""))
+        .unwrap();
+    assert!(
+        beta.contains("status=failed exit_code=1") && beta.contains("file.txt"),
+        "unexpected log: {}",
+        log
+    );
+}
+
+#[test]
+fn foreach_prints_the_command_and_only_prints_it_with_dry_run() {
+    let (_tmp, _remote, super_repo) = super_repo_with(&["alpha"]);
+    let marker = super_repo.join("alpha").join("new file");
+
+    let stdout = super_stdout(&super_repo, &["foreach", "--dry-run", "touch", "new file"]);
+    assert!(
+        stdout.contains("[alpha] $ touch 'new file'"),
+        "unexpected output: {}",
+        stdout
+    );
+    assert!(!marker.exists(), "the command ran with --dry-run");
+
+    let stdout = super_stdout(
+        &super_repo,
+        &["foreach", "--print-cmd", "touch", "new file"],
+    );
+    assert!(
+        stdout.contains("[alpha] $ touch 'new file'"),
+        "unexpected output: {}",
+        stdout
+    );
+    assert!(marker.exists(), "the command didn't run");
+}
+
+#[test]
+fn foreach_explains_why_there_is_nothing_to_run_in() {
+    let tmp = tempfile::tempdir().unwrap();
+    let remote = tmp.path().join("remote");
+    let origin = tmp.path().join("origin");
+    let clone = tmp.path().join("clone");
+    create_remote(&remote);
+    create_super_repo(&origin);
+
+    let stdout = super_stdout(&origin, &["foreach", "pwd"]);
+    assert!(
+        stdout.contains("No submodules found in this super repo, add one with 'super add <repo>'"),
+        "unexpected output: {}",
+        stdout
+    );
+
+    add_submodule(&origin, &remote, "alpha");
+    git(&origin, &["commit", "--quiet", "-m", "Add alpha"]);
+    git(
+        tmp.path(),
+        &["clone", "--quiet", origin.to_str().unwrap(), "clone"],
+    );
+
+    // The command must not run in the empty directory of alpha, which is inside the super repo
+    let stdout = super_stdout(&clone, &["foreach", "pwd"]);
+    assert!(
+        stdout.contains("None of the 1 submodules are initialized, run 'super pull --init'"),
+        "unexpected output: {}",
+        stdout
+    );
+    assert!(!stdout.contains("alpha"), "unexpected output: {}", stdout);
+}
+
+#[test]
+fn foreach_skips_the_submodules_that_are_not_initialized() {
+    let (_tmp, _remote, super_repo) = super_repo_with(&["alpha", "beta"]);
+    git(
+        &super_repo,
+        &["submodule", "--quiet", "deinit", "--force", "beta"],
+    );
+
+    // In the empty directory of beta, git would find the super repo instead
+    let output = run_super(
+        &super_repo,
+        &["foreach", "--git", "rev-parse", "--show-toplevel"],
+    );
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(output.status.success(), "unexpected output: {}", stdout);
+    assert!(
+        line_for(&stdout, "beta").contains("not initialized, run 'super pull --init'"),
+        "unexpected output: {}",
+        stdout
+    );
+    assert!(
+        stderr.contains("total=2 succeeded=1 failed=0 skipped=1"),
+        "unexpected stderr: {}",
+        stderr
+    );
+}
+
+#[test]
+fn max_output_lines_truncates_the_output_but_not_the_log() {
+    let (tmp, _remote, super_repo) = super_repo_with(&["sub"]);
+    let log_file = tmp.path().join("super.log");
+
+    let stdout = super_stdout(
+        &super_repo,
+        &[
+            "--log-file",
+            log_file.to_str().unwrap(),
+            "foreach",
+            "--max-output-lines",
+            "2",
+            "printf",
+            "one\\ntwo\\nthree\\nfour\\n",
+        ],
+    );
+    assert!(
+        stdout.contains("one\ntwo\n… (truncated, 2 more lines)"),
+        "unexpected output: {}",
+        stdout
+    );
+    assert!(!stdout.contains("three"), "unexpected output: {}", stdout);
+
+    let log = std::fs::read_to_string(&log_file).unwrap();
+    assert!(
+        log.contains(r#"output="one\ntwo\nthree\nfour\n""#),
+        "unexpected log: {}",
+        log
+    );
+}
+
+#[test]
+fn expect_codes_counts_the_given_exit_codes_as_success() {
+    let (_tmp, _remote, super_repo) = super_repo_with(&["alpha"]);
+
+    // grep exits with 1 if nothing matched, and with 2 if the file doesn't exist
+    let output = run_super(
+        &super_repo,
+        &[
+            "foreach",
+            "--expect-codes",
+            "0,1",
+            "grep",
+            "-q",
+            "TODO",
+            "file.txt",
+        ],
+    );
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(
+        stderr.contains("succeeded=1 failed=0"),
+        "unexpected stderr: {}",
+        stderr
+    );
+    assert!(output.status.success(), "unexpected stderr: {}", stderr);
+
+    let output = run_super(
+        &super_repo,
+        &[
+            "foreach",
+            "--expect-codes",
+            "0,1",
+            "grep",
+            "-q",
+            "TODO",
+            "missing.txt",
+        ],
+    );
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(
+        stderr.contains("succeeded=0 failed=1"),
+        "unexpected stderr: {}",
+        stderr
+    );
+    assert_eq!(
+        output.status.code(),
+        Some(1),
+        "unexpected stderr: {}",
+        stderr
+    );
+}
+
+#[test]
+fn timeout_kills_the_command_only_in_the_repos_where_it_hangs() {
+    let (_tmp, _remote, super_repo) = super_repo_with(&["fast", "slow"]);
+    std::fs::write(super_repo.join("fast").join("delay"), "0").unwrap();
+    std::fs::write(super_repo.join("slow").join("delay"), "30").unwrap();
+
+    // cat would wait for input forever if stdin was the terminal. sh forks sleep, which has to be
+    // killed along with it.
+    let started = std::time::Instant::now();
+    let output = run_super(
+        &super_repo,
+        &[
+            "foreach",
+            "--timeout",
+            "2",
+            "--shell",
+            "cat && sleep $(cat delay)",
+        ],
+    );
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let stderr = String::from_utf8_lossy(&output.stderr);
+
+    assert!(
+        started.elapsed().as_secs() < 20,
+        "the timeout didn't kill the command"
+    );
+    assert!(
+        stdout.contains("slow (timeout)"),
+        "unexpected output: {}",
+        stdout
+    );
+    assert!(
+        stderr.contains("succeeded=1 failed=1"),
+        "unexpected stderr: {}",
+        stderr
+    );
+}
@@ -0,0 +1,43 @@
+//! Tests for 'super freeze' and 'super thaw'
+
+mod common;
+
+use common::{commit_file, head_sha, line_for, super_repo_with, super_stdout};
+
+#[test]
+fn thaw_restores_the_frozen_commits() {
+    let (_tmp, _remote, super_repo) = super_repo_with(&["alpha"]);
+
+    let repo_dir = super_repo.join("alpha");
+    let frozen = head_sha(&repo_dir);
+    super_stdout(&super_repo, &["freeze"]);
+
+    let lock = std::fs::read_to_string(super_repo.join("super.lock")).unwrap();
+    assert!(lock.contains(&frozen), "unexpected super.lock: {}", lock);
+
+    commit_file(&repo_dir, "second");
+    assert_ne!(head_sha(&repo_dir), frozen);
+
+    let stdout = super_stdout(&super_repo, &["thaw"]);
+
+    assert!(line_for(&stdout, "alpha").contains("thawed"));
+    assert_eq!(head_sha(&repo_dir), frozen);
+}
+
+#[test]
+fn thaw_reports_commits_that_are_missing_locally() {
+    let (_tmp, _remote, super_repo) = super_repo_with(&["alpha"]);
+
+    let missing = "0123456789abcdef0123456789abcdef01234567";
+    let lock = format!(
+        "[[submodule]]\nname = \"alpha\"\npath = \"alpha\"\nsha = \"{}\"\n",
+        missing
+    );
+    std::fs::write(super_repo.join("super.lock"), lock).unwrap();
+
+    let stdout = super_stdout(&super_repo, &["thaw"]);
+
+    let line = line_for(&stdout, "alpha");
+    assert!(line.contains("missing"), "{}", line);
+    assert!(line.contains(&missing[..7]), "{}", line);
+}
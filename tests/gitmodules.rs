@@ -0,0 +1,79 @@
+//! Tests for the 'update' and 'ignore' settings in .gitmodules
+
+mod common;
+
+use common::{add_submodule, commit_file, git, line_for, super_repo_with, super_stdout};
+
+#[test]
+fn pull_skips_submodules_with_update_none() {
+    let (_tmp, remote, super_repo) = super_repo_with(&["manual-repo", "auto-repo"]);
+
+    git(
+        &super_repo,
+        &[
+            "config",
+            "-f",
+            ".gitmodules",
+            "submodule.manual-repo.update",
+            "none",
+        ],
+    );
+    commit_file(&remote, "second");
+
+    let stdout = super_stdout(&super_repo, &["pull"]);
+
+    let manual_line = line_for(&stdout, "manual-repo");
+    assert!(manual_line.contains("skipped"), "{}", manual_line);
+    assert!(manual_line.contains("update=none"), "{}", manual_line);
+    assert!(line_for(&stdout, "auto-repo").contains("updated"));
+
+    let manual_head = git(
+        &super_repo.join("manual-repo"),
+        &["log", "-1", "--format=%s"],
+    );
+    assert_eq!(manual_head.trim(), "first");
+}
+
+#[test]
+fn pull_does_not_stash_submodules_that_ignore_dirty_changes() {
+    let (_tmp, remote, super_repo) = super_repo_with(&[]);
+    for name in ["dirty-repo", "all-repo", "default-repo"] {
+        add_submodule(&super_repo, &remote, name);
+    }
+
+    for (name, ignore) in [("dirty-repo", "dirty"), ("all-repo", "all")] {
+        git(
+            &super_repo,
+            &[
+                "config",
+                "-f",
+                ".gitmodules",
+                &format!("submodule.{}.ignore", name),
+                ignore,
+            ],
+        );
+    }
+    commit_file(&remote, "second");
+
+    // A local change that conflicts with the new commit in the remote
+    for name in ["dirty-repo", "all-repo", "default-repo"] {
+        std::fs::write(super_repo.join(name).join("file.txt"), "local").unwrap();
+    }
+
+    let stdout = super_stdout(&super_repo, &["pull", "--autostash"]);
+
+    for name in ["dirty-repo", "all-repo"] {
+        let repo_dir = super_repo.join(name);
+        assert_eq!(
+            git(&repo_dir, &["stash", "list"]),
+            "",
+            "{} was stashed",
+            name
+        );
+        assert_eq!(
+            std::fs::read_to_string(repo_dir.join("file.txt")).unwrap(),
+            "local"
+        );
+    }
+    assert!(line_for(&stdout, "default-repo").contains("stash conflict"));
+}
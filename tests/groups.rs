@@ -0,0 +1,83 @@
+//! Tests for submodule groups, --group and --exclude
+
+mod common;
+
+use common::{git, line_for, super_command, super_repo_with, super_stdout};
+
+#[test]
+fn list_shows_the_group_of_each_repo() {
+    let (_tmp, _remote, super_repo) = super_repo_with(&["api", "web"]);
+    git(
+        &super_repo,
+        &[
+            "config",
+            "-f",
+            ".gitmodules",
+            "submodule.api.group",
+            "backend",
+        ],
+    );
+
+    let stdout = super_stdout(&super_repo, &["list"]);
+    assert!(
+        line_for(&stdout, "api").contains("backend"),
+        "unexpected output: {}",
+        stdout
+    );
+    assert!(
+        line_for(&stdout, "web").contains("default"),
+        "unexpected output: {}",
+        stdout
+    );
+}
+
+#[test]
+fn group_restricts_status_and_foreach_to_its_repos() {
+    let (_tmp, _remote, super_repo) = super_repo_with(&["api", "web"]);
+    super_stdout(&super_repo, &["config", "api", "group", "backend"]);
+
+    let stdout = super_stdout(&super_repo, &["status", "--group", "backend"]);
+    assert!(stdout.contains("api"), "unexpected output: {}", stdout);
+    assert!(!stdout.contains("web"), "unexpected output: {}", stdout);
+
+    // Repos without a group are in the default group
+    let stdout = super_stdout(&super_repo, &["foreach", "--group", "default", "pwd"]);
+    assert!(stdout.contains("web"), "unexpected output: {}", stdout);
+    assert!(!stdout.contains("api"), "unexpected output: {}", stdout);
+
+    let stdout = super_stdout(&super_repo, &["status", "--group", "frontend"]);
+    assert!(
+        stdout.contains("No repos are in the group frontend"),
+        "unexpected output: {}",
+        stdout
+    );
+}
+
+#[test]
+fn exclude_leaves_repos_out_of_the_selection() {
+    let (_tmp, _remote, super_repo) = super_repo_with(&["api", "web", "docs"]);
+    super_stdout(&super_repo, &["config", "api", "group", "backend"]);
+    super_stdout(&super_repo, &["config", "web", "group", "backend"]);
+
+    // The exclusion is applied after --group
+    let stdout = super_stdout(
+        &super_repo,
+        &["status", "--group", "backend", "--exclude", "web"],
+    );
+    assert!(stdout.contains("api"), "unexpected output: {}", stdout);
+    assert!(!stdout.contains("web"), "unexpected output: {}", stdout);
+    assert!(!stdout.contains("docs"), "unexpected output: {}", stdout);
+
+    // Unknown names only cause a warning
+    let output = super_command(&super_repo)
+        .args(["foreach", "--exclude", "api,nope", "pwd"])
+        .output()
+        .unwrap();
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(output.status.success(), "unexpected output: {}", stdout);
+    assert!(stderr.contains("nope"), "unexpected stderr: {}", stderr);
+    assert!(stdout.contains("web"), "unexpected output: {}", stdout);
+    assert!(stdout.contains("docs"), "unexpected output: {}", stdout);
+    assert!(!stdout.contains("api"), "unexpected output: {}", stdout);
+}
@@ -0,0 +1,63 @@
+//! Tests for 'super --help' and 'super <command> --help'
+
+mod common;
+
+use common::super_stdout;
+
+#[test]
+fn help_of_a_command_only_shows_that_command() {
+    let tmp = tempfile::tempdir().unwrap();
+
+    let stdout = super_stdout(tmp.path(), &["pull", "--help"]);
+    assert!(
+        stdout.trim_start().starts_with("super pull"),
+        "unexpected output: {}",
+        stdout
+    );
+    assert!(
+        stdout.contains("--autostash"),
+        "unexpected output: {}",
+        stdout
+    );
+    assert!(
+        !stdout.contains("super foreach"),
+        "unexpected output: {}",
+        stdout
+    );
+
+    // The command of 'super [status]' is optional
+    let stdout = super_stdout(tmp.path(), &["status", "-h"]);
+    assert!(
+        stdout.contains("--porcelain"),
+        "unexpected output: {}",
+        stdout
+    );
+}
+
+#[test]
+fn every_command_in_the_overview_has_its_own_help() {
+    let tmp = tempfile::tempdir().unwrap();
+
+    let overview = super_stdout(tmp.path(), &["--help"]);
+    let commands: Vec<&str> = overview
+        .lines()
+        .skip_while(|line| *line != "Commands:")
+        .skip(1)
+        .take_while(|line| !line.is_empty())
+        .map(|line| line.split_whitespace().next().unwrap())
+        .collect();
+    assert!(
+        commands.contains(&"foreach"),
+        "unexpected output: {}",
+        overview
+    );
+
+    for command in commands {
+        let stdout = super_stdout(tmp.path(), &["help", command]);
+        assert!(
+            !stdout.starts_with("Unknown command"),
+            "no help for {}",
+            command
+        );
+    }
+}
@@ -0,0 +1,69 @@
+//! Tests for the hooks in ~/.config/super/hooks
+
+mod common;
+
+use common::{
+    add_submodule, commit_file, create_remote, create_super_repo, head_sha, super_command,
+};
+use std::os::unix::fs::PermissionsExt;
+use std::path::Path;
+
+/// Install an executable hook with the given shell script in the hooks directory of the given home
+fn install_hook(home: &Path, name: &str, script: &str) {
+    let hooks_dir = home.join(".config/super/hooks");
+    std::fs::create_dir_all(&hooks_dir).unwrap();
+
+    let hook = hooks_dir.join(name);
+    std::fs::write(&hook, format!("#!/bin/sh\n{}\n", script)).unwrap();
+    std::fs::set_permissions(&hook, std::fs::Permissions::from_mode(0o755)).unwrap();
+}
+
+#[test]
+fn failing_pre_pull_hook_aborts_the_pull() {
+    let tmp = tempfile::tempdir().unwrap();
+    let home = tmp.path().join("home");
+    let remote = tmp.path().join("remote");
+    let super_repo = tmp.path().join("super");
+    create_remote(&remote);
+    create_super_repo(&super_repo);
+    add_submodule(&super_repo, &remote, "alpha");
+    install_hook(&home, "pre-pull", "exit 1");
+
+    let before = head_sha(&super_repo.join("alpha"));
+    commit_file(&remote, "second");
+
+    super_command(&super_repo)
+        .env("HOME", &home)
+        .arg("pull")
+        .output()
+        .unwrap();
+
+    assert_eq!(head_sha(&super_repo.join("alpha")), before);
+}
+
+#[test]
+fn post_pull_hook_gets_the_updated_repos() {
+    let tmp = tempfile::tempdir().unwrap();
+    let home = tmp.path().join("home");
+    let updated = tmp.path().join("updated");
+    let remote = tmp.path().join("remote");
+    let other = tmp.path().join("other");
+    let super_repo = tmp.path().join("super");
+    create_remote(&remote);
+    create_remote(&other);
+    create_super_repo(&super_repo);
+    add_submodule(&super_repo, &remote, "alpha");
+    add_submodule(&super_repo, &other, "beta");
+
+    let script = format!("echo \"$@\" > '{}'", updated.display());
+    install_hook(&home, "post-pull", &script);
+    commit_file(&remote, "second");
+
+    super_command(&super_repo)
+        .env("HOME", &home)
+        .arg("pull")
+        .output()
+        .unwrap();
+
+    assert_eq!(std::fs::read_to_string(&updated).unwrap().trim(), "alpha");
+}
@@ -0,0 +1,50 @@
+//! Tests for interrupting super with Ctrl-C
+
+mod common;
+
+use common::{super_command, super_repo_with};
+use std::process::{Command, Stdio};
+use std::thread;
+use std::time::Duration;
+
+#[test]
+fn interrupt_kills_running_commands() {
+    let (_tmp, _remote, super_repo) = super_repo_with(&["sub"]);
+    let pid_file = super_repo.join("sub").join("pid");
+
+    // 'exec' replaces the shell, so that the pid that is written is the one of sleep
+    let mut child = super_command(&super_repo)
+        .args(["foreach", "sh", "-c", "echo $$ > pid; exec sleep 30"])
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .spawn()
+        .unwrap();
+
+    let mut waited = 0;
+    while std::fs::read_to_string(&pid_file).map_or(true, |pid| !pid.ends_with('\n')) {
+        assert!(waited < 100, "the command didn't start");
+        thread::sleep(Duration::from_millis(100));
+        waited += 1;
+    }
+    let pid = std::fs::read_to_string(&pid_file)
+        .unwrap()
+        .trim()
+        .to_string();
+
+    // Only super gets the signal, unlike with Ctrl-C in a terminal, which signals the whole group
+    Command::new("kill")
+        .args(["-INT", &child.id().to_string()])
+        .status()
+        .unwrap();
+    let status = child.wait().unwrap();
+    assert_eq!(status.code(), Some(130));
+
+    // The sleep was killed as well. Since super has exited, init reaps it right away.
+    thread::sleep(Duration::from_millis(200));
+    let alive = Command::new("kill")
+        .args(["-0", &pid])
+        .stderr(Stdio::null())
+        .status()
+        .unwrap();
+    assert!(!alive.success(), "the command is still running");
+}
@@ -0,0 +1,37 @@
+//! Tests for 'super list'
+
+mod common;
+
+use common::{create_remote, git, super_repo_with, super_stdout};
+
+#[test]
+fn list_paths_prints_only_the_paths() {
+    let (_tmp, _remote, super_repo) = super_repo_with(&["libs/alpha", "beta"]);
+
+    // Even with colors, the paths are printed as they are
+    let stdout = super_stdout(&super_repo, &["--color", "always", "list", "--paths"]);
+    let mut paths: Vec<&str> = stdout.lines().collect();
+    paths.sort();
+    assert_eq!(
+        paths,
+        ["beta", "libs/alpha"],
+        "unexpected output: {:?}",
+        stdout
+    );
+}
+
+#[test]
+fn list_paths_works_without_a_super_repo() {
+    let tmp = tempfile::tempdir().unwrap();
+    let remote = tmp.path().join("remote");
+    let workspace = tmp.path().join("workspace");
+    create_remote(&remote);
+    std::fs::create_dir(&workspace).unwrap();
+    git(
+        &workspace,
+        &["clone", "--quiet", remote.to_str().unwrap(), "alpha"],
+    );
+
+    let stdout = super_stdout(&workspace, &["list", "--paths"]);
+    assert_eq!(stdout, "alpha\n");
+}
@@ -0,0 +1,51 @@
+//! Tests for 'super log'
+
+mod common;
+
+use common::{super_repo_with, super_stdout};
+use std::path::Path;
+use std::process::Command;
+
+/// Make an empty commit with the given message and date in the given repo
+fn commit_at(dir: &Path, message: &str, date: &str) {
+    let status = Command::new("git")
+        .args([
+            "-c",
+            "user.name=super",
+            "-c",
+            "user.email=super@example.com",
+        ])
+        .args(["commit", "--quiet", "--allow-empty", "-m", message])
+        .env("GIT_AUTHOR_DATE", date)
+        .env("GIT_COMMITTER_DATE", date)
+        .current_dir(dir)
+        .status()
+        .expect("failed to execute git");
+    assert!(status.success());
+}
+
+#[test]
+fn log_merges_the_commits_of_all_repos_newest_first() {
+    let (_tmp, _remote, super_repo) = super_repo_with(&["alpha", "beta"]);
+    commit_at(&super_repo.join("alpha"), "older", "2030-01-01T12:00:00Z");
+    commit_at(&super_repo.join("beta"), "newer", "2031-01-01T12:00:00Z");
+
+    let stdout = super_stdout(&super_repo, &["log", "-n", "2"]);
+    let lines: Vec<&str> = stdout.lines().collect();
+    assert_eq!(lines.len(), 2, "unexpected output: {}", stdout);
+    assert!(
+        lines[0].contains("beta") && lines[0].contains("2031-01-01 newer (super)"),
+        "unexpected output: {}",
+        stdout
+    );
+    assert!(
+        lines[1].contains("alpha") && lines[1].contains("2030-01-01 older (super)"),
+        "unexpected output: {}",
+        stdout
+    );
+
+    // --since is passed on to 'git log'
+    let stdout = super_stdout(&super_repo, &["log", "--since", "2030-06-01"]);
+    assert_eq!(stdout.lines().count(), 1, "unexpected output: {}", stdout);
+    assert!(stdout.contains("newer"), "unexpected output: {}", stdout);
+}
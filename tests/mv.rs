@@ -0,0 +1,49 @@
+//! Tests for 'super mv'
+
+mod common;
+
+use common::{git, super_repo_with, super_stdout};
+
+#[test]
+fn mv_moves_a_submodule_and_updates_gitmodules() {
+    let (_tmp, _remote, super_repo) = super_repo_with(&["sub"]);
+
+    let stdout = super_stdout(&super_repo, &["mv", "sub", "libs/sub"]);
+    assert!(
+        stdout.contains("Moved sub"),
+        "unexpected output: {}",
+        stdout
+    );
+
+    assert!(!super_repo.join("sub").exists());
+    assert!(super_repo
+        .join("libs")
+        .join("sub")
+        .join("file.txt")
+        .exists());
+
+    let path = git(
+        &super_repo,
+        &["config", "-f", ".gitmodules", "submodule.sub.path"],
+    );
+    assert_eq!(path.trim(), "libs/sub");
+
+    // The submodule still works in its new place
+    let status = git(&super_repo.join("libs").join("sub"), &["status", "--short"]);
+    assert!(status.trim().is_empty(), "{}", status);
+}
+
+#[test]
+fn mv_refuses_to_overwrite_an_existing_path() {
+    let (_tmp, _remote, super_repo) = super_repo_with(&["sub"]);
+    std::fs::create_dir(super_repo.join("taken")).unwrap();
+
+    let stdout = super_stdout(&super_repo, &["mv", "sub", "taken"]);
+
+    assert!(
+        stdout.contains("already exists"),
+        "unexpected output: {}",
+        stdout
+    );
+    assert!(super_repo.join("sub").join("file.txt").exists());
+}
@@ -0,0 +1,54 @@
+//! Tests for 'super open'
+
+mod common;
+
+use common::{add_submodule, run_super, super_repo_with, super_stdout};
+
+#[test]
+fn open_resolves_unambiguous_prefixes_and_lists_ambiguous_ones() {
+    let (_tmp, remote, super_repo) = super_repo_with(&[]);
+    for name in ["frontend", "backend", "backup"] {
+        add_submodule(&super_repo, &remote, name);
+    }
+
+    let stdout = super_stdout(&super_repo, &["open", "fro"]);
+    let expected = super_repo.canonicalize().unwrap().join("frontend");
+    assert_eq!(stdout.trim(), expected.to_str().unwrap());
+
+    let output = run_super(&super_repo, &["open", "back"]);
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(output.stdout.is_empty());
+    assert!(stderr.contains("backend"), "unexpected stderr: {}", stderr);
+    assert!(stderr.contains("backup"), "unexpected stderr: {}", stderr);
+}
+
+#[test]
+fn unknown_repo_names_get_a_suggestion() {
+    let (_tmp, remote, super_repo) = super_repo_with(&[]);
+    for name in ["frontend", "backend"] {
+        add_submodule(&super_repo, &remote, name);
+    }
+
+    let output = run_super(&super_repo, &["open", "frotnend"]);
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(
+        stderr.contains("did you mean `frontend`?"),
+        "unexpected stderr: {}",
+        stderr
+    );
+
+    let stdout = super_stdout(&super_repo, &["status", "--repos", "backedn"]);
+    assert!(
+        stdout.contains("Unknown repo: backedn, did you mean `backend`?"),
+        "unexpected output: {}",
+        stdout
+    );
+
+    // Names that are nowhere close don't get a suggestion
+    let stdout = super_stdout(&super_repo, &["config", "database", "branch"]);
+    assert!(
+        stdout.contains("Unknown repo: database") && !stdout.contains("did you mean"),
+        "unexpected output: {}",
+        stdout
+    );
+}
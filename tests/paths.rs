@@ -0,0 +1,131 @@
+//! Tests for repos whose paths contain spaces
+
+mod common;
+
+use common::{create_remote, git, line_for, super_repo_with, super_stdout};
+
+#[test]
+fn discovers_repos_with_spaces_in_their_path() {
+    let tmp = tempfile::tempdir().unwrap();
+    let remote = tmp.path().join("remote");
+    let workspace = tmp.path().join("workspace");
+    create_remote(&remote);
+    std::fs::create_dir(&workspace).unwrap();
+
+    git(
+        &workspace,
+        &["clone", "--quiet", remote.to_str().unwrap(), "sub one"],
+    );
+
+    // The workspace is not a git repo itself, so super has to discover the repos in it
+    let stdout = super_stdout(&workspace, &["pull"]);
+
+    assert!(stdout.contains("sub one"), "unexpected output: {}", stdout);
+    assert!(
+        stdout.contains("up to date"),
+        "unexpected output: {}",
+        stdout
+    );
+}
+
+#[test]
+fn foreach_runs_in_submodules_with_spaces_in_their_path() {
+    let (_tmp, _remote, super_repo) = super_repo_with(&["my repos/sub one"]);
+
+    let stdout = super_stdout(&super_repo, &["foreach", "pwd"]);
+    let expected = super_repo
+        .canonicalize()
+        .unwrap()
+        .join("my repos")
+        .join("sub one");
+
+    assert!(
+        stdout.contains(expected.to_str().unwrap()),
+        "unexpected output: {}",
+        stdout
+    );
+}
+
+// macOS doesn't allow file names that are not valid UTF-8, so these tests only run on Linux
+
+#[cfg(target_os = "linux")]
+#[test]
+fn discovers_repos_whose_path_is_not_utf8() {
+    use std::os::unix::ffi::OsStrExt;
+
+    let tmp = tempfile::tempdir().unwrap();
+    let remote = tmp.path().join("remote");
+    let workspace = tmp.path().join("workspace");
+    create_remote(&remote);
+    std::fs::create_dir(&workspace).unwrap();
+    let repo = workspace.join(std::ffi::OsStr::from_bytes(b"caf\xe9"));
+    git(
+        &workspace,
+        &["clone", "--quiet", remote.to_str().unwrap(), "clone"],
+    );
+    std::fs::rename(workspace.join("clone"), &repo).unwrap();
+    std::fs::write(repo.join("file.txt"), "changed").unwrap();
+
+    // The repo is found by its real path, and only its name is shown with a replacement character
+    let stdout = super_stdout(&workspace, &["status"]);
+    assert!(
+        stdout.contains("caf\u{fffd}") && stdout.contains("dirty"),
+        "unexpected output: {}",
+        stdout
+    );
+}
+
+#[cfg(target_os = "linux")]
+#[test]
+fn foreach_runs_in_submodules_whose_path_is_not_utf8() {
+    use std::os::unix::ffi::OsStrExt;
+
+    let (_tmp, remote, super_repo) = super_repo_with(&[]);
+    let path = std::ffi::OsStr::from_bytes(b"caf\xe9");
+    let status = std::process::Command::new("git")
+        .args(["-c", "protocol.file.allow=always", "submodule", "--quiet"])
+        .args(["add", remote.to_str().unwrap()])
+        .arg(path)
+        .current_dir(&super_repo)
+        .status()
+        .unwrap();
+    assert!(status.success());
+
+    let stdout = super_stdout(&super_repo, &["foreach", "touch", "marker"]);
+    assert!(
+        super_repo.join(path).join("marker").exists(),
+        "the command didn't run in the submodule: {}",
+        stdout
+    );
+    assert!(
+        stdout.contains("caf\u{fffd}"),
+        "unexpected output: {}",
+        stdout
+    );
+}
+
+#[cfg(target_os = "linux")]
+#[test]
+fn pull_shows_submodules_whose_name_is_not_utf8() {
+    use std::os::unix::ffi::OsStrExt;
+
+    let (_tmp, remote, super_repo) = super_repo_with(&[]);
+    let path = std::ffi::OsStr::from_bytes(b"caf\xe9");
+    let status = std::process::Command::new("git")
+        .args(["-c", "protocol.file.allow=always", "submodule", "--quiet"])
+        .args(["add", remote.to_str().unwrap()])
+        .arg(path)
+        .current_dir(&super_repo)
+        .status()
+        .unwrap();
+    assert!(status.success());
+    git(&super_repo.join(path), &["checkout", "--quiet", "master"]);
+
+    // The name of the submodule is its path, so it isn't valid UTF-8 either
+    let stdout = super_stdout(&super_repo, &["pull"]);
+    assert!(
+        line_for(&stdout, "caf\u{fffd}").contains("up to date"),
+        "unexpected output: {}",
+        stdout
+    );
+}
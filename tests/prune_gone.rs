@@ -0,0 +1,36 @@
+//! Tests for 'super prune-gone'
+
+mod common;
+
+use common::{commit_file, git, line_for, super_repo_with, super_stdout};
+
+#[test]
+fn prune_gone_only_deletes_unmerged_branches_with_force() {
+    let (_tmp, remote, super_repo) = super_repo_with(&["alpha"]);
+    git(&remote, &["branch", "merged"]);
+    git(&remote, &["checkout", "--quiet", "-b", "unmerged"]);
+    commit_file(&remote, "unmerged");
+    git(&remote, &["checkout", "--quiet", "master"]);
+    let alpha = super_repo.join("alpha");
+    git(&alpha, &["fetch", "--quiet"]);
+    git(&alpha, &["branch", "--quiet", "merged", "origin/merged"]);
+    git(
+        &alpha,
+        &["branch", "--quiet", "unmerged", "origin/unmerged"],
+    );
+
+    // The branches are deleted on the remote, e.g. after their pull requests were merged
+    git(&remote, &["branch", "--quiet", "-D", "merged", "unmerged"]);
+    git(&alpha, &["fetch", "--quiet", "--prune"]);
+
+    let stdout = super_stdout(&super_repo, &["prune-gone"]);
+    let line = line_for(&stdout, "alpha");
+    assert!(line.contains("deleted 1: merged"), "{}", line);
+    assert!(line.contains("kept 1 unmerged: unmerged"), "{}", line);
+    assert!(git(&alpha, &["branch", "--list", "merged"]).is_empty());
+
+    let stdout = super_stdout(&super_repo, &["prune-gone", "--force"]);
+    let line = line_for(&stdout, "alpha");
+    assert!(line.contains("deleted 1: unmerged"), "{}", line);
+    assert!(git(&alpha, &["branch", "--list", "unmerged"]).is_empty());
+}
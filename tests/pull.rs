@@ -0,0 +1,607 @@
+//! End to end tests for 'super pull'
+
+mod common;
+
+use common::{
+    add_submodule, commit_file, create_remote, create_super_repo, git, head_sha, line_for,
+    run_super, super_command, super_repo_with, super_stdout,
+};
+use std::os::unix::fs::PermissionsExt;
+
+#[test]
+fn pull_fast_forwards_repos_whose_remote_advanced() {
+    let tmp = tempfile::tempdir().unwrap();
+    let alpha = tmp.path().join("alpha");
+    let beta = tmp.path().join("beta");
+    let super_repo = tmp.path().join("super");
+    create_remote(&alpha);
+    create_remote(&beta);
+    create_super_repo(&super_repo);
+    add_submodule(&super_repo, &alpha, "alpha");
+    add_submodule(&super_repo, &beta, "beta");
+    git(&super_repo, &["commit", "--quiet", "-m", "Add submodules"]);
+
+    let before = head_sha(&super_repo.join("alpha"));
+    commit_file(&alpha, "second");
+    let after = head_sha(&alpha);
+
+    let stdout = super_stdout(&super_repo, &["pull"]);
+
+    let alpha_line = line_for(&stdout, "alpha");
+    assert!(alpha_line.contains("updated"), "{}", alpha_line);
+    let hashes = format!("master({}) -> master({})", &before[..7], &after[..7]);
+    assert!(alpha_line.contains(&hashes), "{}", alpha_line);
+    assert!(line_for(&stdout, "beta").contains("up to date"));
+
+    assert_eq!(head_sha(&super_repo.join("alpha")), after);
+}
+
+#[test]
+fn pull_fast_forwards_repos_whose_remote_was_already_fetched() {
+    let (_tmp, remote, super_repo) = super_repo_with(&["alpha"]);
+    commit_file(&remote, "second");
+
+    // The fetch of 'super pull' doesn't download anything new then, but the branch is still behind
+    git(&super_repo.join("alpha"), &["fetch", "--quiet", "origin"]);
+    let stdout = super_stdout(&super_repo, &["pull"]);
+
+    assert!(
+        line_for(&stdout, "alpha").contains("updated"),
+        "unexpected output: {}",
+        stdout
+    );
+    assert_eq!(head_sha(&super_repo.join("alpha")), head_sha(&remote));
+}
+
+#[test]
+fn pull_reports_repos_that_diverged_from_their_remote() {
+    let (_tmp, remote, super_repo) = super_repo_with(&["forked"]);
+
+    let repo_dir = super_repo.join("forked");
+    commit_file(&remote, "upstream");
+    commit_file(&repo_dir, "local");
+    let before = head_sha(&repo_dir);
+
+    let stdout = super_stdout(&super_repo, &["pull"]);
+
+    let line = line_for(&stdout, "forked");
+    assert!(line.contains("diverged"), "{}", line);
+    assert_eq!(head_sha(&repo_dir), before);
+}
+
+#[test]
+fn pull_works_from_inside_a_submodule() {
+    let tmp = tempfile::tempdir().unwrap();
+    let alpha = tmp.path().join("alpha");
+    let beta = tmp.path().join("beta");
+    let super_repo = tmp.path().join("super");
+    create_remote(&alpha);
+    create_remote(&beta);
+    create_super_repo(&super_repo);
+    add_submodule(&super_repo, &alpha, "alpha");
+    add_submodule(&super_repo, &beta, "beta");
+
+    commit_file(&beta, "second");
+    let after = head_sha(&beta);
+
+    // Both repos are pulled, even though super runs inside of alpha
+    let stdout = super_stdout(&super_repo.join("alpha"), &["pull"]);
+
+    assert!(line_for(&stdout, "alpha").contains("up to date"));
+    assert!(line_for(&stdout, "beta").contains("updated"));
+    assert_eq!(head_sha(&super_repo.join("beta")), after);
+}
+
+#[test]
+fn pull_only_clones_uninitialized_submodules_with_init() {
+    let tmp = tempfile::tempdir().unwrap();
+    let remote = tmp.path().join("remote");
+    let origin = tmp.path().join("origin");
+    let clone = tmp.path().join("clone");
+    create_remote(&remote);
+    create_super_repo(&origin);
+    add_submodule(&origin, &remote, "alpha");
+    git(&origin, &["commit", "--quiet", "-m", "Add alpha"]);
+
+    // Without --recurse-submodules, the directory of alpha stays empty
+    git(
+        tmp.path(),
+        &["clone", "--quiet", origin.to_str().unwrap(), "clone"],
+    );
+
+    let stdout = super_stdout(&clone, &["pull"]);
+    assert!(line_for(&stdout, "alpha").contains("not initialized"));
+    assert!(!clone.join("alpha").join(".git").exists());
+
+    let stdout = super_stdout(&clone, &["pull", "--init"]);
+    assert!(line_for(&stdout, "alpha").contains("cloned at"));
+    assert_eq!(head_sha(&clone.join("alpha")), head_sha(&remote));
+}
+
+#[test]
+fn pull_does_not_move_repos_that_are_pinned_to_a_tag() {
+    let tmp = tempfile::tempdir().unwrap();
+    let remote = tmp.path().join("remote");
+    let super_repo = tmp.path().join("super");
+    create_remote(&remote);
+    git(&remote, &["tag", "v1.0"]);
+    create_super_repo(&super_repo);
+    add_submodule(&super_repo, &remote, "release");
+
+    let repo_dir = super_repo.join("release");
+    git(&repo_dir, &["checkout", "--quiet", "v1.0"]);
+    let before = head_sha(&repo_dir);
+    commit_file(&remote, "second");
+
+    let stdout = super_stdout(&super_repo, &["pull", "--tags"]);
+
+    let line = line_for(&stdout, "release");
+    assert!(line.contains("pinned"), "{}", line);
+    assert!(line.contains("tag v1.0"), "{}", line);
+    assert_eq!(head_sha(&repo_dir), before);
+}
+
+#[test]
+fn pull_reports_submodules_whose_directory_was_deleted() {
+    let (_tmp, _remote, super_repo) = super_repo_with(&["deleted", "kept"]);
+
+    std::fs::remove_dir_all(super_repo.join("deleted")).unwrap();
+
+    let stdout = super_stdout(&super_repo, &["pull"]);
+
+    let line = line_for(&stdout, "deleted");
+    assert!(line.contains("missing"), "{}", line);
+    assert!(line.contains("super pull --init"), "{}", line);
+    assert!(line_for(&stdout, "kept").contains("up to date"));
+}
+
+#[test]
+fn pull_unshallows_shallow_repos_and_leaves_complete_ones_alone() {
+    let tmp = tempfile::tempdir().unwrap();
+    let remote = tmp.path().join("remote");
+    let super_repo = tmp.path().join("super");
+    create_remote(&remote);
+    commit_file(&remote, "second");
+    create_super_repo(&super_repo);
+
+    // git ignores --depth for plain local paths, so we need a file:// URL here
+    let url = format!("file://{}", remote.display());
+    git(
+        &super_repo,
+        &[
+            "submodule",
+            "add",
+            "--quiet",
+            "--depth",
+            "1",
+            &url,
+            "shallow",
+        ],
+    );
+    git(
+        &super_repo.join("shallow"),
+        &["checkout", "--quiet", "master"],
+    );
+
+    let stdout = super_stdout(&super_repo, &["pull", "--unshallow"]);
+    let line = line_for(&stdout, "shallow");
+    assert!(line.contains("(unshallowed)"), "{}", line);
+
+    let stdout = super_stdout(&super_repo, &["pull", "--unshallow"]);
+    let line = line_for(&stdout, "shallow");
+    assert!(line.contains("(already complete)"), "{}", line);
+}
+
+#[test]
+fn pull_uses_the_default_branch_of_the_remote_without_a_configured_branch() {
+    let tmp = tempfile::tempdir().unwrap();
+    let remote = tmp.path().join("remote");
+    let super_repo = tmp.path().join("super");
+    create_remote(&remote);
+    git(&remote, &["branch", "--move", "master", "main"]);
+    create_super_repo(&super_repo);
+    git(
+        &super_repo,
+        &[
+            "submodule",
+            "--quiet",
+            "add",
+            remote.to_str().unwrap(),
+            "sub",
+        ],
+    );
+    let sub = super_repo.join("sub");
+    git(&sub, &["checkout", "--quiet", "main"]);
+    // Without origin/HEAD, super has to ask the remote for its default branch
+    git(&sub, &["remote", "set-head", "origin", "--delete"]);
+    commit_file(&remote, "second");
+
+    let stdout = super_stdout(&super_repo, &["pull"]);
+
+    assert!(
+        line_for(&stdout, "sub").contains("updated"),
+        "unexpected output: {}",
+        stdout
+    );
+    assert_eq!(head_sha(&sub), head_sha(&remote));
+}
+
+#[test]
+fn pull_fast_forwards_to_the_tracked_branch_and_not_the_default_branch() {
+    let (_tmp, remote, super_repo) = super_repo_with(&["sub"]);
+    git(&remote, &["branch", "release"]);
+    git(
+        &super_repo,
+        &[
+            "config",
+            "-f",
+            ".gitmodules",
+            "submodule.sub.branch",
+            "release",
+        ],
+    );
+    let sub = super_repo.join("sub");
+    git(&sub, &["fetch", "--quiet"]);
+    git(&sub, &["checkout", "--quiet", "release"]);
+
+    // Both branches move, and origin/HEAD points to master
+    git(&remote, &["checkout", "--quiet", "release"]);
+    commit_file(&remote, "fix");
+    let release = head_sha(&remote);
+    git(&remote, &["checkout", "--quiet", "master"]);
+    commit_file(&remote, "second");
+
+    let stdout = super_stdout(&super_repo, &["pull"]);
+
+    assert!(
+        line_for(&stdout, "sub").contains("updated"),
+        "unexpected output: {}",
+        stdout
+    );
+    assert_eq!(head_sha(&sub), release);
+}
+
+#[test]
+fn pull_only_updates_the_repos_from_repos_from() {
+    let (tmp, remote, super_repo) = super_repo_with(&["alpha", "beta"]);
+    commit_file(&remote, "second");
+
+    let list = tmp.path().join("repos.txt");
+    std::fs::write(&list, "# Only the repos I work on\n\nalpha\n").unwrap();
+    let stdout = super_stdout(
+        &super_repo,
+        &["pull", "--repos-from", list.to_str().unwrap()],
+    );
+    assert!(
+        line_for(&stdout, "alpha").contains("updated"),
+        "unexpected output: {}",
+        stdout
+    );
+    assert!(!stdout.contains("beta"), "unexpected output: {}", stdout);
+
+    std::fs::write(&list, "alpha\ngamma\n").unwrap();
+    let stdout = super_stdout(
+        &super_repo,
+        &["pull", "--repos-from", list.to_str().unwrap()],
+    );
+    assert!(
+        stdout.contains("line 2: gamma"),
+        "unexpected output: {}",
+        stdout
+    );
+}
+
+#[test]
+fn pull_exits_with_an_error_if_a_repo_failed() {
+    let (_tmp, _remote, super_repo) = super_repo_with(&["deleted", "kept"]);
+
+    let output = run_super(&super_repo, &["pull"]);
+    assert!(output.status.success());
+
+    std::fs::remove_dir_all(super_repo.join("deleted")).unwrap();
+
+    let output = run_super(&super_repo, &["pull"]);
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert_eq!(output.status.code(), Some(1));
+    assert!(
+        stdout.contains("Failed to pull 1: deleted"),
+        "unexpected output: {}",
+        stdout
+    );
+}
+
+#[test]
+fn pull_sets_the_upstream_of_branches_without_one() {
+    let (_tmp, _remote, super_repo) = super_repo_with(&["alpha", "beta"]);
+    git(&super_repo.join("alpha"), &["branch", "--unset-upstream"]);
+
+    let stdout = super_stdout(&super_repo, &["pull", "--set-upstream"]);
+
+    assert!(
+        line_for(&stdout, "alpha").contains("(upstream set to origin/master)"),
+        "unexpected output: {}",
+        stdout
+    );
+    // beta already had an upstream
+    assert!(
+        !line_for(&stdout, "beta").contains("upstream set"),
+        "unexpected output: {}",
+        stdout
+    );
+    let upstream = git(
+        &super_repo.join("alpha"),
+        &["rev-parse", "--abbrev-ref", "master@{upstream}"],
+    );
+    assert_eq!(upstream.trim(), "origin/master");
+}
+
+#[test]
+fn pull_tells_detached_submodules_at_the_recorded_commit_from_drifted_ones() {
+    let (_tmp, _remote, super_repo) = super_repo_with(&["alpha", "beta"]);
+    git(&super_repo, &["commit", "--quiet", "-m", "Add submodules"]);
+
+    let alpha = super_repo.join("alpha");
+    let beta = super_repo.join("beta");
+    git(&alpha, &["checkout", "--quiet", "--detach"]);
+    commit_file(&beta, "local");
+    git(&beta, &["checkout", "--quiet", "--detach"]);
+
+    let stdout = super_stdout(&super_repo, &["pull"]);
+
+    let alpha_line = line_for(&stdout, "alpha");
+    assert!(alpha_line.contains("pinned"), "{}", alpha_line);
+    let beta_line = line_for(&stdout, "beta");
+    assert!(beta_line.contains("detached"), "{}", beta_line);
+    assert!(beta_line.contains("(drifted)"), "{}", beta_line);
+}
+
+#[test]
+fn pull_serial_pulls_the_repos_in_order() {
+    let (_tmp, _remote, super_repo) = super_repo_with(&["beta", "alpha"]);
+
+    let output = run_super(&super_repo, &["pull", "--serial"]);
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let stderr = String::from_utf8_lossy(&output.stderr);
+
+    // The order of .gitmodules, not the alphabetical one
+    let beta = stdout.find("beta").unwrap();
+    let alpha = stdout.find("alpha").unwrap();
+    assert!(beta < alpha, "unexpected output: {}", stdout);
+    assert!(
+        stderr.contains("[1/2] beta"),
+        "unexpected stderr: {}",
+        stderr
+    );
+    assert!(
+        stderr.contains("[2/2] alpha"),
+        "unexpected stderr: {}",
+        stderr
+    );
+}
+
+#[test]
+fn pull_only_changed_remote_skips_the_fetch_of_unchanged_repos() {
+    let tmp = tempfile::tempdir().unwrap();
+    let alpha = tmp.path().join("alpha");
+    let beta = tmp.path().join("beta");
+    let super_repo = tmp.path().join("super");
+    create_remote(&alpha);
+    create_remote(&beta);
+    create_super_repo(&super_repo);
+    add_submodule(&super_repo, &alpha, "alpha");
+    add_submodule(&super_repo, &beta, "beta");
+    commit_file(&alpha, "second");
+
+    let stdout = super_stdout(&super_repo, &["pull", "--only-changed-remote"]);
+
+    let alpha_line = line_for(&stdout, "alpha");
+    assert!(alpha_line.contains("updated"), "{}", alpha_line);
+    assert!(!alpha_line.contains("no remote change"), "{}", alpha_line);
+    let beta_line = line_for(&stdout, "beta");
+    assert!(beta_line.contains("up to date"), "{}", beta_line);
+    assert!(beta_line.contains("(no remote change)"), "{}", beta_line);
+}
+
+#[test]
+fn pull_explains_why_there_is_nothing_to_pull() {
+    let tmp = tempfile::tempdir().unwrap();
+    let remote = tmp.path().join("remote");
+    let origin = tmp.path().join("origin");
+    let clone = tmp.path().join("clone");
+    create_remote(&remote);
+    create_super_repo(&origin);
+
+    let stdout = super_stdout(&origin, &["pull"]);
+    assert!(
+        stdout.contains("No submodules found in this super repo, add one with 'super add <repo>'"),
+        "unexpected output: {}",
+        stdout
+    );
+
+    add_submodule(&origin, &remote, "alpha");
+    git(&origin, &["commit", "--quiet", "-m", "Add alpha"]);
+    git(
+        tmp.path(),
+        &["clone", "--quiet", origin.to_str().unwrap(), "clone"],
+    );
+
+    let stdout = super_stdout(&clone, &["pull"]);
+    assert!(
+        stdout.contains("None of the 1 submodules are initialized, run 'super pull --init'"),
+        "unexpected output: {}",
+        stdout
+    );
+}
+
+#[test]
+fn pull_reports_repos_with_a_leftover_lock_file() {
+    let (_tmp, remote, super_repo) = super_repo_with(&["alpha"]);
+    let alpha = super_repo.join("alpha");
+    let before = head_sha(&alpha);
+    commit_file(&remote, "second");
+
+    let git_dir = git(&alpha, &["rev-parse", "--absolute-git-dir"]);
+    let lock = std::path::Path::new(git_dir.trim()).join("index.lock");
+    std::fs::write(&lock, "").unwrap();
+
+    let stdout = super_stdout(&super_repo, &["pull"]);
+    assert!(
+        line_for(&stdout, "alpha").contains("locked"),
+        "unexpected output: {}",
+        stdout
+    );
+    assert_eq!(head_sha(&alpha), before);
+
+    // Only locks that are old enough to be stale are removed
+    let stdout = super_stdout(&super_repo, &["pull", "--force-unlock"]);
+    assert!(
+        line_for(&stdout, "alpha").contains("locked"),
+        "unexpected output: {}",
+        stdout
+    );
+
+    let an_hour_ago = std::time::SystemTime::now() - std::time::Duration::from_secs(3600);
+    std::fs::File::options()
+        .write(true)
+        .open(&lock)
+        .unwrap()
+        .set_modified(an_hour_ago)
+        .unwrap();
+    let stdout = super_stdout(&super_repo, &["pull", "--force-unlock"]);
+    assert!(
+        line_for(&stdout, "alpha").contains("updated"),
+        "unexpected output: {}",
+        stdout
+    );
+    assert!(!lock.exists());
+}
+
+#[test]
+fn pull_writes_a_report_of_the_new_commits() {
+    let (tmp, remote, super_repo) = super_repo_with(&["alpha"]);
+    let report = tmp.path().join("report.md");
+    let before = head_sha(&super_repo.join("alpha"));
+    commit_file(&remote, "second");
+    let after = head_sha(&remote);
+    // The report overwrites the file
+    std::fs::write(&report, "old notes").unwrap();
+
+    let stdout = super_stdout(&super_repo, &["pull", "--report", report.to_str().unwrap()]);
+    assert!(
+        stdout.contains("Report written to"),
+        "unexpected output: {}",
+        stdout
+    );
+
+    let report = std::fs::read_to_string(&report).unwrap();
+    assert!(
+        !report.contains("old notes"),
+        "unexpected report: {}",
+        report
+    );
+    let row = format!("| alpha | updated | {} | {} |", &before[..7], &after[..7]);
+    assert!(report.contains(&row), "unexpected report: {}", report);
+    let commit = format!("- `{}` second", &after[..7]);
+    assert!(report.contains(&commit), "unexpected report: {}", report);
+}
+
+#[test]
+fn pull_warns_about_uncommitted_submodule_changes_in_the_super_repo() {
+    let (_tmp, _remote, super_repo) = super_repo_with(&["alpha", "beta"]);
+    git(&super_repo, &["commit", "--quiet", "-m", "Add submodules"]);
+
+    // A local commit moves alpha, while beta only has changes in its working tree
+    commit_file(&super_repo.join("alpha"), "local");
+    std::fs::write(super_repo.join("beta").join("file.txt"), "changed").unwrap();
+
+    let output = run_super(&super_repo, &["pull"]);
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(
+        stderr.contains("uncommitted changes to the commits of 1: alpha"),
+        "unexpected stderr: {}",
+        stderr
+    );
+    assert!(
+        stderr.contains("super commit"),
+        "unexpected stderr: {}",
+        stderr
+    );
+}
+
+#[test]
+fn verbose_pull_shows_how_long_each_repo_took() {
+    let (_tmp, _remote, super_repo) = super_repo_with(&["alpha", "beta"]);
+
+    let stdout = super_stdout(&super_repo, &["-v", "pull"]);
+    assert!(
+        line_for(&stdout, "alpha").trim_end().ends_with("s)"),
+        "unexpected output: {}",
+        stdout
+    );
+    assert!(
+        stdout.contains("Slowest repos: "),
+        "unexpected output: {}",
+        stdout
+    );
+
+    // Without -v, nothing is measured
+    let stdout = super_stdout(&super_repo, &["pull"]);
+    assert!(
+        !stdout.contains("Slowest repos"),
+        "unexpected output: {}",
+        stdout
+    );
+}
+
+#[test]
+fn pull_fails_instead_of_waiting_for_a_remote_that_asks_for_a_password() {
+    let (tmp, _remote, super_repo) = super_repo_with(&["private"]);
+    let private = super_repo.join("private");
+    git(
+        &private,
+        &[
+            "remote",
+            "set-url",
+            "origin",
+            "ssh://git@example.com/private",
+        ],
+    );
+
+    // A stand-in for ssh, that waits for a passphrase unless it runs in batch mode
+    let bin = tmp.path().join("bin");
+    std::fs::create_dir_all(&bin).unwrap();
+    let ssh = bin.join("ssh");
+    std::fs::write(
+        &ssh,
+        concat!(
+            "#!/bin/sh\n",
+            "case \"$*\" in *BatchMode=yes*) ;; *) sleep 30 ;; esac\n",
+            "echo 'Permission denied (publickey).' >&2\n",
+            "exit 255\n",
+        ),
+    )
+    .unwrap();
+    std::fs::set_permissions(&ssh, std::fs::Permissions::from_mode(0o755)).unwrap();
+    let path = format!("{}:{}", bin.display(), std::env::var("PATH").unwrap());
+
+    let started = std::time::Instant::now();
+    let output = super_command(&super_repo)
+        .env("PATH", path)
+        .env_remove("GIT_SSH")
+        .env_remove("GIT_SSH_COMMAND")
+        .arg("pull")
+        .output()
+        .unwrap();
+    let stdout = String::from_utf8_lossy(&output.stdout);
+
+    assert!(
+        started.elapsed().as_secs() < 20,
+        "pull waited for the passphrase"
+    );
+    assert_eq!(output.status.code(), Some(1));
+    assert!(
+        line_for(&stdout, "private").contains("failed"),
+        "unexpected output: {}",
+        stdout
+    );
+}
@@ -0,0 +1,65 @@
+//! Tests for 'super reset'
+
+mod common;
+
+use common::{commit_file, git, line_for, super_repo_with, super_stdout};
+
+#[test]
+fn reset_discards_local_commits_and_changes() {
+    let (_tmp, _remote, super_repo) = super_repo_with(&["alpha", "beta"]);
+    let alpha = super_repo.join("alpha");
+    let origin = git(&alpha, &["rev-parse", "origin/master"]);
+    commit_file(&alpha, "local");
+    std::fs::write(alpha.join("file.txt"), "changed").unwrap();
+
+    // Without --yes, there is nobody to confirm in a test, so nothing is reset
+    let stdout = super_stdout(&super_repo, &["reset", "--hard"]);
+    assert!(
+        line_for(&stdout, "alpha").contains("discarding 1 ahead, 1 changed"),
+        "unexpected output: {}",
+        stdout
+    );
+    assert_ne!(git(&alpha, &["rev-parse", "HEAD"]), origin);
+
+    // Every repo is listed before it is reset, so the outcome is the last line for it
+    let stdout = super_stdout(&super_repo, &["reset", "--hard", "--yes"]);
+    let last = stdout.lines().filter(|line| line.contains("alpha")).last();
+    assert!(
+        last.is_some_and(|line| line.contains("reset") && !line.contains("will reset")),
+        "unexpected output: {}",
+        stdout
+    );
+    assert!(
+        line_for(&stdout, "beta").contains("unchanged"),
+        "unexpected output: {}",
+        stdout
+    );
+    assert_eq!(git(&alpha, &["rev-parse", "HEAD"]), origin);
+    assert_eq!(git(&alpha, &["status", "--porcelain"]), "");
+}
+
+#[test]
+fn reset_skips_detached_heads_unless_forced() {
+    let (_tmp, _remote, super_repo) = super_repo_with(&["alpha"]);
+    let alpha = super_repo.join("alpha");
+    git(&alpha, &["checkout", "--quiet", "--detach"]);
+    std::fs::write(alpha.join("file.txt"), "changed").unwrap();
+
+    let stdout = super_stdout(&super_repo, &["reset", "--hard", "--yes"]);
+    assert!(
+        line_for(&stdout, "alpha").contains("detached HEAD"),
+        "unexpected output: {}",
+        stdout
+    );
+    assert_eq!(
+        std::fs::read_to_string(alpha.join("file.txt")).unwrap(),
+        "changed"
+    );
+
+    super_stdout(&super_repo, &["reset", "--hard", "--force", "--yes"]);
+    assert_eq!(
+        git(&alpha, &["rev-parse", "--abbrev-ref", "HEAD"]),
+        "master\n"
+    );
+    assert_eq!(git(&alpha, &["status", "--porcelain"]), "");
+}
@@ -0,0 +1,47 @@
+//! Tests for running super outside of the super repo, with --root or SUPER_ROOT
+
+mod common;
+
+use common::{super_command, super_repo_with};
+
+#[test]
+fn super_root_selects_the_super_repo() {
+    let (tmp, _remote, super_repo) = super_repo_with(&["sub"]);
+    let elsewhere = tmp.path().join("elsewhere");
+    std::fs::create_dir(&elsewhere).unwrap();
+
+    let output = super_command(&elsewhere)
+        .env("SUPER_ROOT", &super_repo)
+        .args(["status"])
+        .output()
+        .unwrap();
+    let stdout = String::from_utf8_lossy(&output.stdout);
+
+    assert!(stdout.contains("sub"), "unexpected output: {}", stdout);
+    assert!(stdout.contains("clean"), "unexpected output: {}", stdout);
+}
+
+#[test]
+fn root_flag_takes_precedence_over_super_root() {
+    let (tmp, _remote, super_repo) = super_repo_with(&["sub"]);
+
+    let output = super_command(tmp.path())
+        .env("SUPER_ROOT", tmp.path().join("missing"))
+        .args(["--root", super_repo.to_str().unwrap(), "status"])
+        .output()
+        .unwrap();
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.contains("sub"), "unexpected output: {}", stdout);
+
+    let output = super_command(tmp.path())
+        .env("SUPER_ROOT", tmp.path().join("missing"))
+        .args(["status"])
+        .output()
+        .unwrap();
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(
+        stdout.contains("is not a directory"),
+        "unexpected output: {}",
+        stdout
+    );
+}
@@ -0,0 +1,72 @@
+//! Tests for 'super stash'
+
+mod common;
+
+use common::{commit_file, git, line_for, super_repo_with, super_stdout};
+
+#[test]
+fn stash_and_pop_only_touch_repos_with_changes() {
+    let (_tmp, _remote, super_repo) = super_repo_with(&["alpha", "beta"]);
+    let alpha = super_repo.join("alpha");
+    std::fs::write(alpha.join("file.txt"), "changed").unwrap();
+    std::fs::write(super_repo.join("beta").join("new.txt"), "untracked").unwrap();
+
+    // Untracked files are only stashed with --include-untracked
+    let stdout = super_stdout(&super_repo, &["stash"]);
+    assert!(
+        line_for(&stdout, "alpha").contains("stashed"),
+        "unexpected output: {}",
+        stdout
+    );
+    assert!(
+        line_for(&stdout, "beta").contains("nothing to stash"),
+        "unexpected output: {}",
+        stdout
+    );
+    assert_eq!(
+        std::fs::read_to_string(alpha.join("file.txt")).unwrap(),
+        "first"
+    );
+
+    let stdout = super_stdout(&super_repo, &["stash", "pop"]);
+    assert!(
+        line_for(&stdout, "alpha").contains("popped"),
+        "unexpected output: {}",
+        stdout
+    );
+    assert!(
+        line_for(&stdout, "beta").contains("nothing to pop"),
+        "unexpected output: {}",
+        stdout
+    );
+    assert_eq!(
+        std::fs::read_to_string(alpha.join("file.txt")).unwrap(),
+        "changed"
+    );
+}
+
+#[test]
+fn stash_pop_keeps_the_stash_entry_on_conflicts() {
+    let (_tmp, _remote, super_repo) = super_repo_with(&["alpha"]);
+    let alpha = super_repo.join("alpha");
+    std::fs::write(alpha.join("file.txt"), "stashed").unwrap();
+
+    super_stdout(&super_repo, &["stash"]);
+    commit_file(&alpha, "committed");
+
+    let stdout = super_stdout(&super_repo, &["stash", "pop"]);
+    assert!(
+        line_for(&stdout, "alpha").contains("conflict"),
+        "unexpected output: {}",
+        stdout
+    );
+    assert!(
+        stdout.contains("Conflicts in 1: alpha"),
+        "unexpected output: {}",
+        stdout
+    );
+    assert!(
+        git(&alpha, &["stash", "list"]).contains("super stash"),
+        "the stash entry is gone"
+    );
+}
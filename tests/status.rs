@@ -0,0 +1,260 @@
+//! Tests for 'super status'
+
+mod common;
+
+use common::{
+    add_submodule, commit_file, create_remote, git, head_sha, line_for, super_command,
+    super_repo_with, super_stdout,
+};
+
+#[test]
+fn status_filters_repos_by_uncommitted_changes() {
+    let (_tmp, _remote, super_repo) = super_repo_with(&["alpha", "beta"]);
+    std::fs::write(super_repo.join("alpha").join("file.txt"), "changed").unwrap();
+
+    let stdout = super_stdout(&super_repo, &["status", "--filter", "dirty"]);
+    assert!(
+        line_for(&stdout, "alpha").contains("dirty"),
+        "unexpected output: {}",
+        stdout
+    );
+    assert!(!stdout.contains("beta"), "unexpected output: {}", stdout);
+    assert!(
+        stdout.contains("Filtered out 1 repos"),
+        "unexpected output: {}",
+        stdout
+    );
+
+    // --repos and --filter together select the repos that match both
+    let stdout = super_stdout(
+        &super_repo,
+        &["status", "--repos", "alpha", "--filter", "clean"],
+    );
+    assert!(!stdout.contains("alpha"), "unexpected output: {}", stdout);
+    assert!(!stdout.contains("beta"), "unexpected output: {}", stdout);
+}
+
+#[test]
+fn status_shows_ahead_behind_after_fetching() {
+    let (_tmp, remote, super_repo) = super_repo_with(&["tracking", "detached"]);
+    git(
+        &super_repo.join("detached"),
+        &["checkout", "--quiet", "--detach"],
+    );
+    commit_file(&remote, "second");
+
+    // Without --fetch, the new commit of the remote is not known yet
+    let stdout = super_stdout(&super_repo, &["status", "--ahead-behind"]);
+    assert!(
+        line_for(&stdout, "tracking").contains("↑0 ↓0"),
+        "unexpected output: {}",
+        stdout
+    );
+    assert!(
+        line_for(&stdout, "detached").contains('—'),
+        "unexpected output: {}",
+        stdout
+    );
+
+    let stdout = super_stdout(&super_repo, &["status", "--fetch"]);
+    assert!(
+        line_for(&stdout, "tracking").contains("↑0 ↓1"),
+        "unexpected output: {}",
+        stdout
+    );
+}
+
+#[test]
+fn color_always_overrides_no_color_and_never_wins_over_everything() {
+    let (_tmp, _remote, super_repo) = super_repo_with(&["sub"]);
+
+    // stdout is a pipe here, so the output isn't colored by default
+    let stdout = super_stdout(&super_repo, &["status"]);
+    assert!(!stdout.contains('\x1b'), "unexpected output: {}", stdout);
+
+    let output = super_command(&super_repo)
+        .env("NO_COLOR", "1")
+        .args(["--color", "always", "status"])
+        .output()
+        .unwrap();
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.contains('\x1b'), "unexpected output: {}", stdout);
+
+    let stdout = super_stdout(&super_repo, &["--color", "never", "status"]);
+    assert!(!stdout.contains('\x1b'), "unexpected output: {}", stdout);
+}
+
+#[test]
+fn porcelain_prints_one_tab_separated_line_per_repo() {
+    let (_tmp, remote, super_repo) = super_repo_with(&["alpha", "beta"]);
+    std::fs::write(super_repo.join("alpha").join("file.txt"), "changed").unwrap();
+    git(
+        &super_repo.join("beta"),
+        &["checkout", "--quiet", "--detach"],
+    );
+
+    let stdout = super_stdout(&super_repo, &["status", "--porcelain"]);
+    let sha = &head_sha(&remote)[..7];
+
+    let mut lines: Vec<&str> = stdout.lines().collect();
+    lines.sort();
+    assert_eq!(
+        lines,
+        vec![
+            format!("alpha\tdirty\tmaster\t0\t0\t{}", sha),
+            format!("beta\tclean\t-\t-\t-\t{}", sha),
+        ]
+    );
+}
+
+#[test]
+fn status_reports_submodules_that_are_not_initialized() {
+    let (_tmp, _remote, super_repo) = super_repo_with(&["alpha", "beta"]);
+    git(
+        &super_repo,
+        &["submodule", "--quiet", "deinit", "--force", "beta"],
+    );
+
+    // In the empty directory of beta, git would find the super repo instead
+    let stdout = super_stdout(&super_repo, &["status"]);
+    let line = line_for(&stdout, "beta");
+    assert!(line.contains("missing"), "unexpected output: {}", stdout);
+    assert!(
+        line.contains("not initialized, run 'super pull --init'"),
+        "unexpected output: {}",
+        stdout
+    );
+    assert!(
+        line_for(&stdout, "alpha").contains("clean"),
+        "unexpected output: {}",
+        stdout
+    );
+
+    let stdout = super_stdout(&super_repo, &["status", "--porcelain"]);
+    assert!(
+        stdout
+            .lines()
+            .any(|line| line == "beta\tfailed\t-\t-\t-\t-"),
+        "unexpected output: {}",
+        stdout
+    );
+}
+
+#[test]
+fn status_fetch_shows_what_pull_would_do() {
+    let (tmp, moved, super_repo) = super_repo_with(&["fast", "dirty", "forked"]);
+    let still = tmp.path().join("still");
+    create_remote(&still);
+    add_submodule(&super_repo, &still, "local");
+    add_submodule(&super_repo, &still, "same");
+    commit_file(&moved, "second");
+    std::fs::write(super_repo.join("dirty").join("file.txt"), "changed").unwrap();
+    commit_file(&super_repo.join("forked"), "forked");
+    commit_file(&super_repo.join("local"), "local");
+    let before = head_sha(&super_repo.join("fast"));
+
+    let stdout = super_stdout(&super_repo, &["status", "--fetch"]);
+    for (repo, divergence) in [
+        ("fast", "can-ff"),
+        ("dirty", "behind"),
+        ("forked", "diverged"),
+        ("local", "ahead"),
+        ("same", "up to date"),
+    ] {
+        assert!(
+            line_for(&stdout, repo).ends_with(divergence),
+            "unexpected output: {}",
+            stdout
+        );
+    }
+    assert!(
+        stdout.contains("1 can-ff, 1 behind, 1 ahead, 1 diverged, 1 up to date"),
+        "unexpected output: {}",
+        stdout
+    );
+
+    // Nothing but the remote-tracking branches changed
+    assert_eq!(head_sha(&super_repo.join("fast")), before);
+}
+
+#[test]
+fn super_without_a_command_runs_the_default_command_from_superconfig() {
+    let (_tmp, _remote, super_repo) = super_repo_with(&["alpha"]);
+
+    let stdout = super_stdout(&super_repo, &[]);
+    assert!(
+        line_for(&stdout, "alpha").contains("clean"),
+        "unexpected output: {}",
+        stdout
+    );
+
+    let config = "[super]\n\tdefaultCommand = list --paths\n";
+    std::fs::write(super_repo.join(".superconfig"), config).unwrap();
+    let stdout = super_stdout(&super_repo, &[]);
+    assert_eq!(stdout, "alpha\n");
+}
+
+#[test]
+fn status_ends_with_a_footer_that_counts_the_repos_in_each_state() {
+    let (_tmp, _remote, super_repo) = super_repo_with(&["alpha", "beta", "gamma", "delta"]);
+    std::fs::write(super_repo.join("beta").join("file.txt"), "changed").unwrap();
+    git(
+        &super_repo.join("gamma"),
+        &["checkout", "--quiet", "--detach"],
+    );
+
+    let stdout = super_stdout(&super_repo, &["status"]);
+    assert_eq!(
+        stdout.lines().last(),
+        Some("4 repos: 2 clean, 1 detached, 1 dirty"),
+        "unexpected output: {}",
+        stdout
+    );
+}
+
+#[test]
+fn status_prints_a_summary_line_to_stderr() {
+    let (_tmp, _remote, super_repo) = super_repo_with(&["alpha", "beta"]);
+    std::fs::write(super_repo.join("alpha").join("file.txt"), "changed").unwrap();
+
+    // Repos that the filter leaves out are still counted
+    let output = super_command(&super_repo)
+        .args(["status", "--filter", "dirty"])
+        .output()
+        .unwrap();
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(
+        stderr.contains("super: total=2 clean=1 dirty=1 failed=0"),
+        "unexpected stderr: {}",
+        stderr
+    );
+}
+
+#[test]
+fn status_index_tells_worktree_changes_and_staged_pointers_apart() {
+    let (_tmp, _remote, super_repo) = super_repo_with(&["alpha", "beta", "gamma"]);
+    git(&super_repo, &["commit", "--quiet", "-m", "Add submodules"]);
+
+    // alpha is moved and staged, beta is moved but not staged, gamma only has local changes
+    commit_file(&super_repo.join("alpha"), "alpha");
+    git(&super_repo, &["add", "alpha"]);
+    commit_file(&super_repo.join("beta"), "beta");
+    std::fs::write(super_repo.join("gamma").join("file.txt"), "changed").unwrap();
+
+    let stdout = super_stdout(&super_repo, &["status", "--index"]);
+    assert!(
+        line_for(&stdout, "alpha").contains("worktree: clean, pointer: staged"),
+        "unexpected output: {}",
+        stdout
+    );
+    assert!(
+        line_for(&stdout, "beta").contains("worktree: clean, pointer: modified-not-staged"),
+        "unexpected output: {}",
+        stdout
+    );
+    assert!(
+        line_for(&stdout, "gamma").contains("worktree: dirty, pointer: unchanged"),
+        "unexpected output: {}",
+        stdout
+    );
+}
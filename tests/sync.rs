@@ -0,0 +1,125 @@
+//! Tests for 'super sync'
+
+mod common;
+
+use common::{commit_file, git, head_sha, line_for, super_repo_with, super_stdout};
+
+#[test]
+fn sync_clones_and_updates_submodules() {
+    let (tmp, remote, super_repo) = super_repo_with(&["alpha", "beta"]);
+    let clone = tmp.path().join("clone");
+    git(&super_repo, &["commit", "--quiet", "-m", "Add submodules"]);
+    git(
+        tmp.path(),
+        &["clone", "--quiet", super_repo.to_str().unwrap(), "clone"],
+    );
+
+    // The clone has none of the submodules yet
+    let stdout = super_stdout(&clone, &["sync"]);
+    assert!(
+        line_for(&stdout, "alpha").contains("cloned"),
+        "unexpected output: {}",
+        stdout
+    );
+    assert!(
+        line_for(&stdout, "beta").contains("cloned"),
+        "unexpected output: {}",
+        stdout
+    );
+    assert!(clone.join("beta").join("file.txt").exists());
+
+    // Move alpha forward in the super repo, and pull that into the clone
+    commit_file(&remote, "second");
+    git(&super_repo.join("alpha"), &["pull", "--quiet"]);
+    git(&super_repo, &["commit", "--quiet", "-am", "Update alpha"]);
+    git(&clone, &["pull", "--quiet"]);
+
+    let stdout = super_stdout(&clone, &["sync"]);
+    assert!(
+        line_for(&stdout, "alpha").contains("updated"),
+        "unexpected output: {}",
+        stdout
+    );
+    assert!(
+        line_for(&stdout, "beta").contains("unchanged"),
+        "unexpected output: {}",
+        stdout
+    );
+    assert_eq!(head_sha(&clone.join("alpha")), head_sha(&remote));
+}
+
+#[test]
+fn sync_reports_failed_clones_and_synced_urls() {
+    let (tmp, remote, super_repo) = super_repo_with(&["alpha", "beta"]);
+    let mirror = tmp.path().join("mirror");
+    let clone = tmp.path().join("clone");
+    git(
+        tmp.path(),
+        &["clone", "--quiet", remote.to_str().unwrap(), "mirror"],
+    );
+    git(&super_repo, &["commit", "--quiet", "-m", "Add submodules"]);
+    git(
+        tmp.path(),
+        &["clone", "--quiet", super_repo.to_str().unwrap(), "clone"],
+    );
+
+    // A repo that can't be cloned doesn't keep the others from being cloned
+    let missing = tmp.path().join("missing");
+    git(
+        &clone,
+        &[
+            "config",
+            "-f",
+            ".gitmodules",
+            "submodule.alpha.url",
+            missing.to_str().unwrap(),
+        ],
+    );
+    let stdout = super_stdout(&clone, &["sync"]);
+    assert!(
+        line_for(&stdout, "alpha").contains("failed"),
+        "unexpected output: {}",
+        stdout
+    );
+    assert!(
+        line_for(&stdout, "beta").contains("cloned"),
+        "unexpected output: {}",
+        stdout
+    );
+    assert!(
+        stdout.contains("Failed to sync 1: alpha"),
+        "unexpected output: {}",
+        stdout
+    );
+
+    // The URL of beta changes in the super repo, and the one of alpha is fixed again
+    git(
+        &super_repo,
+        &[
+            "config",
+            "-f",
+            ".gitmodules",
+            "submodule.beta.url",
+            mirror.to_str().unwrap(),
+        ],
+    );
+    git(&super_repo, &["commit", "--quiet", "-am", "Move beta"]);
+    git(&clone, &["checkout", "--quiet", ".gitmodules"]);
+    git(&clone, &["pull", "--quiet"]);
+
+    let stdout = super_stdout(&clone, &["sync"]);
+    assert!(
+        line_for(&stdout, "alpha").contains("cloned"),
+        "unexpected output: {}",
+        stdout
+    );
+    assert!(
+        line_for(&stdout, "beta").contains("synced-url"),
+        "unexpected output: {}",
+        stdout
+    );
+    assert_eq!(
+        git(&clone.join("beta"), &["remote", "get-url", "origin"]).trim(),
+        mirror.to_str().unwrap()
+    );
+}
@@ -0,0 +1,36 @@
+//! Tests for 'super tag'
+
+mod common;
+
+use common::{commit_file, git, head_sha, line_for, super_repo_with, super_stdout};
+
+#[test]
+fn tag_tags_all_repos_and_leaves_existing_tags_alone() {
+    let (_tmp, remote, super_repo) = super_repo_with(&["alpha", "beta"]);
+    let beta = super_repo.join("beta");
+    git(&beta, &["tag", "v1.0", "HEAD"]);
+    commit_file(&beta, "second");
+
+    let stdout = super_stdout(&super_repo, &["tag", "v1.0", "-m", "Release 1.0", "--push"]);
+    assert!(
+        line_for(&stdout, "alpha").contains("tagged"),
+        "unexpected output: {}",
+        stdout
+    );
+    assert!(
+        line_for(&stdout, "beta").contains("tag already exists"),
+        "unexpected output: {}",
+        stdout
+    );
+    // The tag is annotated, and was pushed to the remote
+    let alpha = super_repo.join("alpha");
+    assert_eq!(git(&alpha, &["cat-file", "-t", "v1.0"]), "tag\n");
+    assert_eq!(git(&remote, &["tag", "--list"]), "v1.0\n");
+
+    // --force moves the existing tag to the current commit
+    super_stdout(&super_repo, &["tag", "v1.0", "--force"]);
+    assert_eq!(
+        git(&beta, &["rev-parse", "v1.0^{commit}"]).trim(),
+        head_sha(&beta)
+    );
+}
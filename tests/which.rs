@@ -0,0 +1,62 @@
+//! Tests for 'super which'
+
+mod common;
+
+use common::super_command;
+use std::os::unix::fs::PermissionsExt;
+use std::path::Path;
+
+/// Install an executable custom command in the commands directory of the given home
+fn install_command(home: &Path, name: &str) {
+    let commands_dir = home.join(".config/super/commands");
+    std::fs::create_dir_all(&commands_dir).unwrap();
+
+    let command = commands_dir.join(name);
+    std::fs::write(&command, "#!/bin/sh\n").unwrap();
+    std::fs::set_permissions(&command, std::fs::Permissions::from_mode(0o755)).unwrap();
+}
+
+/// Run 'super which' with the given home, and return its stdout
+fn which(home: &Path, name: &str) -> String {
+    let output = super_command(home)
+        .env("HOME", home)
+        .args(["which", name])
+        .output()
+        .unwrap();
+    String::from_utf8_lossy(&output.stdout).to_string()
+}
+
+#[test]
+fn which_tells_built_in_and_custom_commands_apart() {
+    let tmp = tempfile::tempdir().unwrap();
+    let home = tmp.path().join("home");
+    std::fs::create_dir(&home).unwrap();
+
+    // Without a commands directory, there are only built-in commands
+    let stdout = which(&home, "pull");
+    assert_eq!(stdout, "pull is a built-in command\n");
+
+    install_command(&home, "deploy");
+    install_command(&home, "status");
+
+    let stdout = which(&home, "deploy");
+    let expected = home.join(".config/super/commands/deploy");
+    assert_eq!(
+        stdout,
+        format!("deploy is a custom command: {}\n", expected.display())
+    );
+
+    let stdout = which(&home, "status");
+    assert!(
+        stdout.contains("Warning: the custom command") && stdout.contains("is shadowed by it"),
+        "unexpected output: {}",
+        stdout
+    );
+
+    let stdout = which(&home, "missing");
+    assert!(
+        stdout.contains("missing is not a command"),
+        "unexpected output: {}",
+        stdout
+    );
+}